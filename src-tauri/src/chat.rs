@@ -1,4 +1,4 @@
-use crate::fs_commands::validate_url_host;
+use crate::host_allowlist::validate_url_host;
 use futures_util::StreamExt;
 use serde::Deserialize;
 use std::collections::HashMap;
@@ -0,0 +1,977 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Policies live outside the workspace, in `~/.shoulders/<POLICY_DIR>/<key>.json`
+/// where the key is the SHA-256 of the canonical workspace path, so nothing the
+/// AI can write in the workspace (or git can check out) changes them. The
+/// sandbox protects this folder from the filesystem commands.
+const POLICY_DIR: &str = "command-policies";
+/// Append-only decision log (one JSON object per line), relative to the workspace
+/// root, or to `~/.shoulders` for commands outside every workspace.
+const LOG_FILE: &str = "command-log.jsonl";
+/// A log past this size is moved to `command-log.1.jsonl` (replacing the
+/// previous one) before the next entry is written.
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+/// How deep `bash -c`, `eval` and `$(...)` are unwrapped
+const MAX_NESTING: usize = 8;
+
+/// Ordered by severity: when several commands in one line disagree, the
+/// strictest decision wins.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    Allow,
+    Ask,
+    Deny,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    /// Talks to the network: curl, ssh, git push/pull, package installs
+    Network,
+    /// Hard to undo: rm -r/-f, git push --force, git reset --hard, sudo
+    Destructive,
+    /// Runs code the parser can't see: eval, source, a program name from a variable
+    Dynamic,
+}
+
+/// One rule. Every field that is set must match; a rule with no conditions
+/// matches everything.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PolicyRule {
+    pub action: PolicyAction,
+    /// Glob on the program name (`git`, `R*`)
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Globs that must each match some argument. A single-letter flag such as
+    /// `-f` also matches clusters like `-rf`.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Path glob on the working directory; `${workspace}` expands to the workspace root
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Regex on the full, unparsed command line
+    #[serde(default)]
+    pub pattern: Option<String>,
+    #[serde(default)]
+    pub category: Option<Category>,
+    /// Shown to the user and written to the log
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PolicyConfig {
+    /// Decision for commands no rule matches
+    pub default: PolicyAction,
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+}
+
+impl Default for PolicyConfig {
+    /// Used when the workspace has no policy file: everything runs, except
+    /// destructive and network commands, which need confirmation.
+    fn default() -> Self {
+        let ask = |category: Category| PolicyRule {
+            action: PolicyAction::Ask,
+            command: None,
+            args: Vec::new(),
+            cwd: None,
+            pattern: None,
+            category: Some(category),
+            reason: None,
+        };
+        Self {
+            default: PolicyAction::Allow,
+            rules: vec![ask(Category::Destructive), ask(Category::Network)],
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct PolicyDecision {
+    pub action: PolicyAction,
+    /// Index into `rules` of the rule that decided; `None` for the default
+    pub rule: Option<usize>,
+    pub reason: String,
+    /// Built-in categories detected anywhere in the command line
+    pub categories: Vec<Category>,
+}
+
+// ── Parsing ─────────────────────────────────────────────────────────
+
+/// One simple command after wrappers (`sudo`, `env`, `nohup`, ...) and
+/// leading `VAR=value` assignments are stripped.
+#[derive(Debug, Clone)]
+struct ParsedCommand {
+    program: String,
+    args: Vec<String>,
+    /// Targets of `>`, `>>`, `<` and `&>`
+    redirects: Vec<String>,
+    elevated: bool,
+}
+
+/// One simple command as split from the line, before wrappers are stripped.
+#[derive(Default)]
+struct Segment {
+    words: Vec<String>,
+    redirects: Vec<String>,
+}
+
+impl Segment {
+    fn is_empty(&self) -> bool {
+        self.words.is_empty() && self.redirects.is_empty()
+    }
+}
+
+/// Capture the body of `$(...)` starting at `i` (just past the opening paren).
+/// Returns the body and the index past the closing paren.
+fn capture_parens(chars: &[char], mut i: usize) -> (String, usize) {
+    let start = i;
+    let mut depth = 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '\'' => {
+                i += 1;
+                while i < chars.len() && chars[i] != '\'' {
+                    i += 1;
+                }
+            }
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return (chars[start..i].iter().collect(), i + 1);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    (chars[start..].iter().collect(), chars.len())
+}
+
+fn capture_backticks(chars: &[char], mut i: usize) -> (String, usize) {
+    let start = i;
+    while i < chars.len() && chars[i] != '`' {
+        if chars[i] == '\\' {
+            i += 1;
+        }
+        i += 1;
+    }
+    let end = i.min(chars.len());
+    (chars[start..end].iter().collect(), (i + 1).min(chars.len()))
+}
+
+/// Split a command line into simple commands (on `;`, `&&`, `||`, `|`, `&`,
+/// newlines and subshell parens) and each of those into words, following shell
+/// quoting. Redirection targets are kept apart from the words. The bodies of
+/// `$(...)` and backticks are returned separately so they can be checked as
+/// commands too.
+fn split_words(input: &str) -> (Vec<Segment>, Vec<String>) {
+    let chars: Vec<char> = input.chars().collect();
+    let mut segments = Vec::new();
+    let mut nested = Vec::new();
+    let mut words = Segment::default();
+    let mut cur = String::new();
+    let mut in_word = false;
+    let mut redirect_target = false;
+
+    fn finish_word(cur: &mut String, in_word: &mut bool, redirect_target: &mut bool, words: &mut Segment) {
+        if *in_word {
+            if *redirect_target {
+                *redirect_target = false;
+                words.redirects.push(std::mem::take(cur));
+            } else {
+                words.words.push(std::mem::take(cur));
+            }
+            cur.clear();
+            *in_word = false;
+        }
+    }
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            ' ' | '\t' => finish_word(&mut cur, &mut in_word, &mut redirect_target, &mut words),
+            '&' if chars.get(i + 1) == Some(&'>') => {
+                finish_word(&mut cur, &mut in_word, &mut redirect_target, &mut words);
+                redirect_target = true;
+                i += 2;
+                while chars.get(i) == Some(&'>') {
+                    i += 1;
+                }
+                continue;
+            }
+            '\n' | ';' | '|' | '&' | '(' | ')' => {
+                finish_word(&mut cur, &mut in_word, &mut redirect_target, &mut words);
+                if !words.is_empty() {
+                    segments.push(std::mem::take(&mut words));
+                }
+            }
+            '<' | '>' => {
+                // `2>`: the digits are a file descriptor, not a word
+                if in_word && cur.chars().all(|d| d.is_ascii_digit()) {
+                    cur.clear();
+                    in_word = false;
+                } else {
+                    finish_word(&mut cur, &mut in_word, &mut redirect_target, &mut words);
+                }
+                i += 1;
+                while matches!(chars.get(i), Some('<') | Some('>') | Some('|')) {
+                    i += 1;
+                }
+                match chars.get(i) {
+                    // `2>&1`: duplicating a descriptor has no target
+                    Some('&') => {
+                        i += 1;
+                        while matches!(chars.get(i), Some(d) if d.is_ascii_digit() || *d == '-') {
+                            i += 1;
+                        }
+                    }
+                    // `<(cmd)` process substitution
+                    Some('(') => {
+                        let (body, next) = capture_parens(&chars, i + 1);
+                        nested.push(body);
+                        i = next;
+                    }
+                    _ => redirect_target = true,
+                }
+                continue;
+            }
+            '#' if !in_word => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            '\'' => {
+                in_word = true;
+                i += 1;
+                while i < chars.len() && chars[i] != '\'' {
+                    cur.push(chars[i]);
+                    i += 1;
+                }
+            }
+            '"' => {
+                in_word = true;
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    match chars[i] {
+                        '\\' if matches!(chars.get(i + 1), Some('"') | Some('\\') | Some('$') | Some('`')) => {
+                            cur.push(chars[i + 1]);
+                            i += 1;
+                        }
+                        '$' if chars.get(i + 1) == Some(&'(') => {
+                            let (body, next) = capture_parens(&chars, i + 2);
+                            cur.push_str(&format!("$({})", body));
+                            nested.push(body);
+                            i = next;
+                            continue;
+                        }
+                        '`' => {
+                            let (body, next) = capture_backticks(&chars, i + 1);
+                            cur.push_str(&format!("`{}`", body));
+                            nested.push(body);
+                            i = next;
+                            continue;
+                        }
+                        ch => cur.push(ch),
+                    }
+                    i += 1;
+                }
+            }
+            '\\' => {
+                // Backslash-newline is a line continuation
+                if let Some(&next) = chars.get(i + 1) {
+                    if next != '\n' {
+                        in_word = true;
+                        cur.push(next);
+                    }
+                }
+                i += 1;
+            }
+            '$' if chars.get(i + 1) == Some(&'(') => {
+                let (body, next) = capture_parens(&chars, i + 2);
+                in_word = true;
+                cur.push_str(&format!("$({})", body));
+                nested.push(body);
+                i = next;
+                continue;
+            }
+            '`' => {
+                let (body, next) = capture_backticks(&chars, i + 1);
+                in_word = true;
+                cur.push_str(&format!("`{}`", body));
+                nested.push(body);
+                i = next;
+                continue;
+            }
+            _ => {
+                in_word = true;
+                cur.push(c);
+            }
+        }
+        i += 1;
+    }
+    finish_word(&mut cur, &mut in_word, &mut redirect_target, &mut words);
+    if !words.is_empty() {
+        segments.push(words);
+    }
+
+    (segments, nested)
+}
+
+fn is_assignment(word: &str) -> bool {
+    match word.split_once('=') {
+        Some((name, _)) => {
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        None => false,
+    }
+}
+
+fn basename(word: &str) -> String {
+    word.rsplit('/').next().unwrap_or(word).to_string()
+}
+
+/// Skip a wrapper's own options. `with_value` lists options that take a separate argument.
+fn skip_options(words: &[String], mut idx: usize, with_value: &[&str]) -> usize {
+    while let Some(w) = words.get(idx) {
+        if w == "--" {
+            return idx + 1;
+        }
+        if !w.starts_with('-') || w.len() == 1 {
+            break;
+        }
+        idx += if with_value.contains(&w.as_str()) { 2 } else { 1 };
+    }
+    idx
+}
+
+/// Strip wrappers and assignments from one simple command. Command strings
+/// handed to `bash -c` or `eval` are pushed to `nested`. A segment that is only
+/// a redirect (`> file`) becomes a command with an empty program.
+fn resolve_segment(segment: &Segment, nested: &mut Vec<String>) -> Option<ParsedCommand> {
+    let words = &segment.words;
+    let mut idx = 0;
+    let mut elevated = false;
+
+    loop {
+        while words.get(idx).is_some_and(|w| is_assignment(w)) {
+            idx += 1;
+        }
+        let Some(word) = words.get(idx) else { break };
+        let name = basename(word);
+        idx = match name.as_str() {
+            "sudo" | "doas" => {
+                elevated = true;
+                skip_options(words, idx + 1, &["-u", "-g", "-C", "-D", "-h", "-p", "-r", "-t", "-U"])
+            }
+            "env" => skip_options(words, idx + 1, &["-u", "-C", "-S"]),
+            "nohup" | "time" | "command" | "builtin" | "exec" => skip_options(words, idx + 1, &[]),
+            "nice" => skip_options(words, idx + 1, &["-n"]),
+            // The first operand is the duration
+            "timeout" => skip_options(words, idx + 1, &["-s", "-k", "--signal", "--kill-after"]) + 1,
+            "xargs" => skip_options(words, idx + 1, &["-I", "-n", "-P", "-L", "-s", "-d", "-E", "-a"]),
+            _ => break,
+        };
+    }
+
+    if idx >= words.len() && segment.redirects.is_empty() {
+        return None;
+    }
+    let program = words.get(idx).map(|w| basename(w)).unwrap_or_default();
+    let args: Vec<String> = words.get(idx + 1..).unwrap_or_default().to_vec();
+
+    match program.as_str() {
+        "bash" | "sh" | "zsh" | "dash" | "ksh" => {
+            if let Some(pos) = args.iter().position(|a| a == "-c") {
+                if let Some(script) = args.get(pos + 1) {
+                    nested.push(script.clone());
+                }
+            }
+        }
+        "eval" => nested.push(args.join(" ")),
+        _ => {}
+    }
+
+    Some(ParsedCommand {
+        program,
+        args,
+        redirects: segment.redirects.clone(),
+        elevated,
+    })
+}
+
+/// Every simple command in `input`, including those inside `bash -c`, `eval`
+/// and command substitutions.
+fn parse_command_line(input: &str) -> Vec<ParsedCommand> {
+    let mut commands = Vec::new();
+    let mut pending = vec![(input.to_string(), 0)];
+
+    while let Some((text, depth)) = pending.pop() {
+        let (segments, mut nested) = split_words(&text);
+        for segment in &segments {
+            if let Some(cmd) = resolve_segment(segment, &mut nested) {
+                commands.push(cmd);
+            }
+        }
+        if depth < MAX_NESTING {
+            pending.extend(nested.into_iter().map(|n| (n, depth + 1)));
+        } else if !nested.is_empty() {
+            // Too deep to follow: treat as code we can't see
+            commands.push(ParsedCommand {
+                program: "eval".to_string(),
+                args: nested,
+                redirects: Vec::new(),
+                elevated: false,
+            });
+        }
+    }
+
+    commands
+}
+
+// ── Classification ──────────────────────────────────────────────────
+
+/// True if `args` contains `long` or a short-option cluster containing `short`.
+fn has_flag(args: &[String], short: Option<char>, long: &[&str]) -> bool {
+    args.iter().any(|a| {
+        if long.contains(&a.as_str()) {
+            return true;
+        }
+        match short {
+            Some(s) => {
+                a.len() > 1
+                    && a.starts_with('-')
+                    && !a.starts_with("--")
+                    && a[1..].chars().all(|c| c.is_ascii_alphanumeric())
+                    && a[1..].contains(s)
+            }
+            None => false,
+        }
+    })
+}
+
+/// `git [-C dir] [-c k=v] <sub> args...`
+fn git_subcommand(args: &[String]) -> Option<(&str, &[String])> {
+    let idx = skip_options(args, 0, &["-C", "-c", "--git-dir", "--work-tree", "--namespace"]);
+    args.get(idx).map(|sub| (sub.as_str(), &args[idx + 1..]))
+}
+
+fn classify(cmd: &ParsedCommand) -> Vec<Category> {
+    let mut categories = Vec::new();
+    let args = &cmd.args;
+    let first = args.first().map(String::as_str).unwrap_or("");
+    let any_arg = |needles: &[&str]| args.iter().any(|a| needles.iter().any(|n| a.contains(n)));
+
+    let destructive = cmd.elevated
+        || match cmd.program.as_str() {
+            "rm" => has_flag(args, Some('r'), &["--recursive"])
+                || has_flag(args, Some('R'), &[])
+                || has_flag(args, Some('f'), &["--force"]),
+            "rmdir" | "shred" | "dd" | "fdisk" | "truncate" | "wipefs" => true,
+            p if p.starts_with("mkfs") => true,
+            "chmod" | "chown" | "chgrp" => has_flag(args, Some('R'), &["--recursive"]),
+            "find" => has_flag(args, None, &["-delete"])
+                || args.windows(2).any(|w| w[0] == "-exec" && basename(&w[1]) == "rm"),
+            "diskutil" => first.starts_with("erase") || first == "partitionDisk",
+            "git" => match git_subcommand(args) {
+                Some(("push", rest)) => {
+                    has_flag(rest, Some('f'), &["--force", "--force-with-lease", "--mirror", "--delete"])
+                        || has_flag(rest, Some('d'), &[])
+                        || rest.iter().any(|a| a.starts_with('+') || a.starts_with(':'))
+                }
+                Some(("reset", rest)) => has_flag(rest, None, &["--hard"]),
+                Some(("clean", rest)) => has_flag(rest, Some('f'), &["--force"]),
+                Some(("branch", rest)) => has_flag(rest, Some('D'), &[]),
+                Some(("checkout", rest)) | Some(("restore", rest)) => {
+                    rest.iter().any(|a| a == "." || a == "--")
+                }
+                Some(("stash", rest)) => matches!(rest.first().map(String::as_str), Some("drop") | Some("clear")),
+                Some(("filter-branch", _)) | Some(("filter-repo", _)) => true,
+                _ => false,
+            },
+            _ => false,
+        };
+    if destructive {
+        categories.push(Category::Destructive);
+    }
+
+    let network = match cmd.program.as_str() {
+        "curl" | "wget" | "ssh" | "scp" | "sftp" | "rsync" | "nc" | "ncat" | "netcat" | "telnet" | "ftp"
+        | "http" | "https" | "aria2c" => true,
+        "git" => matches!(
+            git_subcommand(args).map(|(sub, _)| sub),
+            Some("push") | Some("pull") | Some("fetch") | Some("clone") | Some("ls-remote") | Some("submodule")
+        ),
+        "npm" | "pnpm" | "yarn" | "bun" => matches!(
+            first,
+            "install" | "i" | "add" | "ci" | "publish" | "update" | "upgrade" | "exec" | "dlx"
+        ) || (cmd.program == "yarn" && args.is_empty()),
+        "npx" | "pnpx" | "bunx" | "uvx" => true,
+        "pip" | "pip3" | "uv" => matches!(first, "install" | "download" | "add" | "sync"),
+        "cargo" => matches!(first, "install" | "publish" | "fetch" | "add"),
+        "gem" | "brew" | "apt" | "apt-get" | "dnf" | "yum" | "pacman" | "conda" | "mamba" | "port" => {
+            matches!(first, "install" | "upgrade" | "update" | "create" | "-S" | "-Syu" | "tap")
+        }
+        "python" | "python3" => args.windows(2).any(|w| w[0] == "-m" && w[1].starts_with("pip")),
+        "R" | "Rscript" => any_arg(&["install.packages", "remotes::", "devtools::", "pak::", "BiocManager::", "download.file", "url("]),
+        _ => false,
+    };
+    if network {
+        categories.push(Category::Network);
+    }
+
+    // A redirect target built at run time could name any file
+    let dynamic_redirect = cmd.redirects.iter().any(|r| r.contains('$') || r.contains('`'));
+    if matches!(cmd.program.as_str(), "eval" | "source" | ".")
+        || cmd.program.contains('$')
+        || cmd.program.contains('`')
+        || dynamic_redirect
+    {
+        categories.push(Category::Dynamic);
+    }
+
+    categories
+}
+
+// ── Rules ───────────────────────────────────────────────────────────
+
+/// `*` and `?` wildcards over the whole string.
+fn simple_glob(pattern: &str, text: &str) -> bool {
+    let mut re = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            c => re.push_str(&regex_lite::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    regex_lite::Regex::new(&re).map(|r| r.is_match(text)).unwrap_or(false)
+}
+
+fn arg_matches(pattern: &str, args: &[String]) -> bool {
+    let mut chars = pattern.chars();
+    if let (Some('-'), Some(flag), None) = (chars.next(), chars.next(), chars.next()) {
+        if flag.is_ascii_alphanumeric() {
+            return has_flag(args, Some(flag), &[]);
+        }
+    }
+    args.iter().any(|a| simple_glob(pattern, a))
+}
+
+fn rule_matches(
+    rule: &PolicyRule,
+    cmd: &ParsedCommand,
+    categories: &[Category],
+    raw: &str,
+    cwd: &str,
+    workspace: &str,
+) -> Result<bool, String> {
+    if let Some(ref glob) = rule.command {
+        if !simple_glob(glob, &cmd.program) {
+            return Ok(false);
+        }
+    }
+    if !rule.args.iter().all(|a| arg_matches(a, &cmd.args)) {
+        return Ok(false);
+    }
+    if let Some(category) = rule.category {
+        if !categories.contains(&category) {
+            return Ok(false);
+        }
+    }
+    if let Some(ref glob) = rule.cwd {
+        let glob = glob.replace("${workspace}", workspace.trim_end_matches('/'));
+        let re = crate::fs_commands::glob_to_regex(&glob)?;
+        // glob_to_regex matches relative paths: an anchored glob loses its leading '/'
+        if !re.is_match(cwd.trim_matches('/')) {
+            return Ok(false);
+        }
+    }
+    if let Some(ref pattern) = rule.pattern {
+        let re = regex_lite::Regex::new(pattern).map_err(|e| format!("Invalid rule pattern '{}': {}", pattern, e))?;
+        if !re.is_match(raw) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn policy_path(workspace: &Path) -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Cannot find home directory")?;
+    let canonical = fs::canonicalize(workspace).unwrap_or_else(|_| workspace.to_path_buf());
+    let key = crate::fs_commands::content_hash(canonical.to_string_lossy().as_bytes());
+    Ok(home.join(".shoulders").join(POLICY_DIR).join(format!("{}.json", key)))
+}
+
+fn load_config(workspace: &Path) -> Result<PolicyConfig, String> {
+    let path = policy_path(workspace)?;
+    match fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).map_err(|e| format!("{} is invalid: {}", path.display(), e)),
+        Err(_) => Ok(PolicyConfig::default()),
+    }
+}
+
+/// Reject rules whose `pattern` or `cwd` would deny every command at run time.
+fn validate_config(config: &PolicyConfig) -> Result<(), String> {
+    for (i, rule) in config.rules.iter().enumerate() {
+        if let Some(ref pattern) = rule.pattern {
+            regex_lite::Regex::new(pattern).map_err(|e| format!("Rule {}: invalid pattern '{}': {}", i + 1, pattern, e))?;
+        }
+        if let Some(ref glob) = rule.cwd {
+            let glob = glob.replace("${workspace}", "workspace");
+            crate::fs_commands::glob_to_regex(&glob).map_err(|e| format!("Rule {}: {}", i + 1, e))?;
+        }
+    }
+    Ok(())
+}
+
+/// The shell words of a line that may touch the app's protected files.
+fn protected_word(commands: &[ParsedCommand]) -> Option<&str> {
+    commands
+        .iter()
+        .flat_map(|c| std::iter::once(&c.program).chain(&c.args).chain(&c.redirects))
+        .map(String::as_str)
+        .find(|w| crate::sandbox::mentions_protected(w))
+}
+
+fn describe(action: PolicyAction, rule: Option<(usize, &PolicyRule)>, categories: &[Category], program: &str) -> String {
+    if let Some((_, PolicyRule { reason: Some(reason), .. })) = rule {
+        return reason.clone();
+    }
+    let what = match rule.and_then(|(_, r)| r.category).or_else(|| categories.first().copied()) {
+        Some(Category::Destructive) => "destructive command".to_string(),
+        Some(Category::Network) => "network access".to_string(),
+        Some(Category::Dynamic) => "dynamically evaluated command".to_string(),
+        None if program.is_empty() => "command".to_string(),
+        None => format!("`{}`", program),
+    };
+    match (action, rule) {
+        (PolicyAction::Allow, Some((i, _))) => format!("allowed by rule {}", i + 1),
+        (_, Some((i, _))) => format!("{} (rule {})", what, i + 1),
+        (PolicyAction::Allow, None) => "allowed by default".to_string(),
+        (_, None) => format!("{} (default policy)", what),
+    }
+}
+
+/// Decide what to do with `command` run in `cwd`. Each simple command in the
+/// line is matched against the rules in order (first match wins, otherwise the
+/// default applies), and the strictest result across them is returned.
+/// An unreadable policy file denies everything, and so does a command that
+/// names `.shoulders` or the files the sandbox protects. Without a workspace
+/// the default policy applies.
+pub(crate) fn evaluate(workspace: Option<&str>, cwd: &str, command: &str) -> PolicyDecision {
+    let commands = parse_command_line(command);
+    let mut categories: Vec<Category> = Vec::new();
+    for cmd in &commands {
+        for c in classify(cmd) {
+            if !categories.contains(&c) {
+                categories.push(c);
+            }
+        }
+    }
+
+    let deny = |reason: String, categories: Vec<Category>| PolicyDecision {
+        action: PolicyAction::Deny,
+        rule: None,
+        reason,
+        categories,
+    };
+    if let Some(word) = protected_word(&commands) {
+        return deny(format!("`{}` refers to settings the app manages", word), categories);
+    }
+    let config = match workspace {
+        Some(ws) => match load_config(Path::new(ws)) {
+            Ok(c) => c,
+            Err(e) => return deny(e, categories),
+        },
+        None => PolicyConfig::default(),
+    };
+    let workspace = workspace.unwrap_or("");
+
+    let mut decision = PolicyDecision {
+        action: config.default,
+        rule: None,
+        reason: describe(config.default, None, &categories, ""),
+        categories: categories.clone(),
+    };
+    let mut strictest: Option<PolicyAction> = None;
+
+    for cmd in &commands {
+        let cmd_categories = classify(cmd);
+        let mut matched = None;
+        for (i, rule) in config.rules.iter().enumerate() {
+            match rule_matches(rule, cmd, &cmd_categories, command, cwd, workspace) {
+                Ok(true) => {
+                    matched = Some((i, rule));
+                    break;
+                }
+                Ok(false) => {}
+                Err(e) => return deny(e, categories),
+            }
+        }
+        let action = matched.map_or(config.default, |(_, r)| r.action);
+        if strictest.is_none_or(|s| action > s) {
+            strictest = Some(action);
+            decision = PolicyDecision {
+                action,
+                rule: matched.map(|(i, _)| i),
+                reason: describe(action, matched, &cmd_categories, &cmd.program),
+                categories: categories.clone(),
+            };
+        }
+    }
+
+    decision
+}
+
+/// Check `command` against the policy of the workspace it runs in and log the
+/// decision. The workspace is `workspace` if given, otherwise the sandbox root
+/// containing `cwd`; outside every root the default policy applies and the
+/// decision goes to the global log. Returns the decision if the command may not run.
+pub(crate) fn gate(
+    sandbox: &crate::sandbox::SandboxState,
+    workspace: Option<String>,
+    cwd: &str,
+    command: &str,
+    approved: bool,
+) -> Result<(), PolicyDecision> {
+    let workspace = workspace.or_else(|| sandbox.root_for(cwd).map(|r| r.to_string_lossy().to_string()));
+    let decision = evaluate(workspace.as_deref(), cwd, command);
+    let runs = match decision.action {
+        PolicyAction::Allow => true,
+        PolicyAction::Ask => approved,
+        PolicyAction::Deny => false,
+    };
+    log_decision(workspace.as_deref(), cwd, command, &decision, approved, runs);
+    if runs {
+        Ok(())
+    } else {
+        Err(decision)
+    }
+}
+
+#[derive(Serialize)]
+struct LogEntry<'a> {
+    /// Unix seconds
+    time: u64,
+    cwd: &'a str,
+    command: &'a str,
+    action: PolicyAction,
+    rule: Option<usize>,
+    reason: &'a str,
+    /// Whether the user confirmed an `ask` decision
+    approved: bool,
+    /// Whether the command was started
+    ran: bool,
+}
+
+/// Append a decision to the workspace's `.shoulders/command-log.jsonl`, or to
+/// `~/.shoulders/command-log.jsonl` without a workspace.
+pub(crate) fn log_decision(workspace: Option<&str>, cwd: &str, command: &str, decision: &PolicyDecision, approved: bool, ran: bool) {
+    let entry = LogEntry {
+        time: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        cwd,
+        command,
+        action: decision.action,
+        rule: decision.rule,
+        reason: &decision.reason,
+        approved,
+        ran,
+    };
+    let line = match serde_json::to_string(&entry) {
+        Ok(l) => l,
+        Err(_) => return,
+    };
+    let dir = match workspace {
+        Some(workspace) => Path::new(workspace).join(".shoulders"),
+        None => match dirs::home_dir() {
+            Some(home) => home.join(".shoulders"),
+            None => return,
+        },
+    };
+    let path = dir.join(LOG_FILE);
+    let result = fs::create_dir_all(&dir)
+        .and_then(|_| rotate_log(&path))
+        .and_then(|_| fs::OpenOptions::new().create(true).append(true).open(&path))
+        .and_then(|mut f| writeln!(f, "{}", line));
+    if let Err(e) = result {
+        eprintln!("[command-policy] failed to write log: {}", e);
+    }
+}
+
+fn rotate_log(path: &Path) -> std::io::Result<()> {
+    match fs::metadata(path) {
+        Ok(meta) if meta.len() > MAX_LOG_BYTES => fs::rename(path, path.with_extension("1.jsonl")),
+        _ => Ok(()),
+    }
+}
+
+/// Dry run: what the policy would decide for `command`, without logging.
+#[tauri::command]
+pub async fn command_policy_check(workspace: String, cwd: String, command: String) -> Result<PolicyDecision, String> {
+    Ok(evaluate(Some(&workspace), &cwd, &command))
+}
+
+/// The workspace's policy, or the built-in default if it has no policy file.
+#[tauri::command]
+pub async fn command_policy_get(workspace: String) -> Result<PolicyConfig, String> {
+    load_config(Path::new(&workspace))
+}
+
+/// Validate and save the workspace's policy. `None` deletes it, restoring the default.
+#[tauri::command]
+pub async fn command_policy_set(workspace: String, config: Option<PolicyConfig>) -> Result<(), String> {
+    let path = policy_path(Path::new(&workspace))?;
+    let Some(config) = config else {
+        return match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        };
+    };
+    validate_config(&config)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let content = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn programs(line: &str) -> Vec<String> {
+        let mut programs: Vec<String> = parse_command_line(line).into_iter().map(|c| c.program).collect();
+        programs.sort();
+        programs
+    }
+
+    fn action(line: &str) -> PolicyAction {
+        evaluate(None, "/tmp", line).action
+    }
+
+    fn rule(action: PolicyAction) -> PolicyRule {
+        PolicyRule {
+            action,
+            command: None,
+            args: Vec::new(),
+            cwd: None,
+            pattern: None,
+            category: None,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn splits_operators_and_pipes() {
+        assert_eq!(programs("ls -la && grep foo bar.txt | wc -l; echo done"), ["echo", "grep", "ls", "wc"]);
+    }
+
+    #[test]
+    fn quoted_operators_stay_in_the_word() {
+        let commands = parse_command_line("echo 'a && rm -rf /' \"b | c\"");
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].args, ["a && rm -rf /", "b | c"]);
+    }
+
+    #[test]
+    fn strips_wrappers_and_assignments() {
+        let commands = parse_command_line("FOO=1 sudo -u root env BAR=2 nohup /bin/rm file");
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].program, "rm");
+        assert_eq!(commands[0].args, ["file"]);
+        assert!(commands[0].elevated);
+    }
+
+    #[test]
+    fn follows_nested_scripts() {
+        assert_eq!(programs("bash -c 'curl example.com'"), ["bash", "curl"]);
+        assert_eq!(programs("echo $(rm -rf build)"), ["echo", "rm"]);
+        assert_eq!(programs("echo `whoami`"), ["echo", "whoami"]);
+        assert_eq!(programs("eval 'git push'"), ["eval", "git"]);
+    }
+
+    #[test]
+    fn collects_redirect_targets() {
+        let commands = parse_command_line("cat < in.txt > out.txt 2>&1");
+        assert_eq!(commands[0].redirects, ["in.txt", "out.txt"]);
+    }
+
+    #[test]
+    fn classifies_commands() {
+        let categories = |line: &str| evaluate(None, "/tmp", line).categories;
+        assert_eq!(categories("rm -rf build"), [Category::Destructive]);
+        assert_eq!(categories("git push --force origin main"), [Category::Destructive, Category::Network]);
+        assert_eq!(categories("npm install"), [Category::Network]);
+        assert_eq!(categories("source env.sh"), [Category::Dynamic]);
+        assert_eq!(categories("echo hi > $OUT"), [Category::Dynamic]);
+        assert!(categories("git status && ls").is_empty());
+    }
+
+    #[test]
+    fn default_policy() {
+        assert_eq!(action("ls -la"), PolicyAction::Allow);
+        assert_eq!(action("rm -rf build"), PolicyAction::Ask);
+        assert_eq!(action("curl https://example.com"), PolicyAction::Ask);
+        assert_eq!(action("ls && sudo true"), PolicyAction::Ask);
+    }
+
+    #[test]
+    fn protected_files_are_denied() {
+        assert_eq!(action("cat ~/.shoulders/allowed-hosts.json"), PolicyAction::Deny);
+        assert_eq!(action("echo '{}' > ~/.shoulders/command-policies/x.json"), PolicyAction::Deny);
+        assert_eq!(action("rm -r ~/.shoulders"), PolicyAction::Deny);
+    }
+
+    #[test]
+    fn first_matching_rule_wins_and_strictest_command_decides() {
+        let config = PolicyConfig {
+            default: PolicyAction::Allow,
+            rules: vec![
+                PolicyRule { command: Some("git".into()), args: vec!["status".into()], ..rule(PolicyAction::Allow) },
+                PolicyRule { command: Some("git".into()), ..rule(PolicyAction::Deny) },
+            ],
+        };
+        let decide = |line: &str| {
+            parse_command_line(line)
+                .iter()
+                .map(|cmd| {
+                    let categories = classify(cmd);
+                    config
+                        .rules
+                        .iter()
+                        .find(|r| rule_matches(r, cmd, &categories, line, "/tmp", "/tmp").unwrap())
+                        .map_or(config.default, |r| r.action)
+                })
+                .max()
+                .unwrap()
+        };
+        assert_eq!(decide("git status"), PolicyAction::Allow);
+        assert_eq!(decide("git status && git log"), PolicyAction::Deny);
+        assert_eq!(decide("ls"), PolicyAction::Allow);
+    }
+
+    #[test]
+    fn short_flag_rules_match_clusters() {
+        let args = vec!["-rf".to_string(), "build".to_string()];
+        assert!(arg_matches("-f", &args));
+        assert!(arg_matches("-r", &args));
+        assert!(!arg_matches("-v", &args));
+        assert!(arg_matches("bu*", &args));
+    }
+}
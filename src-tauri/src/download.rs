@@ -0,0 +1,422 @@
+use crate::host_allowlist::validate_url_host;
+use crate::sandbox::SandboxState;
+use reqwest::header::{CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+pub struct DownloadState {
+    pub downloads: Mutex<HashMap<String, tokio::sync::watch::Sender<bool>>>,
+}
+
+impl Default for DownloadState {
+    fn default() -> Self {
+        Self {
+            downloads: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct DownloadOptions {
+    /// Extra request headers, e.g. `Accept`
+    pub headers: HashMap<String, String>,
+    /// Expected SHA-256 (hex). On a mismatch the file is deleted and the download fails.
+    pub sha256: Option<String>,
+    /// Discard a `.part` file left by an interrupted download instead of resuming it
+    pub restart: bool,
+    /// Replace an existing file at the target path
+    pub overwrite: bool,
+}
+
+#[derive(Serialize)]
+pub struct DownloadResult {
+    pub path: String,
+    pub bytes: u64,
+    pub sha256: String,
+    /// Bytes kept from an earlier interrupted download (0 for a fresh one)
+    pub resumed_from: u64,
+    pub final_url: String,
+    pub content_type: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+struct DownloadProgress {
+    downloaded: u64,
+    /// `None` when the server sent no length
+    total: Option<u64>,
+}
+
+/// Validators of the response a `.part` file came from, so a resumed download
+/// only continues the same version of the file.
+#[derive(Serialize, Deserialize)]
+struct PartMeta {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+/// Longest wait for the next chunk before the download counts as stalled
+const STALL_TIMEOUT: Duration = Duration::from_secs(60);
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+const MAX_REDIRECTS: usize = 10;
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn read_part_meta(meta_path: &Path, url: &str) -> Option<PartMeta> {
+    let meta: PartMeta = serde_json::from_str(&fs::read_to_string(meta_path).ok()?).ok()?;
+    (meta.url == url && (meta.etag.is_some() || meta.last_modified.is_some())).then_some(meta)
+}
+
+fn hash_file(path: &Path, hasher: &mut Sha256) -> Result<(), String> {
+    let mut file = fs::File::open(path).map_err(|e| format!("Cannot read partial download: {}", e))?;
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).map_err(|e| format!("Cannot read partial download: {}", e))?;
+        if n == 0 {
+            return Ok(());
+        }
+        hasher.update(&buf[..n]);
+    }
+}
+
+fn header_string(response: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    response.headers().get(name).and_then(|v| v.to_str().ok()).map(|v| v.to_string())
+}
+
+/// Send the request, continuing from `offset` when the server supports it.
+/// Returns the response and the offset its body starts at, which is 0 when
+/// the server sent the whole file instead.
+async fn request_from(
+    client: &reqwest::Client,
+    url: &str,
+    headers: &HashMap<String, String>,
+    offset: u64,
+    meta: Option<&PartMeta>,
+) -> Result<(reqwest::Response, u64), String> {
+    let send = |range: Option<(u64, &str)>| {
+        let mut req = client.get(url).header("User-Agent", "Shoulders/1.0");
+        for (key, value) in headers {
+            req = req.header(key.as_str(), value.as_str());
+        }
+        if let Some((offset, validator)) = range {
+            req = req.header(RANGE, format!("bytes={}-", offset)).header(IF_RANGE, validator);
+        }
+        req.send()
+    };
+
+    let validator = meta.and_then(|m| m.etag.as_deref().or(m.last_modified.as_deref()));
+    if let (true, Some(validator)) = (offset > 0, validator) {
+        let response = send(Some((offset, validator))).await.map_err(|e| format!("Download failed: {}", e))?;
+        let status = response.status().as_u16();
+        let continues = header_string(&response, CONTENT_RANGE)
+            .is_some_and(|range| range.starts_with(&format!("bytes {}-", offset)));
+        if status == 206 && continues {
+            return Ok((response, offset));
+        }
+        if response.status().is_success() && status != 206 {
+            // Range ignored or the file changed (If-Range failed): full body
+            return Ok((response, 0));
+        }
+        // 416 or a mismatched range: start over below
+    }
+
+    let response = send(None).await.map_err(|e| format!("Download failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Download failed with HTTP {}", response.status()));
+    }
+    Ok((response, 0))
+}
+
+async fn run_download(
+    app: &tauri::AppHandle,
+    event: &str,
+    url: &str,
+    target: &Path,
+    options: &DownloadOptions,
+    cancel_rx: &mut tokio::sync::watch::Receiver<bool>,
+) -> Result<DownloadResult, String> {
+    let part_path = with_suffix(target, ".part");
+    let meta_path = with_suffix(target, ".part.json");
+    if options.restart {
+        let _ = fs::remove_file(&part_path);
+        let _ = fs::remove_file(&meta_path);
+    }
+
+    let meta = read_part_meta(&meta_path, url);
+    let existing = match meta {
+        Some(_) => fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0),
+        None => 0,
+    };
+
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(30))
+        .redirect(reqwest::redirect::Policy::custom(|attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if let Err(e) = validate_url_host(attempt.url().as_str()) {
+                attempt.error(e)
+            } else {
+                attempt.follow()
+            }
+        }))
+        .build()
+        .map_err(|e| format!("HTTP client error: {}", e))?;
+
+    let (mut response, offset) = request_from(&client, url, &options.headers, existing, meta.as_ref()).await?;
+
+    let final_url = response.url().to_string();
+    let content_type = header_string(&response, CONTENT_TYPE);
+    let new_meta = PartMeta {
+        url: url.to_string(),
+        etag: header_string(&response, ETAG),
+        last_modified: header_string(&response, LAST_MODIFIED),
+    };
+    if offset == 0 {
+        // Without a validator a later resume could splice two versions together
+        if new_meta.etag.is_some() || new_meta.last_modified.is_some() {
+            let json = serde_json::to_string(&new_meta).map_err(|e| e.to_string())?;
+            fs::write(&meta_path, json).map_err(|e| format!("Cannot write {}: {}", meta_path.display(), e))?;
+        } else {
+            let _ = fs::remove_file(&meta_path);
+        }
+    }
+
+    let mut hasher = Sha256::new();
+    let mut file = if offset > 0 {
+        hash_file(&part_path, &mut hasher)?;
+        fs::OpenOptions::new().append(true).open(&part_path)
+    } else {
+        fs::File::create(&part_path)
+    }
+    .map_err(|e| format!("Cannot write {}: {}", part_path.display(), e))?;
+
+    let total = response.content_length().map(|len| len + offset);
+    let mut downloaded = offset;
+    let mut last_emit = Instant::now();
+    let _ = app.emit(event, DownloadProgress { downloaded, total });
+
+    loop {
+        let chunk = tokio::select! {
+            chunk = tokio::time::timeout(STALL_TIMEOUT, response.chunk()) => match chunk {
+                Ok(chunk) => chunk.map_err(|e| format!("Download interrupted: {}", e))?,
+                Err(_) => return Err("Download stalled: no data for 60 seconds".to_string()),
+            },
+            _ = cancel_rx.changed() => return Err("Download cancelled".to_string()),
+        };
+        let Some(chunk) = chunk else { break };
+        file.write_all(&chunk).map_err(|e| format!("Write error: {}", e))?;
+        hasher.update(&chunk);
+        downloaded += chunk.len() as u64;
+
+        if last_emit.elapsed() >= PROGRESS_INTERVAL {
+            last_emit = Instant::now();
+            let _ = app.emit(event, DownloadProgress { downloaded, total });
+        }
+    }
+    file.sync_all().map_err(|e| format!("Write error: {}", e))?;
+    drop(file);
+    let _ = app.emit(event, DownloadProgress { downloaded, total: Some(downloaded) });
+
+    if total.is_some_and(|total| total != downloaded) {
+        return Err(format!("Download incomplete: got {} of {} bytes", downloaded, total.unwrap_or(0)));
+    }
+
+    let sha256 = hex::encode(hasher.finalize());
+    if let Some(ref expected) = options.sha256 {
+        if !expected.trim().eq_ignore_ascii_case(&sha256) {
+            let _ = fs::remove_file(&part_path);
+            let _ = fs::remove_file(&meta_path);
+            return Err(format!("SHA-256 mismatch: expected {}, got {}", expected.trim(), sha256));
+        }
+    }
+
+    if options.overwrite && target.exists() {
+        fs::remove_file(target).map_err(|e| format!("Cannot replace {}: {}", target.display(), e))?;
+    }
+    fs::rename(&part_path, target).map_err(|e| format!("Cannot move download into place: {}", e))?;
+    let _ = fs::remove_file(&meta_path);
+
+    Ok(DownloadResult {
+        path: target.to_string_lossy().to_string(),
+        bytes: downloaded,
+        sha256,
+        resumed_from: offset,
+        final_url,
+        content_type,
+    })
+}
+
+/// Stream `url` to `path`, for PDFs, datasets and other large files. The URL
+/// and every redirect must pass the host allowlist, and `path` the sandbox.
+///
+/// Data goes to `{path}.part` and is renamed into place once complete and
+/// verified. If a download is interrupted or cancelled, the `.part` file stays,
+/// and the next call for the same URL resumes it with a Range request. This
+/// only happens when the server sent an ETag or Last-Modified to check the
+/// file against.
+///
+/// Emits `download-progress-{download_id}` → { downloaded, total } at most
+/// every 250ms, and once when done.
+#[tauri::command]
+pub async fn download_to_file(
+    app: tauri::AppHandle,
+    sandbox: tauri::State<'_, SandboxState>,
+    state: tauri::State<'_, DownloadState>,
+    download_id: String,
+    url: String,
+    path: String,
+    options: Option<DownloadOptions>,
+) -> Result<DownloadResult, String> {
+    validate_url_host(&url)?;
+    sandbox.check_write(&path)?;
+    let options = options.unwrap_or_default();
+    let target = PathBuf::from(&path);
+    if target.is_dir() {
+        return Err(format!("{} is a directory", path));
+    }
+    if target.exists() && !options.overwrite {
+        return Err(format!("File already exists: {}", path));
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Cannot create directory: {}", e))?;
+    }
+
+    let (cancel_tx, mut cancel_rx) = tokio::sync::watch::channel(false);
+    {
+        let mut downloads = state.downloads.lock().unwrap();
+        if downloads.contains_key(&download_id) {
+            return Err(format!("Download {} is already running", download_id));
+        }
+        downloads.insert(download_id.clone(), cancel_tx);
+    }
+
+    let event = format!("download-progress-{}", download_id);
+    let result = run_download(&app, &event, &url, &target, &options, &mut cancel_rx).await;
+    state.downloads.lock().unwrap().remove(&download_id);
+    result
+}
+
+/// Stop a running download. Its `.part` file is kept for resuming.
+#[tauri::command]
+pub async fn download_cancel(state: tauri::State<'_, DownloadState>, download_id: String) -> Result<(), String> {
+    let downloads = state.downloads.lock().unwrap();
+    if let Some(cancel_tx) = downloads.get(&download_id) {
+        let _ = cancel_tx.send(true);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Serve one canned response per connection, in order. Resolves to the
+    /// request heads received, lowercased.
+    async fn serve(responses: Vec<&'static str>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/file.pdf", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut head = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    head.extend_from_slice(&buf[..n]);
+                }
+                requests.push(String::from_utf8_lossy(&head).to_ascii_lowercase());
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    fn meta(url: &str) -> PartMeta {
+        PartMeta { url: url.to_string(), etag: Some("\"v1\"".to_string()), last_modified: None }
+    }
+
+    async fn request(url: &str, offset: u64, meta: Option<&PartMeta>) -> Result<(String, u64), String> {
+        let client = reqwest::Client::new();
+        let (response, start) = request_from(&client, url, &HashMap::new(), offset, meta).await?;
+        Ok((response.text().await.unwrap(), start))
+    }
+
+    const PARTIAL: &str = "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 5-9/10\r\nContent-Length: 5\r\nConnection: close\r\n\r\n56789";
+    const FULL: &str = "HTTP/1.1 200 OK\r\nContent-Length: 10\r\nConnection: close\r\n\r\n0123456789";
+
+    #[tokio::test]
+    async fn resumes_with_range_and_validator() {
+        let (url, server) = serve(vec![PARTIAL]).await;
+        assert_eq!(request(&url, 5, Some(&meta(&url))).await.unwrap(), ("56789".to_string(), 5));
+        let requests = server.await.unwrap();
+        assert!(requests[0].contains("range: bytes=5-\r\n"));
+        assert!(requests[0].contains("if-range: \"v1\"\r\n"));
+    }
+
+    #[tokio::test]
+    async fn full_body_when_range_is_ignored() {
+        let (url, server) = serve(vec![FULL]).await;
+        assert_eq!(request(&url, 5, Some(&meta(&url))).await.unwrap(), ("0123456789".to_string(), 0));
+        assert_eq!(server.await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn starts_over_on_a_bad_range() {
+        let unsatisfiable = "HTTP/1.1 416 Range Not Satisfiable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let wrong_offset = "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-9/10\r\nContent-Length: 10\r\nConnection: close\r\n\r\n0123456789";
+        for first in [unsatisfiable, wrong_offset] {
+            let (url, server) = serve(vec![first, FULL]).await;
+            assert_eq!(request(&url, 5, Some(&meta(&url))).await.unwrap(), ("0123456789".to_string(), 0));
+            let requests = server.await.unwrap();
+            assert!(!requests[1].contains("range:"));
+        }
+    }
+
+    #[tokio::test]
+    async fn no_range_without_a_validator() {
+        let (url, server) = serve(vec![FULL]).await;
+        assert_eq!(request(&url, 5, None).await.unwrap().1, 0);
+        assert!(!server.await.unwrap()[0].contains("range:"));
+
+        let (url, _server) = serve(vec!["HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"]).await;
+        assert_eq!(request(&url, 0, None).await.unwrap_err(), "Download failed with HTTP 404 Not Found");
+    }
+
+    #[test]
+    fn part_meta_must_match_the_url_and_have_a_validator() {
+        let dir = std::env::temp_dir().join(format!("shoulders-download-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file.pdf.part.json");
+        let url = "https://example.com/file.pdf";
+
+        fs::write(&path, serde_json::to_string(&meta(url)).unwrap()).unwrap();
+        assert!(read_part_meta(&path, url).is_some());
+        assert!(read_part_meta(&path, "https://example.com/other.pdf").is_none());
+
+        let unvalidated = PartMeta { etag: None, ..meta(url) };
+        fs::write(&path, serde_json::to_string(&unvalidated).unwrap()).unwrap();
+        assert!(read_part_meta(&path, url).is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(with_suffix(Path::new("/a/b.pdf"), ".part"), Path::new("/a/b.pdf.part"));
+    }
+}
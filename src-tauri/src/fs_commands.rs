@@ -1,44 +1,17 @@
 use base64::{Engine, engine::general_purpose::STANDARD};
-use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
+use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use std::process::Command;
 use std::sync::Mutex;
-use tauri::Emitter;
-
-pub const ALLOWED_HOSTS: &[&str] = &[
-    "api.anthropic.com",
-    "api.openai.com",
-    "generativelanguage.googleapis.com",
-    "shoulde.rs",
-    "api.github.com",
-    "api.crossref.org",
-    "api.exa.ai",
-    "api.openalex.org",
-];
-
-pub fn validate_url_host(raw_url: &str) -> Result<(), String> {
-    let parsed = url::Url::parse(raw_url)
-        .map_err(|e| format!("Invalid URL: {}", e))?;
-    let host = parsed.host_str()
-        .ok_or_else(|| "URL has no host".to_string())?;
-
-    // Allow localhost / 127.0.0.1 in debug builds only
-    #[cfg(debug_assertions)]
-    {
-        if host == "localhost" || host == "127.0.0.1" {
-            return Ok(());
-        }
-    }
 
-    if ALLOWED_HOSTS.contains(&host) {
-        Ok(())
-    } else {
-        Err(format!("URL host not in allowlist: {}", host))
-    }
-}
+use crate::host_allowlist::validate_url_host;
+use crate::http_cache::{self, HttpCacheState};
+use crate::http_retry::{self, HostLimiterState, Retry, RetryPolicy};
+use crate::ignore::IgnoreMatcher;
+use crate::sandbox::SandboxState;
 
 #[derive(Serialize, Clone)]
 pub struct FileEntry {
@@ -47,22 +20,57 @@ pub struct FileEntry {
     pub is_dir: bool,
     pub children: Option<Vec<FileEntry>>,
     pub modified: Option<u64>,
+    pub created: Option<u64>,
+    /// File size in bytes (files only)
+    pub size: Option<u64>,
+    /// Number of visible entries inside a directory
+    pub child_count: Option<usize>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DirPage {
+    pub entries: Vec<FileEntry>,
+    /// Visible entries in the directory, across all pages
+    pub total: usize,
+    pub offset: usize,
+    pub has_more: bool,
+}
+
+struct WatchSession {
+    _watcher: RecommendedWatcher,
 }
 
 pub struct WatcherState {
-    pub watcher: Mutex<Option<RecommendedWatcher>>,
+    watchers: Mutex<HashMap<u32, WatchSession>>,
+    next_id: Mutex<u32>,
 }
 
 impl Default for WatcherState {
     fn default() -> Self {
         Self {
-            watcher: Mutex::new(None),
+            watchers: Mutex::new(HashMap::new()),
+            next_id: Mutex::new(1),
         }
     }
 }
 
-fn build_file_tree(dir: &Path) -> Result<Vec<FileEntry>, String> {
-    let mut entries = Vec::new();
+/// Entries hidden from the file tree, content search and the search index
+/// regardless of ignore files: hidden directories, node_modules, target and
+/// .DS_Store.
+pub(crate) fn is_skipped_entry(name: &str, is_dir: bool) -> bool {
+    (name.starts_with('.') && is_dir) || name == "node_modules" || name == "target" || name == ".DS_Store"
+}
+
+struct DirItem {
+    name: String,
+    path: std::path::PathBuf,
+    is_dir: bool,
+}
+
+/// Entries of one directory that survive the skip and ignore rules, sorted
+/// directories first, then alphabetically.
+fn list_visible(dir: &Path, ignore: &IgnoreMatcher) -> Result<Vec<DirItem>, String> {
+    let mut items = Vec::new();
     let read_dir = fs::read_dir(dir).map_err(|e| e.to_string())?;
 
     for entry in read_dir {
@@ -70,42 +78,14 @@ fn build_file_tree(dir: &Path) -> Result<Vec<FileEntry>, String> {
         let path = entry.path();
         let name = entry.file_name().to_string_lossy().to_string();
 
-        // Skip hidden directories, node_modules, target, .DS_Store
-        if name.starts_with('.') && path.is_dir() {
-            continue;
-        }
-        if name == "node_modules" || name == "target" || name == ".DS_Store" {
+        let is_dir = path.is_dir();
+        if is_skipped_entry(&name, is_dir) || ignore.is_ignored(&path) {
             continue;
         }
-
-        let is_dir = path.is_dir();
-        let children = if is_dir {
-            Some(build_file_tree(&path)?)
-        } else {
-            None
-        };
-
-        let modified = if !is_dir {
-            fs::metadata(&path)
-                .and_then(|m| m.modified())
-                .ok()
-                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                .map(|d| d.as_secs())
-        } else {
-            None
-        };
-
-        entries.push(FileEntry {
-            name,
-            path: path.to_string_lossy().to_string(),
-            is_dir,
-            children,
-            modified,
-        });
+        items.push(DirItem { name, path, is_dir });
     }
 
-    // Sort: directories first, then alphabetical
-    entries.sort_by(|a, b| {
+    items.sort_by(|a, b| {
         if a.is_dir == b.is_dir {
             a.name.to_lowercase().cmp(&b.name.to_lowercase())
         } else if a.is_dir {
@@ -115,38 +95,287 @@ fn build_file_tree(dir: &Path) -> Result<Vec<FileEntry>, String> {
         }
     });
 
+    Ok(items)
+}
+
+fn unix_secs(time: std::io::Result<std::time::SystemTime>) -> Option<u64> {
+    time.ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+}
+
+fn file_entry(item: DirItem, children: Option<Vec<FileEntry>>, child_count: Option<usize>) -> FileEntry {
+    let meta = fs::metadata(&item.path).ok();
+    let (modified, created, size) = match (&meta, item.is_dir) {
+        (Some(m), false) => (unix_secs(m.modified()), unix_secs(m.created()), Some(m.len())),
+        (Some(m), true) => (None, unix_secs(m.created()), None),
+        (None, _) => (None, None, None),
+    };
+
+    FileEntry {
+        name: item.name,
+        path: item.path.to_string_lossy().to_string(),
+        is_dir: item.is_dir,
+        children,
+        modified,
+        created,
+        size,
+        child_count,
+    }
+}
+
+fn build_file_tree(dir: &Path, ignore: &IgnoreMatcher) -> Result<Vec<FileEntry>, String> {
+    let mut entries = Vec::new();
+
+    for item in list_visible(dir, ignore)? {
+        let (children, child_count) = if item.is_dir {
+            let children = build_file_tree(&item.path, ignore)?;
+            let count = children.len();
+            (Some(children), Some(count))
+        } else {
+            (None, None)
+        };
+        entries.push(file_entry(item, children, child_count));
+    }
+
     Ok(entries)
 }
 
+/// One level of `dir`, starting at `offset`. Directories carry a child count
+/// instead of children.
+fn list_dir_page(dir: &Path, offset: usize, limit: Option<usize>) -> Result<DirPage, String> {
+    let ignore = IgnoreMatcher::new(dir);
+    let items = list_visible(dir, &ignore)?;
+    let total = items.len();
+    let end = limit.map_or(total, |l| offset.saturating_add(l).min(total));
+
+    let entries = items
+        .into_iter()
+        .skip(offset)
+        .take(end.saturating_sub(offset))
+        .map(|item| {
+            let child_count = if item.is_dir {
+                list_visible(&item.path, &ignore).map(|c| c.len()).ok()
+            } else {
+                None
+            };
+            file_entry(item, None, child_count)
+        })
+        .collect();
+
+    Ok(DirPage {
+        entries,
+        total,
+        offset,
+        has_more: end < total,
+    })
+}
+
+/// Hex SHA-256 of file content, used to detect changes between a read and a later write.
+pub(crate) fn content_hash(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    hex::encode(Sha256::digest(data))
+}
+
+/// Write `data` to a temp file next to `path` and fsync it, keeping the original
+/// file's permissions. The caller renames it into place (same directory, so the
+/// rename is atomic) or removes it.
+pub(crate) fn write_temp_sibling(path: &Path, data: &[u8]) -> std::io::Result<std::path::PathBuf> {
+    use std::io::Write;
+
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let tmp = parent.join(format!(".{}.{}.tmp", name, uuid::Uuid::new_v4().simple()));
+
+    let result = (|| {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        if let Ok(meta) = fs::metadata(path) {
+            fs::set_permissions(&tmp, meta.permissions())?;
+        }
+        Ok(())
+    })();
+
+    match result {
+        Ok(()) => Ok(tmp),
+        Err(e) => {
+            let _ = fs::remove_file(&tmp);
+            Err(e)
+        }
+    }
+}
+
 #[tauri::command]
-pub async fn read_dir_recursive(path: String) -> Result<Vec<FileEntry>, String> {
-    build_file_tree(Path::new(&path))
+pub async fn read_dir_recursive(sandbox: tauri::State<'_, SandboxState>, path: String) -> Result<Vec<FileEntry>, String> {
+    sandbox.check(&path)?;
+    let dir = Path::new(&path);
+    build_file_tree(dir, &IgnoreMatcher::new(dir))
+}
+
+/// What a conditional write expects to find on disk. Either field may be set;
+/// every field that is set must match.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct ExpectedVersion {
+    pub modified_ms: Option<u64>,
+    pub hash: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct FileVersion {
+    /// Modification time in Unix milliseconds
+    pub modified_ms: u64,
+    /// SHA-256 of the content
+    pub hash: String,
+}
+
+#[derive(Serialize)]
+pub struct WriteConflict {
+    /// Always `"conflict"`, so the frontend can tell it apart from a plain message
+    pub kind: &'static str,
+    pub path: String,
+    /// What is on disk now; `None` if the file no longer exists
+    pub current: Option<FileVersion>,
 }
 
+/// Write errors. Plain failures serialize as a string like every other command
+/// error; a failed version check serializes as a `WriteConflict` object.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum WriteError {
+    Io(String),
+    Conflict(WriteConflict),
+}
+
+impl From<String> for WriteError {
+    fn from(e: String) -> Self {
+        WriteError::Io(e)
+    }
+}
+
+fn modified_ms(meta: &fs::Metadata) -> u64 {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn current_version(path: &Path) -> Option<FileVersion> {
+    let meta = fs::metadata(path).ok()?;
+    let data = fs::read(path).ok()?;
+    Some(FileVersion {
+        modified_ms: modified_ms(&meta),
+        hash: content_hash(&data),
+    })
+}
+
+/// Write `data` to `path` via a temp file, fsync and rename, so a crash never
+/// leaves a truncated file. With `expected`, the write is rejected if the file on
+/// disk no longer matches. Files in a workspace get a local history snapshot
+/// (see history.rs).
+pub(crate) fn write_atomic(path: &Path, data: &[u8], expected: Option<ExpectedVersion>) -> Result<FileVersion, WriteError> {
+    // Write through symlinks rather than replacing them
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+
+    if let Some(expected) = expected {
+        let current = current_version(&target);
+        let matches = current.as_ref().is_some_and(|cur| {
+            expected.modified_ms.is_none_or(|m| m == cur.modified_ms)
+                && expected.hash.as_ref().is_none_or(|h| *h == cur.hash)
+        });
+        if !matches {
+            return Err(WriteError::Conflict(WriteConflict {
+                kind: "conflict",
+                path: path.to_string_lossy().to_string(),
+                current,
+            }));
+        }
+    }
+
+    let pending = crate::history::PendingSnapshot::before_write(&target, data.len());
+    let tmp = write_temp_sibling(&target, data).map_err(|e| format!("Write error: {}", e))?;
+    if let Err(e) = fs::rename(&tmp, &target) {
+        let _ = fs::remove_file(&tmp);
+        return Err(WriteError::Io(format!("Write error: {}", e)));
+    }
+    if let Some(pending) = pending {
+        pending.after_write(data);
+    }
+
+    let meta = fs::metadata(&target).map_err(|e| e.to_string())?;
+    Ok(FileVersion {
+        modified_ms: modified_ms(&meta),
+        hash: content_hash(data),
+    })
+}
+
+/// List a single directory level (lazy tree loading).
+#[tauri::command]
+pub async fn read_dir(sandbox: tauri::State<'_, SandboxState>, path: String) -> Result<Vec<FileEntry>, String> {
+    sandbox.check(&path)?;
+    Ok(list_dir_page(Path::new(&path), 0, None)?.entries)
+}
+
+/// Paginated single-level listing for very large folders.
 #[tauri::command]
-pub async fn read_file(path: String) -> Result<String, String> {
+pub async fn read_dir_page(
+    sandbox: tauri::State<'_, SandboxState>,
+    path: String,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<DirPage, String> {
+    sandbox.check(&path)?;
+    list_dir_page(Path::new(&path), offset.unwrap_or(0), Some(limit.unwrap_or(200)))
+}
+
+#[tauri::command]
+pub async fn read_file(sandbox: tauri::State<'_, SandboxState>, path: String) -> Result<String, String> {
+    sandbox.check(&path)?;
     fs::read_to_string(&path).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn read_file_base64(path: String) -> Result<String, String> {
+pub async fn read_file_base64(sandbox: tauri::State<'_, SandboxState>, path: String) -> Result<String, String> {
+    sandbox.check(&path)?;
     let bytes = fs::read(&path).map_err(|e| e.to_string())?;
     Ok(STANDARD.encode(&bytes))
 }
 
+/// Current version of a file, to pass back as `expected` on a later write.
 #[tauri::command]
-pub async fn write_file(path: String, content: String) -> Result<(), String> {
-    fs::write(&path, &content).map_err(|e| e.to_string())
+pub async fn file_version(sandbox: tauri::State<'_, SandboxState>, path: String) -> Result<FileVersion, String> {
+    sandbox.check(&path)?;
+    current_version(Path::new(&path)).ok_or_else(|| format!("Cannot read {}", path))
+}
+
+#[tauri::command]
+pub async fn write_file(
+    sandbox: tauri::State<'_, SandboxState>,
+    path: String,
+    content: String,
+    expected: Option<ExpectedVersion>,
+) -> Result<FileVersion, WriteError> {
+    sandbox.check_write(&path)?;
+    write_atomic(Path::new(&path), content.as_bytes(), expected)
 }
 
 #[tauri::command]
-pub async fn write_file_base64(path: String, data: String) -> Result<(), String> {
+pub async fn write_file_base64(
+    sandbox: tauri::State<'_, SandboxState>,
+    path: String,
+    data: String,
+    expected: Option<ExpectedVersion>,
+) -> Result<FileVersion, WriteError> {
+    sandbox.check_write(&path)?;
     let bytes = STANDARD.decode(&data).map_err(|e| format!("Base64 decode error: {}", e))?;
-    fs::write(&path, &bytes).map_err(|e| format!("Write error: {}", e))
+    write_atomic(Path::new(&path), &bytes, expected)
 }
 
 #[tauri::command]
-pub async fn create_file(path: String, content: String) -> Result<(), String> {
+pub async fn create_file(sandbox: tauri::State<'_, SandboxState>, path: String, content: String) -> Result<(), String> {
+    sandbox.check_write(&path)?;
     if Path::new(&path).exists() {
         return Err("File already exists".to_string());
     }
@@ -154,39 +383,57 @@ pub async fn create_file(path: String, content: String) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub async fn create_dir(path: String) -> Result<(), String> {
+pub async fn create_dir(sandbox: tauri::State<'_, SandboxState>, path: String) -> Result<(), String> {
+    sandbox.check_write(&path)?;
     fs::create_dir_all(&path).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn rename_path(old_path: String, new_path: String) -> Result<(), String> {
+pub async fn rename_path(sandbox: tauri::State<'_, SandboxState>, old_path: String, new_path: String) -> Result<(), String> {
+    sandbox.check_write(&old_path)?;
+    sandbox.check_write(&new_path)?;
     fs::rename(&old_path, &new_path).map_err(|e| e.to_string())
 }
 
+/// Move `path` to the workspace trash (`.shoulders/trash`) and return its trash
+/// entry. With `hard`, delete it permanently instead and return `None`.
 #[tauri::command]
-pub async fn delete_path(path: String) -> Result<(), String> {
+pub async fn delete_path(
+    sandbox: tauri::State<'_, SandboxState>,
+    path: String,
+    hard: Option<bool>,
+) -> Result<Option<crate::trash::TrashItem>, String> {
+    sandbox.check_write(&path)?;
     let p = Path::new(&path);
+    if !hard.unwrap_or(false) {
+        return crate::trash::move_to_trash(p).map(Some);
+    }
     if p.is_dir() {
-        fs::remove_dir_all(p).map_err(|e| e.to_string())
+        fs::remove_dir_all(p).map_err(|e| e.to_string())?;
     } else {
-        fs::remove_file(p).map_err(|e| e.to_string())
+        fs::remove_file(p).map_err(|e| e.to_string())?;
     }
+    Ok(None)
 }
 
 #[tauri::command]
-pub async fn copy_file(src: String, dest: String) -> Result<(), String> {
+pub async fn copy_file(sandbox: tauri::State<'_, SandboxState>, src: String, dest: String) -> Result<(), String> {
+    sandbox.check(&src)?;
+    sandbox.check_write(&dest)?;
     fs::copy(&src, &dest).map_err(|e| e.to_string())?;
     Ok(())
 }
 
 #[tauri::command]
-pub async fn copy_dir(src: String, dest: String) -> Result<(), String> {
+pub async fn copy_dir(sandbox: tauri::State<'_, SandboxState>, src: String, dest: String) -> Result<(), String> {
+    sandbox.check(&src)?;
+    sandbox.check_write(&dest)?;
     let src = Path::new(&src);
     let dest = Path::new(&dest);
     copy_dir_recursive(src, dest).map_err(|e| e.to_string())
 }
 
-fn copy_dir_recursive(src: &Path, dest: &Path) -> std::io::Result<()> {
+pub(crate) fn copy_dir_recursive(src: &Path, dest: &Path) -> std::io::Result<()> {
     fs::create_dir_all(dest)?;
     for entry in fs::read_dir(src)? {
         let entry = entry?;
@@ -211,49 +458,44 @@ pub async fn path_exists(path: String) -> Result<bool, String> {
     Ok(Path::new(&path).exists())
 }
 
+/// Start a recursive watcher on `path` and return its id. Several watchers can
+/// run at once (one per workspace window, plus external folders); every
+/// `fs-change` payload carries the `watcher_id` it came from.
 #[tauri::command]
 pub async fn watch_directory(
     app: tauri::AppHandle,
     state: tauri::State<'_, WatcherState>,
+    sandbox: tauri::State<'_, SandboxState>,
     path: String,
-) -> Result<(), String> {
-    let app_clone = app.clone();
-
-    let mut watcher = RecommendedWatcher::new(
-        move |res: Result<Event, notify::Error>| {
-            match res {
-                Ok(event) => {
-                    let paths: Vec<String> = event
-                        .paths
-                        .iter()
-                        .map(|p| p.to_string_lossy().to_string())
-                        .collect();
-                    let _ = app_clone.emit(
-                        "fs-change",
-                        serde_json::json!({
-                            "kind": format!("{:?}", event.kind),
-                            "paths": paths,
-                        }),
-                    );
-                }
-                Err(e) => eprintln!("[fs-watch] error: {:?}", e),
-            }
-        },
-        Config::default(),
-    )
-    .map_err(|e| e.to_string())?;
+) -> Result<u32, String> {
+    sandbox.check(&path)?;
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = RecommendedWatcher::new(tx, Config::default()).map_err(|e| e.to_string())?;
 
     watcher
         .watch(Path::new(&path), RecursiveMode::Recursive)
         .map_err(|e| e.to_string())?;
 
-    *state.watcher.lock().unwrap() = Some(watcher);
-    Ok(())
+    let id = {
+        let mut id_lock = state.next_id.lock().unwrap();
+        let id = *id_lock;
+        *id_lock += 1;
+        id
+    };
+    state
+        .watchers
+        .lock()
+        .unwrap()
+        .insert(id, WatchSession { _watcher: watcher });
+
+    std::thread::spawn(move || crate::fs_events::run_debouncer(app, id, path, rx));
+    Ok(id)
 }
 
+/// Stop one watcher. Dropping it closes its channel, which ends its debounce thread.
 #[tauri::command]
-pub async fn unwatch_directory(state: tauri::State<'_, WatcherState>) -> Result<(), String> {
-    *state.watcher.lock().unwrap() = None;
+pub async fn unwatch_directory(state: tauri::State<'_, WatcherState>, id: u32) -> Result<(), String> {
+    state.watchers.lock().unwrap().remove(&id);
     Ok(())
 }
 
@@ -263,21 +505,56 @@ pub struct ApiProxyRequest {
     pub method: String,
     pub headers: HashMap<String, String>,
     pub body: String,
+    /// `body` is base64-encoded bytes, for binary uploads
+    #[serde(default)]
+    pub body_base64: bool,
+    /// Return the response body base64-encoded, for binary downloads
+    #[serde(default)]
+    pub response_base64: bool,
+    /// Retry, timeout and per-host concurrency settings
+    #[serde(default)]
+    pub retry: RetryPolicy,
 }
 
-#[tauri::command]
-pub async fn proxy_api_call(request: ApiProxyRequest) -> Result<String, String> {
-    validate_url_host(&request.url)?;
+/// Result of `proxy_api_request`. Any status is a result, not an error.
+#[derive(Serialize)]
+pub struct ApiProxyResponse {
+    pub status: u16,
+    /// Status is 2xx
+    pub ok: bool,
+    /// Lowercase header names; repeated headers are joined with ", "
+    pub headers: HashMap<String, String>,
+    pub body: String,
+    /// `body` is base64-encoded (the request set `response_base64`)
+    pub body_base64: bool,
+    /// Attempts retried before this response
+    pub retries: u32,
+}
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .map_err(|e| e.to_string())?;
+struct ProxyResponse {
+    status: u16,
+    headers: reqwest::header::HeaderMap,
+    body: Vec<u8>,
+    retries: u32,
+}
+
+impl ProxyResponse {
+    fn body_string(&self, base64: bool) -> String {
+        if base64 {
+            STANDARD.encode(&self.body)
+        } else {
+            String::from_utf8_lossy(&self.body).to_string()
+        }
+    }
+}
 
+fn build_proxy_request(client: &reqwest::Client, request: &ApiProxyRequest, body: &[u8]) -> Result<reqwest::RequestBuilder, String> {
     let mut req = match request.method.as_str() {
         "POST" => client.post(&request.url),
         "GET" => client.get(&request.url),
         "PUT" => client.put(&request.url),
+        "PATCH" => client.patch(&request.url),
+        "DELETE" => client.delete(&request.url),
         _ => return Err(format!("Unsupported method: {}", request.method)),
     };
 
@@ -285,39 +562,443 @@ pub async fn proxy_api_call(request: ApiProxyRequest) -> Result<String, String>
         req = req.header(key.as_str(), value.as_str());
     }
 
-    if !request.body.is_empty() {
-        req = req.body(request.body);
+    if !body.is_empty() {
+        req = req.body(body.to_vec());
+    }
+    Ok(req)
+}
+
+/// Send a proxied request. GET requests go through the HTTP response cache.
+/// 429 and 5xx responses are retried with backoff per `request.retry`, and
+/// requests to one host share a concurrency limit, so batch lookups queue
+/// instead of failing. Errors only when no response arrived.
+async fn send_proxy_request(
+    cache: &HttpCacheState,
+    limiter: &HostLimiterState,
+    request: &ApiProxyRequest,
+) -> Result<ProxyResponse, String> {
+    validate_url_host(&request.url)?;
+    let host = url::Url::parse(&request.url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_string()))
+        .unwrap_or_default();
+    let policy = &request.retry;
+    let body = if request.body_base64 {
+        STANDARD.decode(request.body.trim()).map_err(|e| format!("Invalid base64 body: {}", e))?
+    } else {
+        request.body.as_bytes().to_vec()
+    };
+
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(policy.timeout_secs.max(1)))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let idempotent = http_retry::is_idempotent(&request.method);
+    let _permit = limiter.acquire(&host, policy.max_concurrent).await?;
+
+    let mut attempt = 0;
+    loop {
+        let req = build_proxy_request(&client, request, &body)?;
+        let headers = || request.headers.iter().map(|(k, v)| (k.as_str(), v.as_str()));
+        let result = if request.method == "GET" && !http_cache::has_credentials(headers()) {
+            let key = http_cache::cache_key(&request.url, headers());
+            http_cache::get(cache, &key, &request.url, req, usize::MAX)
+                .await
+                .map(|r| (r.status, r.headers, r.body))
+        } else {
+            match req.send().await {
+                Ok(response) => {
+                    let status = response.status().as_u16();
+                    let headers = response.headers().clone();
+                    response.bytes().await.map(|body| (status, headers, body.to_vec())).map_err(|e| e.to_string())
+                }
+                Err(e) => Err(e.to_string()),
+            }
+        };
+
+        let retry = match &result {
+            Ok((status, headers, _)) => policy.on_status(attempt, *status, headers, idempotent),
+            Err(_) => policy.on_error(attempt, idempotent),
+        };
+        match retry {
+            Retry::After(wait) => {
+                tokio::time::sleep(wait).await;
+                attempt += 1;
+            }
+            Retry::GiveUp => {
+                let (status, headers, body) = result?;
+                return Ok(ProxyResponse { status, headers, body, retries: attempt });
+            }
+        }
     }
+}
 
-    let response = req.send().await.map_err(|e| e.to_string())?;
-    let status = response.status().as_u16();
-    let body = response.text().await.map_err(|e| e.to_string())?;
+/// Proxy a request and return its body; non-2xx statuses are an
+/// `API error {status}: {body}` string.
+#[tauri::command]
+pub async fn proxy_api_call(
+    cache: tauri::State<'_, HttpCacheState>,
+    limiter: tauri::State<'_, HostLimiterState>,
+    request: ApiProxyRequest,
+) -> Result<String, String> {
+    let response = send_proxy_request(&cache, &limiter, &request).await?;
+    let status = response.status;
+    let body = response.body_string(request.response_base64);
 
     if status >= 200 && status < 300 {
         Ok(body)
+    } else if response.retries > 0 {
+        Err(format!("API error {} (after {} retries): {}", status, response.retries, body))
     } else {
         Err(format!("API error {}: {}", status, body))
     }
 }
 
+/// Proxy a request and return status, headers and body. Only transport
+/// failures (blocked host, no connection, timeout) are errors.
+#[tauri::command]
+pub async fn proxy_api_request(
+    cache: tauri::State<'_, HttpCacheState>,
+    limiter: tauri::State<'_, HostLimiterState>,
+    request: ApiProxyRequest,
+) -> Result<ApiProxyResponse, String> {
+    let response = send_proxy_request(&cache, &limiter, &request).await?;
+    let mut headers: HashMap<String, String> = HashMap::new();
+    for (name, value) in &response.headers {
+        let value = String::from_utf8_lossy(value.as_bytes()).to_string();
+        headers
+            .entry(name.as_str().to_string())
+            .and_modify(|existing| {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            })
+            .or_insert(value);
+    }
+
+    Ok(ApiProxyResponse {
+        status: response.status,
+        ok: (200..300).contains(&response.status),
+        headers,
+        body: response.body_string(request.response_base64),
+        body_base64: request.response_base64,
+        retries: response.retries,
+    })
+}
+
 #[derive(Serialize, Clone)]
 pub struct SearchResult {
     pub path: String,
     pub name: String,
     pub line: usize,
+    /// 1-based character column of the first match in the untrimmed line
+    pub column: usize,
     pub text: String,
+    /// Match spans within `text`
+    pub matches: Vec<TextRange>,
+}
+
+/// Highlight span within a result's text, as character offsets (end exclusive).
+#[derive(Serialize, Clone)]
+pub struct TextRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct SearchOptions {
+    /// Treat the query as a regular expression (regex-lite syntax)
+    pub regex: bool,
+    pub whole_word: bool,
+    pub case_sensitive: bool,
+    /// Glob patterns relative to the search root; when non-empty, only matching files are searched
+    pub include: Vec<String>,
+    /// Glob patterns relative to the search root; matching files and folders are skipped
+    pub exclude: Vec<String>,
+}
+
+enum MatchPattern {
+    Literal { needle: Vec<char>, case_sensitive: bool, whole_word: bool },
+    Regex(regex_lite::Regex),
+}
+
+/// Finds match spans within a line. Plain-text queries are matched per character
+/// so case folding and word boundaries work beyond ASCII; regex queries use
+/// regex-lite, whose `(?i)` and `\b` are ASCII-only.
+pub(crate) struct ContentMatcher {
+    pattern: MatchPattern,
+}
+
+fn fold_char(c: char, case_sensitive: bool) -> char {
+    if case_sensitive {
+        c
+    } else {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl ContentMatcher {
+    pub(crate) fn new(query: &str, options: &SearchOptions) -> Result<Self, String> {
+        if query.is_empty() {
+            return Err("Search query is empty".to_string());
+        }
+
+        let pattern = if options.regex {
+            let mut pattern = query.to_string();
+            if options.whole_word {
+                pattern = format!(r"\b(?:{})\b", pattern);
+            }
+            if !options.case_sensitive {
+                pattern = format!("(?i){}", pattern);
+            }
+            let re = regex_lite::Regex::new(&pattern).map_err(|e| format!("Invalid regex: {}", e))?;
+            MatchPattern::Regex(re)
+        } else {
+            MatchPattern::Literal {
+                needle: query.chars().map(|c| fold_char(c, options.case_sensitive)).collect(),
+                case_sensitive: options.case_sensitive,
+                whole_word: options.whole_word,
+            }
+        };
+
+        Ok(Self { pattern })
+    }
+
+    /// Non-overlapping match spans in `line`, as character offsets.
+    pub(crate) fn find_ranges(&self, line: &str) -> Vec<TextRange> {
+        let mut ranges = Vec::new();
+
+        match &self.pattern {
+            MatchPattern::Literal { needle, case_sensitive, whole_word } => {
+                let hay: Vec<char> = line.chars().map(|c| fold_char(c, *case_sensitive)).collect();
+                let n = needle.len();
+                let mut i = 0;
+                while i + n <= hay.len() {
+                    let bounded = !*whole_word
+                        || ((i == 0 || !is_word_char(hay[i - 1]))
+                            && (i + n == hay.len() || !is_word_char(hay[i + n])));
+                    if hay[i..i + n] == needle[..] && bounded {
+                        ranges.push(TextRange { start: i, end: i + n });
+                        i += n;
+                    } else {
+                        i += 1;
+                    }
+                }
+            }
+            MatchPattern::Regex(re) => {
+                for m in re.find_iter(line) {
+                    if m.start() == m.end() {
+                        continue;
+                    }
+                    let start = line[..m.start()].chars().count();
+                    let len = line[m.start()..m.end()].chars().count();
+                    ranges.push(TextRange { start, end: start + len });
+                }
+            }
+        }
+
+        ranges
+    }
+
+    /// Replace every match in `line`; regex replacements may reference groups
+    /// as `$1` or `$name`. Returns the new line and the number of replacements.
+    pub(crate) fn replace_line(&self, line: &str, replacement: &str) -> (String, usize) {
+        let mut out = String::with_capacity(line.len());
+
+        match &self.pattern {
+            MatchPattern::Literal { .. } => {
+                let ranges = self.find_ranges(line);
+                let chars: Vec<char> = line.chars().collect();
+                let mut last = 0;
+                for r in &ranges {
+                    out.extend(&chars[last..r.start]);
+                    out.push_str(replacement);
+                    last = r.end;
+                }
+                out.extend(&chars[last..]);
+                (out, ranges.len())
+            }
+            MatchPattern::Regex(re) => {
+                let mut last = 0;
+                let mut count = 0;
+                for caps in re.captures_iter(line) {
+                    let m = match caps.get(0) {
+                        Some(m) if m.start() < m.end() => m,
+                        _ => continue,
+                    };
+                    out.push_str(&line[last..m.start()]);
+                    caps.expand(replacement, &mut out);
+                    last = m.end();
+                    count += 1;
+                }
+                out.push_str(&line[last..]);
+                (out, count)
+            }
+        }
+    }
+}
+
+/// Translate a glob into an anchored regex over `/`-separated relative paths.
+/// `*` and `?` stay within a path segment, `**` crosses segments, `{a,b}`
+/// alternates. Patterns without a `/` match at any depth (`*.bib`), and a
+/// pattern naming a folder also covers everything inside it.
+pub(crate) fn glob_to_regex(glob: &str) -> Result<regex_lite::Regex, String> {
+    let trimmed = glob.trim().trim_end_matches('/');
+    let anchored = trimmed.starts_with('/');
+    let body = trimmed.trim_start_matches('/');
+
+    let mut re = String::from("^");
+    if !anchored && !body.contains('/') {
+        re.push_str("(?:.*/)?");
+    }
+
+    let chars: Vec<char> = body.chars().collect();
+    let mut i = 0;
+    let mut in_group = false;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    re.push_str("(?:.*/)?");
+                    i += 2;
+                } else {
+                    re.push_str(".*");
+                    i += 1;
+                }
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            '{' => {
+                re.push_str("(?:");
+                in_group = true;
+            }
+            '}' if in_group => {
+                re.push(')');
+                in_group = false;
+            }
+            ',' if in_group => re.push('|'),
+            _ => re.push_str(&regex_lite::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    re.push_str("(?:/.*)?$");
+
+    regex_lite::Regex::new(&re).map_err(|e| format!("Invalid glob '{}': {}", glob, e))
+}
+
+/// Include/exclude glob filters evaluated against paths relative to the search root.
+pub(crate) struct PathFilter {
+    include: Vec<regex_lite::Regex>,
+    exclude: Vec<regex_lite::Regex>,
+}
+
+impl PathFilter {
+    pub(crate) fn new(include: &[String], exclude: &[String]) -> Result<Self, String> {
+        let compile = |globs: &[String]| -> Result<Vec<regex_lite::Regex>, String> {
+            globs.iter().filter(|g| !g.trim().is_empty()).map(|g| glob_to_regex(g)).collect()
+        };
+        Ok(Self {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    fn relative(root: &Path, path: &Path) -> String {
+        path.strip_prefix(root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+
+    pub(crate) fn is_excluded(&self, root: &Path, path: &Path) -> bool {
+        let rel = Self::relative(root, path);
+        self.exclude.iter().any(|re| re.is_match(&rel))
+    }
+
+    pub(crate) fn is_included(&self, root: &Path, path: &Path) -> bool {
+        if self.include.is_empty() {
+            return true;
+        }
+        let rel = Self::relative(root, path);
+        self.include.iter().any(|re| re.is_match(&rel))
+    }
 }
 
 #[tauri::command]
-pub async fn search_file_contents(dir: String, query: String, max_results: usize) -> Result<Vec<SearchResult>, String> {
-    let query_lower = query.to_lowercase();
+pub async fn search_file_contents(
+    sandbox: tauri::State<'_, SandboxState>,
+    dir: String,
+    query: String,
+    max_results: usize,
+    options: Option<SearchOptions>,
+) -> Result<Vec<SearchResult>, String> {
+    sandbox.check(&dir)?;
+    let options = options.unwrap_or_default();
+    let matcher = ContentMatcher::new(&query, &options)?;
+    let filter = PathFilter::new(&options.include, &options.exclude)?;
+    let root = Path::new(&dir);
+    let ignore = IgnoreMatcher::new(root);
+
     let mut results = Vec::new();
-    search_dir_contents(Path::new(&dir), &query_lower, &mut results, max_results)?;
+    walk_search_files(root, root, &filter, &ignore, &mut |path, name| {
+        let content = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(_) => return true,
+        };
+        for (i, line) in content.lines().enumerate() {
+            let ranges = matcher.find_ranges(line);
+            if ranges.is_empty() {
+                continue;
+            }
+
+            // Ranges are reported against the trimmed text shown to the user
+            let indent = line.chars().count() - line.trim_start().chars().count();
+            let text = line.trim().to_string();
+            let text_len = text.chars().count();
+            let matches = ranges
+                .iter()
+                .map(|r| TextRange {
+                    start: r.start.saturating_sub(indent).min(text_len),
+                    end: r.end.saturating_sub(indent).min(text_len),
+                })
+                .filter(|r| r.start < r.end)
+                .collect();
+
+            results.push(SearchResult {
+                path: path.to_string_lossy().to_string(),
+                name: name.to_string(),
+                line: i + 1,
+                column: ranges[0].start + 1,
+                text,
+                matches,
+            });
+            if results.len() >= max_results {
+                return false;
+            }
+        }
+        true
+    });
     Ok(results)
 }
 
+/// Run a fixed internal command and return its output. Goes through the same
+/// command policy as `shell_spawn`; `approved` is for user-initiated actions.
 #[tauri::command]
-pub async fn run_shell_command(cwd: String, command: String) -> Result<String, String> {
+pub async fn run_shell_command(
+    sandbox: tauri::State<'_, SandboxState>,
+    cwd: String,
+    command: String,
+    approved: Option<bool>,
+) -> Result<String, String> {
+    sandbox.check(&cwd)?;
+    crate::command_policy::gate(&sandbox, None, &cwd, &command, approved.unwrap_or(false))
+        .map_err(|d| format!("Blocked by command policy: {}", d.reason))?;
     #[cfg(unix)]
     let output = Command::new("bash")
         .args(&["-c", &command])
@@ -350,20 +1031,22 @@ pub async fn run_shell_command(cwd: String, command: String) -> Result<String, S
     Ok(result)
 }
 
-fn search_dir_contents(dir: &Path, query: &str, results: &mut Vec<SearchResult>, max_results: usize) -> Result<(), String> {
-    if results.len() >= max_results {
-        return Ok(());
-    }
-
+/// Visit every searchable text file under `dir` that passes `filter` and isn't
+/// ignored, in directory order. The visitor returns false to stop the walk; so
+/// does this function.
+pub(crate) fn walk_search_files(
+    root: &Path,
+    dir: &Path,
+    filter: &PathFilter,
+    ignore: &IgnoreMatcher,
+    visit: &mut dyn FnMut(&Path, &str) -> bool,
+) -> bool {
     let read_dir = match fs::read_dir(dir) {
         Ok(d) => d,
-        Err(_) => return Ok(()),
+        Err(_) => return true,
     };
 
     for entry in read_dir {
-        if results.len() >= max_results {
-            break;
-        }
         let entry = match entry {
             Ok(e) => e,
             Err(_) => continue,
@@ -371,102 +1054,190 @@ fn search_dir_contents(dir: &Path, query: &str, results: &mut Vec<SearchResult>,
         let path = entry.path();
         let name = entry.file_name().to_string_lossy().to_string();
 
-        // Skip hidden dirs, node_modules, target
-        if name.starts_with('.') && path.is_dir() {
-            continue;
-        }
-        if name == "node_modules" || name == "target" {
+        if is_skipped_entry(&name, path.is_dir()) || filter.is_excluded(root, &path) || ignore.is_ignored(&path) {
             continue;
         }
 
         if path.is_dir() {
-            search_dir_contents(&path, query, results, max_results)?;
-        } else if is_searchable_text(&name) {
-            if let Ok(content) = fs::read_to_string(&path) {
-                for (i, line) in content.lines().enumerate() {
-                    if line.to_lowercase().contains(query) {
-                        results.push(SearchResult {
-                            path: path.to_string_lossy().to_string(),
-                            name: name.clone(),
-                            line: i + 1,
-                            text: line.trim().to_string(),
-                        });
-                        if results.len() >= max_results {
-                            break;
-                        }
-                    }
-                }
+            if !walk_search_files(root, &path, filter, ignore, visit) {
+                return false;
             }
+        } else if is_searchable_text(&name) && filter.is_included(root, &path) && !visit(&path, &name) {
+            return false;
         }
     }
-    Ok(())
+    true
 }
 
-#[tauri::command]
-pub async fn fetch_url_content(url: String) -> Result<String, String> {
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .map_err(|e| e.to_string())?;
+/// Readable content of a fetched URL.
+#[derive(Serialize)]
+pub struct FetchedPage {
+    pub url: String,
+    /// URL after redirects
+    pub final_url: String,
+    /// MIME type without parameters; sniffed from the body when the server
+    /// sends none or `application/octet-stream`
+    pub content_type: String,
+    pub title: Option<String>,
+    /// Page count, for PDFs
+    pub pages: Option<usize>,
+    /// Markdown for HTML, plain text for PDF and text, pretty-printed JSON;
+    /// truncated at 50KB
+    pub content: String,
+    /// Bibliographic `citation_*` meta tags, when an HTML page has them
+    pub citation: Option<crate::html_extract::CitationMeta>,
+}
 
-    let response = client
-        .get(&url)
-        .header("User-Agent", "Shoulders/1.0")
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
+/// Largest response body fetch_url_content will download
+const MAX_FETCH_BYTES: usize = 30 * 1024 * 1024;
 
-    let status = response.status().as_u16();
-    if status < 200 || status >= 300 {
-        return Err(format!("HTTP error {}", status));
-    }
+#[derive(PartialEq)]
+enum FetchedKind {
+    Html,
+    Pdf,
+    Json,
+    Text,
+    Binary,
+}
 
-    let html = response.text().await.map_err(|e| e.to_string())?;
-    let text = strip_html(&html);
+/// What to do with a response body, from its declared MIME type, falling back
+/// to the body itself. A `%PDF` body is a PDF whatever the server says.
+fn classify_content(mime: &str, body: &[u8]) -> (FetchedKind, String) {
+    if body.starts_with(b"%PDF") {
+        return (FetchedKind::Pdf, "application/pdf".to_string());
+    }
+    let kind = match mime {
+        "text/html" | "application/xhtml+xml" => FetchedKind::Html,
+        "application/pdf" | "application/x-pdf" => FetchedKind::Pdf,
+        m if m.ends_with("/json") || m.ends_with("+json") => FetchedKind::Json,
+        m if m.starts_with("text/")
+            || m.ends_with("/xml")
+            || m.ends_with("+xml")
+            || m == "application/javascript"
+            || m == "application/x-bibtex"
+            || m == "application/x-research-info-systems" =>
+        {
+            FetchedKind::Text
+        }
+        "" | "application/octet-stream" | "binary/octet-stream" => {
+            let head = String::from_utf8_lossy(&body[..body.len().min(1024)]).trim_start().to_lowercase();
+            if head.starts_with("<!doctype html") || head.starts_with("<html") {
+                return (FetchedKind::Html, "text/html".to_string());
+            }
+            if (head.starts_with('{') || head.starts_with('[')) && serde_json::from_slice::<serde_json::Value>(body).is_ok() {
+                return (FetchedKind::Json, "application/json".to_string());
+            }
+            if std::str::from_utf8(body).is_ok() && !body.contains(&0) {
+                return (FetchedKind::Text, "text/plain".to_string());
+            }
+            FetchedKind::Binary
+        }
+        _ => FetchedKind::Binary,
+    };
+    let mime = if mime.is_empty() { "application/octet-stream" } else { mime };
+    (kind, mime.to_string())
+}
 
-    // Truncate at 50KB
-    if text.len() > 50_000 {
-        let mut t = text;
-        t.truncate(50_000);
-        t.push_str("\n... [truncated at 50KB]");
-        Ok(t)
-    } else {
-        Ok(text)
+/// Cut `text` to at most `max` bytes on a character boundary, with a note.
+fn truncate_text(mut text: String, max: usize) -> String {
+    if text.len() <= max {
+        return text;
     }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    text.push_str(&format!("\n... [truncated at {}KB]", max / 1000));
+    text
 }
 
-fn strip_html(html: &str) -> String {
-    use regex_lite::Regex;
+pub(crate) async fn read_body_limited(mut response: reqwest::Response, max: usize) -> Result<Vec<u8>, String> {
+    let too_large = || format!("Response is larger than {}MB", max / (1024 * 1024));
+    if response.content_length().is_some_and(|len| len > max as u64) {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        body.extend_from_slice(&chunk);
+        if body.len() > max {
+            return Err(too_large());
+        }
+    }
+    Ok(body)
+}
 
-    // Remove <script> and <style> blocks
-    let re_script = Regex::new(r"(?is)<script[\s>].*?</script>").unwrap();
-    let text = re_script.replace_all(html, "");
-    let re_style = Regex::new(r"(?is)<style[\s>].*?</style>").unwrap();
-    let text = re_style.replace_all(&text, "");
-    let re_nav = Regex::new(r"(?is)<(nav|header|footer)[\s>].*?</\1>").unwrap();
-    let text = re_nav.replace_all(&text, "");
+/// Fetch a URL for the AI and return its readable content. HTML goes through
+/// the article extractor, PDFs through the text extractor, JSON is pretty-printed
+/// and other text passes through. Binary types (images, archives) are an error.
+/// Raw responses go through the HTTP response cache.
+#[tauri::command]
+pub async fn fetch_url_content(
+    cache: tauri::State<'_, HttpCacheState>,
+    url: String,
+) -> Result<FetchedPage, String> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()
+        .map_err(|e| e.to_string())?;
 
-    // Strip all remaining HTML tags
-    let re_tags = Regex::new(r"<[^>]+>").unwrap();
-    let text = re_tags.replace_all(&text, "");
+    let user_agent = "Shoulders/1.0";
+    let request = client.get(&url).header("User-Agent", user_agent);
+    let key = http_cache::cache_key(&url, [("User-Agent", user_agent)]);
+    let response = http_cache::get(&cache, &key, &url, request, MAX_FETCH_BYTES).await?;
 
-    // Decode common HTML entities
-    let text = text
-        .replace("&amp;", "&")
-        .replace("&lt;", "<")
-        .replace("&gt;", ">")
-        .replace("&quot;", "\"")
-        .replace("&#39;", "'")
-        .replace("&apos;", "'")
-        .replace("&nbsp;", " ");
+    let status = response.status;
+    if status < 200 || status >= 300 {
+        return Err(format!("HTTP error {}", status));
+    }
 
-    // Collapse whitespace: multiple newlines → double newline, multiple spaces → single
-    let re_newlines = Regex::new(r"\n{3,}").unwrap();
-    let text = re_newlines.replace_all(&text, "\n\n");
-    let re_spaces = Regex::new(r"[ \t]{2,}").unwrap();
-    let text = re_spaces.replace_all(&text, " ");
+    let final_url = response.final_url;
+    let mime = response
+        .content_type
+        .split(';')
+        .next()
+        .map(|v| v.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    let body = response.body;
+    let (kind, content_type) = classify_content(&mime, &body);
+
+    let (title, pages, content, citation) = match kind {
+        FetchedKind::Html => {
+            let base = final_url.clone();
+            let article = tokio::task::spawn_blocking(move || {
+                crate::html_extract::extract(&String::from_utf8_lossy(&body), Some(&base))
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+            (article.title, None, article.markdown, article.citation)
+        }
+        FetchedKind::Pdf => {
+            let pdf = tokio::task::spawn_blocking(move || crate::pdf_text::extract_text(&body))
+                .await
+                .map_err(|e| e.to_string())??;
+            (pdf.title, Some(pdf.pages), pdf.text, None)
+        }
+        FetchedKind::Json => {
+            let text = serde_json::from_slice::<serde_json::Value>(&body)
+                .and_then(|v| serde_json::to_string_pretty(&v))
+                .unwrap_or_else(|_| String::from_utf8_lossy(&body).to_string());
+            (None, None, text, None)
+        }
+        FetchedKind::Text => (None, None, String::from_utf8_lossy(&body).to_string(), None),
+        FetchedKind::Binary => {
+            return Err(format!("Unsupported content type {}: binary content cannot be shown as text", content_type));
+        }
+    };
 
-    text.trim().to_string()
+    Ok(FetchedPage {
+        url,
+        final_url,
+        content_type,
+        title,
+        pages,
+        content: truncate_text(content, 50_000),
+        citation,
+    })
 }
 
 #[tauri::command]
@@ -479,7 +1250,7 @@ pub async fn get_global_config_dir() -> Result<String, String> {
     Ok(dir.to_string_lossy().to_string())
 }
 
-fn is_searchable_text(name: &str) -> bool {
+pub(crate) fn is_searchable_text(name: &str) -> bool {
     let name_lower = name.to_lowercase();
     let extensions = [
         ".md", ".txt", ".js", ".ts", ".jsx", ".tsx", ".py", ".r", ".rs",
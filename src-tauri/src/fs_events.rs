@@ -0,0 +1,259 @@
+use crate::ignore::{is_ignore_source, IgnoreMatcher};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+/// Quiet period before a batch is emitted
+const DEBOUNCE: Duration = Duration::from_millis(200);
+/// Upper bound on how long a continuous burst can delay a batch
+const MAX_DELAY: Duration = Duration::from_millis(1000);
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Removed,
+    Renamed,
+}
+
+#[derive(Serialize, Clone)]
+pub struct FsChange {
+    pub kind: ChangeKind,
+    /// The affected path; for renames, the new path
+    pub path: String,
+    /// Previous path, renames only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+}
+
+/// Payload of the `fs-change` event. `paths` is every path touched by the
+/// batch (both sides of a rename), for listeners that only need to match names.
+#[derive(Serialize, Clone)]
+pub struct FsChangeBatch {
+    /// Id returned by the `watch_directory` call that produced this batch
+    pub watcher_id: u32,
+    pub paths: Vec<String>,
+    pub changes: Vec<FsChange>,
+}
+
+struct Pending {
+    seq: u64,
+    kind: ChangeKind,
+    from: Option<PathBuf>,
+}
+
+/// Changes accumulated during one debounce window, one entry per path.
+#[derive(Default)]
+struct Batch {
+    entries: HashMap<PathBuf, Pending>,
+    next_seq: u64,
+}
+
+impl Batch {
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn insert(&mut self, path: PathBuf, kind: ChangeKind, from: Option<PathBuf>) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.entries.insert(path, Pending { seq, kind, from });
+    }
+
+    /// Fold a new change into whatever is already pending for the path.
+    fn record(&mut self, path: PathBuf, kind: ChangeKind) {
+        use ChangeKind::*;
+        let prev = self.entries.get(&path).map(|p| (p.kind, p.from.clone()));
+        match (prev, kind) {
+            (None, _) => self.insert(path, kind, None),
+            // Created and gone again within the window: nothing happened
+            (Some((Created, _)), Removed) => {
+                self.entries.remove(&path);
+            }
+            (Some((Renamed, from)), Removed) => {
+                self.entries.remove(&path);
+                if let Some(from) = from {
+                    self.insert(from, Removed, None);
+                }
+            }
+            (Some((Created, _)), _) | (Some((Renamed, _)), _) => {}
+            (Some((Removed, _)), Created) | (Some((Removed, _)), Modified) => {
+                self.insert(path, Modified, None)
+            }
+            (Some((Modified, _)), Created) => {}
+            (Some(_), _) => self.insert(path, kind, None),
+        }
+    }
+
+    fn record_rename(&mut self, from: PathBuf, to: PathBuf) {
+        // A paired rename supersedes the From/To halves some backends also emit
+        let origin = match self.entries.remove(&from) {
+            Some(Pending { kind: ChangeKind::Created, .. }) => {
+                self.insert(to, ChangeKind::Created, None);
+                return;
+            }
+            Some(Pending { kind: ChangeKind::Renamed, from: Some(orig), .. }) => orig,
+            _ => from,
+        };
+        self.insert(to, ChangeKind::Renamed, Some(origin));
+    }
+
+    fn record_event(&mut self, event: Event) {
+        let kind = match event.kind {
+            EventKind::Access(_) => return,
+            EventKind::Create(_) => ChangeKind::Created,
+            EventKind::Remove(_) => ChangeKind::Removed,
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                let mut paths = event.paths.into_iter();
+                if let (Some(from), Some(to)) = (paths.next(), paths.next()) {
+                    self.record_rename(from, to);
+                }
+                return;
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => ChangeKind::Removed,
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => ChangeKind::Created,
+            // Unpaired renames (FSEvents) and unknown kinds: decide by what is on disk now
+            EventKind::Modify(ModifyKind::Name(_)) => {
+                for path in event.paths {
+                    let kind = if path.exists() { ChangeKind::Created } else { ChangeKind::Removed };
+                    self.record(path, kind);
+                }
+                return;
+            }
+            EventKind::Modify(_) => ChangeKind::Modified,
+            EventKind::Any | EventKind::Other => {
+                for path in event.paths {
+                    let kind = if path.exists() { ChangeKind::Modified } else { ChangeKind::Removed };
+                    self.record(path, kind);
+                }
+                return;
+            }
+        };
+        for path in event.paths {
+            self.record(path, kind);
+        }
+    }
+
+    fn take(&mut self) -> Vec<(PathBuf, Pending)> {
+        let mut entries: Vec<_> = self.entries.drain().collect();
+        entries.sort_by_key(|(_, p)| p.seq);
+        entries
+    }
+}
+
+/// Apply the tree's ignore rules. `.shoulders/` is always delivered: the app
+/// watches its own state files there even when the folder is git-ignored.
+/// A rename across the ignore boundary becomes a plain create or remove.
+fn visible_changes(
+    root: &Path,
+    ignore: &IgnoreMatcher,
+    entries: Vec<(PathBuf, Pending)>,
+) -> Vec<(PathBuf, ChangeKind, Option<PathBuf>)> {
+    let shoulders = root.join(".shoulders");
+    let visible = |p: &Path| p.starts_with(&shoulders) || !ignore.is_ignored(p);
+
+    entries
+        .into_iter()
+        .filter_map(|(path, pending)| match (pending.kind, pending.from) {
+            (ChangeKind::Renamed, Some(from)) => match (visible(&from), visible(&path)) {
+                (true, true) => Some((path, ChangeKind::Renamed, Some(from))),
+                (true, false) => Some((from, ChangeKind::Removed, None)),
+                (false, true) => Some((path, ChangeKind::Created, None)),
+                (false, false) => None,
+            },
+            (kind, _) => visible(&path).then_some((path, kind, None)),
+        })
+        .collect()
+}
+
+fn flush(
+    app: &tauri::AppHandle,
+    watcher_id: u32,
+    root: &str,
+    ignore: &mut Option<IgnoreMatcher>,
+    batch: &mut Batch,
+) {
+    let entries = batch.take();
+    if entries.is_empty() {
+        return;
+    }
+
+    let root_path = Path::new(root);
+    if ignore.is_none() || entries.iter().any(|(p, _)| is_ignore_source(p)) {
+        *ignore = Some(IgnoreMatcher::new(root_path));
+    }
+    let changes = match ignore {
+        Some(matcher) => visible_changes(root_path, matcher, entries),
+        None => return,
+    };
+    if changes.is_empty() {
+        return;
+    }
+
+    let touched: Vec<PathBuf> = changes
+        .iter()
+        .flat_map(|(path, _, from)| std::iter::once(path.clone()).chain(from.clone()))
+        .collect();
+    crate::search_index::update_paths(app, root, &touched);
+
+    let payload = FsChangeBatch {
+        watcher_id,
+        paths: touched.iter().map(|p| p.to_string_lossy().to_string()).collect(),
+        changes: changes
+            .into_iter()
+            .map(|(path, kind, from)| FsChange {
+                kind,
+                path: path.to_string_lossy().to_string(),
+                from: from.map(|f| f.to_string_lossy().to_string()),
+            })
+            .collect(),
+    };
+    let _ = app.emit("fs-change", payload);
+}
+
+/// Drain raw notify events for `root`, emitting one coalesced `fs-change`
+/// batch after each quiet period. Returns when the watcher (the sender) is dropped.
+pub(crate) fn run_debouncer(
+    app: tauri::AppHandle,
+    watcher_id: u32,
+    root: String,
+    rx: Receiver<notify::Result<Event>>,
+) {
+    let mut batch = Batch::default();
+    let mut ignore: Option<IgnoreMatcher> = None;
+    let mut first_at = Instant::now();
+
+    loop {
+        let received = if batch.is_empty() {
+            rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            let deadline = first_at + MAX_DELAY;
+            let wait = DEBOUNCE.min(deadline.saturating_duration_since(Instant::now()));
+            rx.recv_timeout(wait)
+        };
+
+        match received {
+            Ok(Ok(event)) => {
+                if batch.is_empty() {
+                    first_at = Instant::now();
+                }
+                batch.record_event(event);
+                if !batch.is_empty() && first_at.elapsed() >= MAX_DELAY {
+                    flush(&app, watcher_id, &root, &mut ignore, &mut batch);
+                }
+            }
+            Ok(Err(e)) => eprintln!("[fs-watch] error: {:?}", e),
+            Err(RecvTimeoutError::Timeout) => flush(&app, watcher_id, &root, &mut ignore, &mut batch),
+            Err(RecvTimeoutError::Disconnected) => {
+                flush(&app, watcher_id, &root, &mut ignore, &mut batch);
+                break;
+            }
+        }
+    }
+}
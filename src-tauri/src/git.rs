@@ -3,7 +3,7 @@ use git2::{
     Cred, DiffOptions, FetchOptions, IndexAddOption, Oid, PushOptions, RemoteCallbacks,
     Repository, Signature, Sort, StatusOptions,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
 fn open_repo(repo_path: &str) -> Result<Repository, String> {
@@ -69,107 +69,838 @@ pub async fn git_add_all(repo_path: String) -> Result<(), String> {
     Ok(())
 }
 
-#[tauri::command]
-pub async fn git_commit(repo_path: String, message: String) -> Result<String, String> {
-    let repo = open_repo(&repo_path)?;
-    let sig =
-        Signature::now("Shoulders", "shoulders@local").map_err(|e| e.message().to_string())?;
+/// Per-call overrides for `git_commit`. Every field is optional.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct CommitOptions {
+    /// Author for this commit; the committer stays the configured identity
+    pub author_name: Option<String>,
+    pub author_email: Option<String>,
+    /// `Name <email>` entries, appended as `Co-authored-by:` trailers
+    pub co_authors: Vec<String>,
+    /// Sign the commit. Unset follows `commit.gpgsign`.
+    pub sign: Option<bool>,
+}
 
-    let mut index = repo.index().map_err(|e| e.message().to_string())?;
-    let tree_oid = index
-        .write_tree()
-        .map_err(|e| e.message().to_string())?;
-    let tree = repo
-        .find_tree(tree_oid)
-        .map_err(|e| e.message().to_string())?;
+/// Identity from `user.name`/`user.email` (repo config, then global, then
+/// system), or "Shoulders" when none is configured.
+fn config_signature(repo: &Repository) -> Result<Signature<'static>, String> {
+    match repo.signature() {
+        Ok(sig) => Ok(sig.to_owned()),
+        Err(_) => Signature::now("Shoulders", "shoulders@local").map_err(|e| e.message().to_string()),
+    }
+}
 
-    let parent_commit = match repo.head() {
-        Ok(head) => {
-            let commit = head
-                .peel_to_commit()
-                .map_err(|e| e.message().to_string())?;
-            Some(commit)
+fn with_co_authors(message: &str, co_authors: &[String]) -> Result<String, String> {
+    if co_authors.is_empty() {
+        return Ok(message.to_string());
+    }
+    let mut message = message.trim_end().to_string();
+    message.push_str("\n\n");
+    for co_author in co_authors {
+        let co_author = co_author.trim();
+        let valid = co_author
+            .split_once('<')
+            .is_some_and(|(name, rest)| !name.trim().is_empty() && rest.ends_with('>') && rest.len() > 1);
+        if !valid {
+            return Err(format!("Co-author must look like 'Name <email>', got '{}'", co_author));
+        }
+        message.push_str(&format!("Co-authored-by: {}\n", co_author));
+    }
+    Ok(message)
+}
+
+/// How long a signing program may run, including any passphrase prompt it
+/// shows, before it is killed and the commit fails.
+const SIGN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+
+/// How git would sign a commit: the program and key from `gpg.format`,
+/// `gpg.program` / `gpg.ssh.program` / `gpg.x509.program` and `user.signingkey`.
+struct SigningConfig {
+    ssh: bool,
+    program: String,
+    key: String,
+}
+
+impl SigningConfig {
+    fn read(config: &git2::Config, committer: &Signature) -> Result<Self, String> {
+        let format = config.get_string("gpg.format").unwrap_or_else(|_| "openpgp".to_string());
+        let signing_key = config.get_string("user.signingkey").ok();
+
+        if format == "ssh" {
+            let program = config
+                .get_string("gpg.ssh.program")
+                .unwrap_or_else(|_| "ssh-keygen".to_string());
+            let key = signing_key.ok_or_else(|| "SSH signing needs user.signingkey".to_string())?;
+            return Ok(SigningConfig { ssh: true, program, key });
+        }
+
+        let program = match format.as_str() {
+            "openpgp" => config.get_string("gpg.program").unwrap_or_else(|_| "gpg".to_string()),
+            "x509" => config
+                .get_string("gpg.x509.program")
+                .unwrap_or_else(|_| "gpgsm".to_string()),
+            other => return Err(format!("Unsupported gpg.format '{}'", other)),
+        };
+        // Without user.signingkey, git signs with the key matching the committer
+        let key = signing_key.unwrap_or_else(|| {
+            format!("{} <{}>", committer.name().unwrap_or(""), committer.email().unwrap_or(""))
+        });
+        Ok(SigningConfig { ssh: false, program, key })
+    }
+}
+
+/// Sign a commit buffer the way git does. Returns the armored signature for the
+/// `gpgsig` header. The signing program can block on a passphrase prompt, so it
+/// runs on a blocking thread and is killed after `SIGN_TIMEOUT`.
+async fn sign_commit_buffer(signing: SigningConfig, content: String) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || run_signing(&signing, &content))
+        .await
+        .map_err(|e| format!("Signing failed: {}", e))?
+}
+
+fn run_signing(signing: &SigningConfig, content: &str) -> Result<String, String> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let program = &signing.program;
+    if signing.ssh {
+        let key = &signing.key;
+        let dir = std::env::temp_dir().join(format!("shoulders-sign-{}", uuid::Uuid::new_v4().simple()));
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        let result = (|| {
+            // A literal public key (the key itself lives in ssh-agent) goes to a file
+            let key_file = match key.strip_prefix("key::") {
+                Some(literal) => Some(literal.to_string()),
+                None if key.starts_with("ssh-") || key.starts_with("ecdsa-") || key.starts_with("sk-") => Some(key.clone()),
+                None => None,
+            };
+            let key_path = match key_file {
+                Some(literal) => {
+                    let path = dir.join("key.pub");
+                    std::fs::write(&path, literal).map_err(|e| e.to_string())?;
+                    path
+                }
+                None => match key.strip_prefix("~/") {
+                    Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+                    None => std::path::PathBuf::from(key),
+                },
+            };
+
+            let buffer = dir.join("commit");
+            std::fs::write(&buffer, content).map_err(|e| e.to_string())?;
+            let child = Command::new(program)
+                .args(["-Y", "sign", "-n", "git", "-f"])
+                .arg(&key_path)
+                .arg(&buffer)
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .map_err(|e| format!("Cannot run {}: {}", program, e))?;
+            let output = wait_for_signer(child, program)?;
+            if !output.status.success() {
+                return Err(format!(
+                    "SSH signing failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ));
+            }
+            std::fs::read_to_string(dir.join("commit.sig")).map_err(|e| format!("SSH signing failed: {}", e))
+        })();
+        let _ = std::fs::remove_dir_all(&dir);
+        return result;
+    }
+
+    let mut child = Command::new(program)
+        .args(["--status-fd=2", "-bsau", &signing.key])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Cannot run {}: {}", program, e))?;
+    let written = child
+        .stdin
+        .take()
+        .ok_or_else(|| "Cannot write to signing program".to_string())
+        .and_then(|mut stdin| {
+            stdin
+                .write_all(content.as_bytes())
+                .map_err(|e| format!("Cannot write to {}: {}", program, e))
+        });
+    if let Err(e) = written {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(e);
+    }
+    let output = wait_for_signer(child, program)?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() || !stderr.contains("[GNUPG:] SIG_CREATED") {
+        return Err(format!("Signing failed: {}", stderr.trim()));
+    }
+    String::from_utf8(output.stdout).map_err(|_| "Signing program returned invalid output".to_string())
+}
+
+/// `wait_with_output`, but killing the program once `SIGN_TIMEOUT` has passed.
+fn wait_for_signer(mut child: std::process::Child, program: &str) -> Result<std::process::Output, String> {
+    use std::io::Read;
+
+    // Drain the pipes on their own threads so a chatty program can't stall on a full pipe
+    let drain = |pipe: Option<Box<dyn Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut buf);
+            }
+            buf
+        })
+    };
+    let stdout = drain(child.stdout.take().map(|p| Box::new(p) as Box<dyn Read + Send>));
+    let stderr = drain(child.stderr.take().map(|p| Box::new(p) as Box<dyn Read + Send>));
+
+    let deadline = std::time::Instant::now() + SIGN_TIMEOUT;
+    let status = loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => break status,
+            None if std::time::Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("{} did not finish signing within {} seconds", program, SIGN_TIMEOUT.as_secs()));
+            }
+            None => std::thread::sleep(std::time::Duration::from_millis(50)),
         }
-        Err(_) => None, // Initial commit — no parent
     };
+    Ok(std::process::Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// Point HEAD's branch at `oid` (creating it on the first commit), or move a
+/// detached HEAD. `repo.commit(Some("HEAD"), …)` does this itself; signed
+/// commits are created without touching refs.
+fn advance_head(repo: &Repository, oid: Oid, summary: &str) -> Result<(), String> {
+    let head = repo
+        .find_reference("HEAD")
+        .map_err(|e| e.message().to_string())?;
+    let log_message = format!("commit: {}", summary);
+    match head.symbolic_target() {
+        Some(branch_ref) => {
+            repo.reference(branch_ref, oid, true, &log_message)
+                .map_err(|e| e.message().to_string())?;
+        }
+        None => repo
+            .set_head_detached(oid)
+            .map_err(|e| e.message().to_string())?,
+    }
+    Ok(())
+}
 
-    let parents: Vec<&git2::Commit> = parent_commit.iter().collect();
+/// A commit that still needs its signature: the buffer `commit_create_buffer`
+/// produced and how to sign it.
+struct UnsignedCommit {
+    content: String,
+    signing: SigningConfig,
+}
 
+/// Commit `tree` on top of `parents` and move HEAD to it. `sign` unset follows
+/// `commit.gpgsign`. Unsigned commits are written right away; signed ones come
+/// back as an `UnsignedCommit` for `write_signed_commit`, since the git2 objects
+/// here can't be held across an await.
+fn create_commit(
+    repo: &Repository,
+    author: &Signature,
+    committer: &Signature,
+    message: &str,
+    tree: &git2::Tree,
+    parents: &[&git2::Commit],
+    sign: Option<bool>,
+) -> Result<Result<Oid, UnsignedCommit>, String> {
+    let config = repo.config().map_err(|e| e.message().to_string())?;
+    if !sign.unwrap_or_else(|| config.get_bool("commit.gpgsign").unwrap_or(false)) {
+        let oid = repo
+            .commit(Some("HEAD"), author, committer, message, tree, parents)
+            .map_err(|e| e.message().to_string())?;
+        return Ok(Ok(oid));
+    }
+    let buffer = repo
+        .commit_create_buffer(author, committer, message, tree, parents)
+        .map_err(|e| e.message().to_string())?;
+    let content = buffer
+        .as_str()
+        .ok_or_else(|| "Commit is not valid UTF-8".to_string())?
+        .to_string();
+    let signing = SigningConfig::read(&config, committer)?;
+    Ok(Err(UnsignedCommit { content, signing }))
+}
+
+/// Sign an `UnsignedCommit`, write it and move HEAD to it.
+async fn write_signed_commit(repo: &mut Repository, unsigned: UnsignedCommit, summary: &str) -> Result<Oid, String> {
+    let UnsignedCommit { content, signing } = unsigned;
+    let signature = sign_commit_buffer(signing, content.clone()).await?;
     let oid = repo
-        .commit(Some("HEAD"), &sig, &sig, &message, &tree, &parents)
+        .commit_signed(&content, &signature, Some("gpgsig"))
         .map_err(|e| e.message().to_string())?;
+    advance_head(repo, oid, summary)?;
+    Ok(oid)
+}
 
+/// Commit the index. Author and committer come from the git config (see
+/// `config_signature`); `options` can override the author, add co-author
+/// trailers and turn signing on or off. Returns the commit hash.
+#[tauri::command]
+pub async fn git_commit(
+    repo_path: String,
+    message: String,
+    options: Option<CommitOptions>,
+) -> Result<String, String> {
+    let mut repo = open_repo(&repo_path)?;
+    let options = options.unwrap_or_default();
+    let message = with_co_authors(&message, &options.co_authors)?;
+
+    let created = {
+        let committer = config_signature(&repo)?;
+        let author = if options.author_name.is_some() || options.author_email.is_some() {
+            let name = options
+                .author_name
+                .as_deref()
+                .unwrap_or(committer.name().unwrap_or("Shoulders"));
+            let email = options
+                .author_email
+                .as_deref()
+                .unwrap_or(committer.email().unwrap_or("shoulders@local"));
+            Signature::now(name, email).map_err(|e| e.message().to_string())?
+        } else {
+            committer.clone()
+        };
+
+        let mut index = repo.index().map_err(|e| e.message().to_string())?;
+        let tree_oid = index
+            .write_tree()
+            .map_err(|e| e.message().to_string())?;
+        let tree = repo
+            .find_tree(tree_oid)
+            .map_err(|e| e.message().to_string())?;
+
+        let parent_commit = match repo.head() {
+            Ok(head) => {
+                let commit = head
+                    .peel_to_commit()
+                    .map_err(|e| e.message().to_string())?;
+                Some(commit)
+            }
+            Err(_) => None, // Initial commit — no parent
+        };
+
+        let parents: Vec<&git2::Commit> = parent_commit.iter().collect();
+
+        create_commit(&repo, &author, &committer, &message, &tree, &parents, options.sign)?
+    };
+
+    let oid = match created {
+        Ok(oid) => oid,
+        Err(unsigned) => write_signed_commit(&mut repo, unsigned, message.lines().next().unwrap_or("")).await?,
+    };
     Ok(oid.to_string())
 }
 
+/// One changed path. `index` compares HEAD to the index (what a commit would
+/// contain), `worktree` compares the index to the files on disk. Each is one of
+/// "added", "modified", "deleted", "renamed", "typechange", or `None` when that
+/// side is unchanged. Untracked files have `worktree: "untracked"`.
+#[derive(Serialize, Clone)]
+pub struct StatusEntry {
+    /// Relative to the repo root; the new path for renames
+    pub path: String,
+    /// Previous path of a renamed file
+    pub orig_path: Option<String>,
+    pub index: Option<&'static str>,
+    pub worktree: Option<&'static str>,
+    /// Unmerged path during a merge; `index`/`worktree` are `None`
+    pub conflicted: bool,
+    /// Only reported with `include_ignored`. Ignored folders are listed once, not per file.
+    pub ignored: bool,
+}
+
+fn index_state(status: git2::Status) -> Option<&'static str> {
+    if status.contains(git2::Status::INDEX_NEW) {
+        Some("added")
+    } else if status.contains(git2::Status::INDEX_MODIFIED) {
+        Some("modified")
+    } else if status.contains(git2::Status::INDEX_DELETED) {
+        Some("deleted")
+    } else if status.contains(git2::Status::INDEX_RENAMED) {
+        Some("renamed")
+    } else if status.contains(git2::Status::INDEX_TYPECHANGE) {
+        Some("typechange")
+    } else {
+        None
+    }
+}
+
+fn worktree_state(status: git2::Status) -> Option<&'static str> {
+    if status.contains(git2::Status::WT_NEW) {
+        Some("untracked")
+    } else if status.contains(git2::Status::WT_MODIFIED) {
+        Some("modified")
+    } else if status.contains(git2::Status::WT_DELETED) {
+        Some("deleted")
+    } else if status.contains(git2::Status::WT_RENAMED) {
+        Some("renamed")
+    } else if status.contains(git2::Status::WT_TYPECHANGE) {
+        Some("typechange")
+    } else {
+        None
+    }
+}
+
+/// Changed, untracked and conflicted paths, with renames detected in both the
+/// index and the working tree. An empty list means a clean tree.
 #[tauri::command]
-pub async fn git_status(repo_path: String) -> Result<String, String> {
+pub async fn git_status(
+    repo_path: String,
+    include_ignored: Option<bool>,
+) -> Result<Vec<StatusEntry>, String> {
     let repo = open_repo(&repo_path)?;
     let mut opts = StatusOptions::new();
     opts.include_untracked(true)
-        .recurse_untracked_dirs(true);
+        .recurse_untracked_dirs(true)
+        .renames_head_to_index(true)
+        .renames_index_to_workdir(true)
+        .include_ignored(include_ignored.unwrap_or(false))
+        .recurse_ignored_dirs(false);
 
     let statuses = repo
         .statuses(Some(&mut opts))
         .map_err(|e| e.message().to_string())?;
 
-    let mut lines = Vec::new();
+    let path_of = |file: git2::DiffFile| file.path().map(|p| p.to_string_lossy().to_string());
+
+    let mut entries = Vec::new();
     for entry in statuses.iter() {
-        let path = entry.path().unwrap_or("");
         let status = entry.status();
-        let code = status_to_porcelain(status);
-        if !code.is_empty() {
-            lines.push(format!("{} {}", code, path));
+        if status.is_empty() || status == git2::Status::CURRENT {
+            continue;
         }
+
+        // For renames, the delta's new file is the current path
+        let head_to_index = entry.head_to_index();
+        let index_to_workdir = entry.index_to_workdir();
+        let path = index_to_workdir
+            .as_ref()
+            .and_then(|d| path_of(d.new_file()))
+            .or_else(|| head_to_index.as_ref().and_then(|d| path_of(d.new_file())))
+            .or_else(|| entry.path().map(|p| p.to_string()))
+            .unwrap_or_default();
+        let orig_path = if status.contains(git2::Status::INDEX_RENAMED) {
+            head_to_index.as_ref().and_then(|d| path_of(d.old_file()))
+        } else if status.contains(git2::Status::WT_RENAMED) {
+            index_to_workdir.as_ref().and_then(|d| path_of(d.old_file()))
+        } else {
+            None
+        };
+
+        let conflicted = status.contains(git2::Status::CONFLICTED);
+        entries.push(StatusEntry {
+            path,
+            orig_path,
+            index: if conflicted { None } else { index_state(status) },
+            worktree: if conflicted { None } else { worktree_state(status) },
+            conflicted,
+            ignored: status.contains(git2::Status::IGNORED),
+        });
     }
 
-    Ok(lines.join("\n"))
+    Ok(entries)
 }
 
-fn status_to_porcelain(status: git2::Status) -> String {
-    let index_char = if status.contains(git2::Status::INDEX_NEW) {
-        'A'
-    } else if status.contains(git2::Status::INDEX_MODIFIED) {
-        'M'
-    } else if status.contains(git2::Status::INDEX_DELETED) {
-        'D'
-    } else if status.contains(git2::Status::INDEX_RENAMED) {
-        'R'
-    } else {
-        ' '
+#[tauri::command]
+pub async fn git_branch(repo_path: String) -> Result<String, String> {
+    let repo = open_repo(&repo_path)?;
+    let result = match repo.head() {
+        Ok(head) => head.shorthand().unwrap_or("HEAD").to_string(),
+        Err(_) => String::new(),
     };
+    Ok(result)
+}
 
-    let wt_char = if status.contains(git2::Status::WT_NEW) {
-        '?'
-    } else if status.contains(git2::Status::WT_MODIFIED) {
-        'M'
-    } else if status.contains(git2::Status::WT_DELETED) {
-        'D'
-    } else {
-        ' '
+// ── Branches ──
+
+#[derive(Serialize, Clone)]
+pub struct BranchInfo {
+    /// `main` for a local branch, `origin/main` for a remote-tracking one
+    pub name: String,
+    /// Remote name for remote-tracking branches
+    pub remote: Option<String>,
+    pub is_head: bool,
+    /// Upstream of a local branch, e.g. `origin/main`
+    pub upstream: Option<String>,
+    pub hash: String,
+    pub date: String,
+    pub message: String,
+}
+
+/// A checkout that would overwrite uncommitted changes. Serializes as an object
+/// so the frontend can list the files; other failures stay plain strings.
+#[derive(Serialize)]
+pub struct CheckoutBlocked {
+    /// Always `"blocked"`
+    pub kind: &'static str,
+    pub branch: String,
+    /// Paths relative to the repo root
+    pub files: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum CheckoutError {
+    Git(String),
+    Blocked(CheckoutBlocked),
+}
+
+impl From<String> for CheckoutError {
+    fn from(e: String) -> Self {
+        CheckoutError::Git(e)
+    }
+}
+
+fn commit_date(commit: &git2::Commit) -> String {
+    let time = commit.time();
+    let offset = chrono::FixedOffset::east_opt(time.offset_minutes() * 60)
+        .unwrap_or(chrono::FixedOffset::east_opt(0).unwrap());
+    chrono::DateTime::from_timestamp(time.seconds(), 0)
+        .unwrap_or_default()
+        .with_timezone(&offset)
+        .to_rfc3339()
+}
+
+fn validate_branch_name(name: &str) -> Result<(), String> {
+    if !git2::Branch::name_is_valid(name).unwrap_or(false) {
+        return Err(format!("'{}' is not a valid branch name", name));
+    }
+    Ok(())
+}
+
+/// Switch the working tree and HEAD to `refname`. Uses a safe checkout: local
+/// changes to files that are the same on both branches carry over, and changes
+/// the switch would overwrite block it.
+fn checkout_ref(repo: &Repository, refname: &str, branch: &str) -> Result<(), CheckoutError> {
+    let target = repo
+        .find_reference(refname)
+        .and_then(|r| r.peel_to_tree())
+        .map_err(|e| e.message().to_string())?;
+
+    let mut blocked = Vec::new();
+    let result = {
+        let mut opts = git2::build::CheckoutBuilder::new();
+        opts.safe()
+            .notify_on(git2::CheckoutNotificationType::CONFLICT)
+            .notify(|_, path, _, _, _| {
+                if let Some(path) = path {
+                    blocked.push(path.to_string_lossy().to_string());
+                }
+                true
+            });
+        repo.checkout_tree(target.as_object(), Some(&mut opts))
     };
 
-    // Untracked files: "?? path"
-    if status.contains(git2::Status::WT_NEW) && !status.contains(git2::Status::INDEX_NEW) {
-        return "??".to_string();
+    if !blocked.is_empty() {
+        blocked.sort();
+        return Err(CheckoutError::Blocked(CheckoutBlocked {
+            kind: "blocked",
+            branch: branch.to_string(),
+            files: blocked,
+        }));
     }
+    result.map_err(|e| e.message().to_string())?;
+
+    repo.set_head(refname).map_err(|e| e.message().to_string())?;
+    Ok(())
+}
 
-    let code = format!("{}{}", index_char, wt_char);
-    if code.trim().is_empty() {
-        String::new()
+/// Local branches, then remote-tracking branches when `include_remote` is set.
+#[tauri::command]
+pub async fn git_branch_list(
+    repo_path: String,
+    include_remote: Option<bool>,
+) -> Result<Vec<BranchInfo>, String> {
+    let repo = open_repo(&repo_path)?;
+    let filter = if include_remote.unwrap_or(false) {
+        None
     } else {
-        code
+        Some(git2::BranchType::Local)
+    };
+
+    let mut result = Vec::new();
+    let branches = repo.branches(filter).map_err(|e| e.message().to_string())?;
+    for item in branches {
+        let (branch, kind) = item.map_err(|e| e.message().to_string())?;
+        let reference = branch.get();
+        // Skip symbolic refs such as origin/HEAD
+        if reference.symbolic_target().is_some() {
+            continue;
+        }
+        let name = match branch.name() {
+            Ok(Some(name)) => name.to_string(),
+            _ => continue,
+        };
+        let commit = match reference.peel_to_commit() {
+            Ok(commit) => commit,
+            Err(_) => continue,
+        };
+        let remote = match kind {
+            git2::BranchType::Remote => name.split('/').next().map(|r| r.to_string()),
+            git2::BranchType::Local => None,
+        };
+        let upstream = match kind {
+            git2::BranchType::Local => branch
+                .upstream()
+                .ok()
+                .and_then(|u| u.name().ok().flatten().map(|n| n.to_string())),
+            git2::BranchType::Remote => None,
+        };
+
+        result.push(BranchInfo {
+            is_head: branch.is_head(),
+            upstream,
+            hash: commit.id().to_string(),
+            date: commit_date(&commit),
+            message: commit.summary().unwrap_or("").to_string(),
+            remote,
+            name,
+        });
     }
+
+    // Local before remote, each alphabetical
+    result.sort_by(|a, b| (a.remote.is_some(), &a.name).cmp(&(b.remote.is_some(), &b.name)));
+    Ok(result)
 }
 
+/// Create a local branch at `start_point` (a branch, `origin/branch` or commit
+/// hash; HEAD if omitted). Starting from a remote-tracking branch sets it as
+/// the upstream. With `checkout`, switches to the new branch.
 #[tauri::command]
-pub async fn git_branch(repo_path: String) -> Result<String, String> {
+pub async fn git_branch_create(
+    repo_path: String,
+    name: String,
+    start_point: Option<String>,
+    checkout: Option<bool>,
+) -> Result<(), CheckoutError> {
+    validate_branch_name(&name)?;
     let repo = open_repo(&repo_path)?;
-    let result = match repo.head() {
-        Ok(head) => head.shorthand().unwrap_or("HEAD").to_string(),
-        Err(_) => String::new(),
+    if repo.find_branch(&name, git2::BranchType::Local).is_ok() {
+        return Err(format!("Branch '{}' already exists", name).into());
+    }
+
+    let start = start_point.unwrap_or_else(|| "HEAD".to_string());
+    let commit = repo
+        .revparse_single(&start)
+        .and_then(|obj| obj.peel_to_commit())
+        .map_err(|_| format!("Unknown start point '{}'", start))?;
+    let mut branch = repo
+        .branch(&name, &commit, false)
+        .map_err(|e| e.message().to_string())?;
+    if repo.find_branch(&start, git2::BranchType::Remote).is_ok() {
+        branch
+            .set_upstream(Some(&start))
+            .map_err(|e| e.message().to_string())?;
+    }
+
+    if checkout.unwrap_or(false) {
+        checkout_ref(&repo, &format!("refs/heads/{}", name), &name)?;
+    }
+    Ok(())
+}
+
+/// Switch to a branch. `name` may be a local branch, a remote-tracking branch
+/// (`origin/draft`) or the name of a branch that exists on exactly one remote;
+/// the last two create a local branch tracking it. Fails with a
+/// `CheckoutBlocked` object when uncommitted changes would be overwritten.
+#[tauri::command]
+pub async fn git_checkout_branch(repo_path: String, name: String) -> Result<(), CheckoutError> {
+    let repo = open_repo(&repo_path)?;
+
+    if repo.find_branch(&name, git2::BranchType::Local).is_ok() {
+        let is_head = repo
+            .head()
+            .ok()
+            .is_some_and(|h| h.shorthand() == Some(name.as_str()));
+        if is_head {
+            return Ok(());
+        }
+        return checkout_ref(&repo, &format!("refs/heads/{}", name), &name);
+    }
+
+    // Remote branch: find the tracking ref, then create a local branch for it
+    let remote_name = if repo.find_branch(&name, git2::BranchType::Remote).is_ok() {
+        name.clone()
+    } else {
+        let remotes = repo.remotes().map_err(|e| e.message().to_string())?;
+        let matches: Vec<String> = remotes
+            .iter()
+            .flatten()
+            .map(|remote| format!("{}/{}", remote, name))
+            .filter(|candidate| repo.find_branch(candidate, git2::BranchType::Remote).is_ok())
+            .collect();
+        match matches.len() {
+            0 => return Err(format!("Branch '{}' not found", name).into()),
+            1 => matches.into_iter().next().unwrap(),
+            _ => {
+                return Err(format!(
+                    "Branch '{}' exists on several remotes; use one of: {}",
+                    name,
+                    matches.join(", ")
+                )
+                .into())
+            }
+        }
     };
-    Ok(result)
+
+    let local_name = remote_name
+        .split_once('/')
+        .map(|(_, branch)| branch.to_string())
+        .unwrap_or_else(|| remote_name.clone());
+    if repo.find_branch(&local_name, git2::BranchType::Local).is_ok() {
+        return Err(format!(
+            "A local branch '{}' already exists; check it out instead of {}",
+            local_name, remote_name
+        )
+        .into());
+    }
+
+    let commit = repo
+        .find_branch(&remote_name, git2::BranchType::Remote)
+        .and_then(|b| b.get().peel_to_commit())
+        .map_err(|e| e.message().to_string())?;
+    let mut branch = repo
+        .branch(&local_name, &commit, false)
+        .map_err(|e| e.message().to_string())?;
+    branch
+        .set_upstream(Some(&remote_name))
+        .map_err(|e| e.message().to_string())?;
+
+    let result = checkout_ref(&repo, &format!("refs/heads/{}", local_name), &local_name);
+    if result.is_err() {
+        // Don't leave a branch behind for a switch that didn't happen
+        let _ = branch.delete();
+    }
+    result
+}
+
+/// Rename a local branch. Renaming the current branch keeps it checked out.
+#[tauri::command]
+pub async fn git_branch_rename(
+    repo_path: String,
+    old_name: String,
+    new_name: String,
+    force: Option<bool>,
+) -> Result<(), String> {
+    validate_branch_name(&new_name)?;
+    let repo = open_repo(&repo_path)?;
+    let mut branch = repo
+        .find_branch(&old_name, git2::BranchType::Local)
+        .map_err(|_| format!("Branch '{}' not found", old_name))?;
+    branch
+        .rename(&new_name, force.unwrap_or(false))
+        .map_err(|e| e.message().to_string())?;
+    Ok(())
+}
+
+/// Delete a local branch. Refuses the current branch, and a branch with
+/// commits that are neither in HEAD nor in its upstream unless `force` is set.
+#[tauri::command]
+pub async fn git_branch_delete(
+    repo_path: String,
+    name: String,
+    force: Option<bool>,
+) -> Result<(), String> {
+    let repo = open_repo(&repo_path)?;
+    let mut branch = repo
+        .find_branch(&name, git2::BranchType::Local)
+        .map_err(|_| format!("Branch '{}' not found", name))?;
+    if branch.is_head() {
+        return Err(format!("Cannot delete '{}': it is the current branch", name));
+    }
+
+    if !force.unwrap_or(false) {
+        let tip = branch
+            .get()
+            .peel_to_commit()
+            .map_err(|e| e.message().to_string())?
+            .id();
+        let contains = |oid: Oid| oid == tip || repo.graph_descendant_of(oid, tip).unwrap_or(false);
+        let in_head = repo
+            .head()
+            .ok()
+            .and_then(|h| h.target())
+            .is_some_and(contains);
+        let in_upstream = branch
+            .upstream()
+            .ok()
+            .and_then(|u| u.get().target())
+            .is_some_and(contains);
+        if !in_head && !in_upstream {
+            return Err(format!(
+                "Branch '{}' has commits that are not merged or pushed. Delete it anyway with force.",
+                name
+            ));
+        }
+    }
+
+    branch.delete().map_err(|e| e.message().to_string())?;
+    Ok(())
+}
+
+/// Set or clear (`upstream: None`) the upstream of a local branch, e.g. `origin/main`.
+#[tauri::command]
+pub async fn git_branch_set_upstream(
+    repo_path: String,
+    name: String,
+    upstream: Option<String>,
+) -> Result<(), String> {
+    let repo = open_repo(&repo_path)?;
+    let mut branch = repo
+        .find_branch(&name, git2::BranchType::Local)
+        .map_err(|_| format!("Branch '{}' not found", name))?;
+    branch
+        .set_upstream(upstream.as_deref())
+        .map_err(|e| e.message().to_string())?;
+    Ok(())
+}
+
+/// Delete a branch on the remote, and its remote-tracking ref locally.
+#[tauri::command]
+pub async fn git_branch_delete_remote(
+    repo_path: String,
+    remote: String,
+    name: String,
+    token: String,
+) -> Result<(), String> {
+    let repo = open_repo(&repo_path)?;
+    let mut remote_obj = repo
+        .find_remote(&remote)
+        .map_err(|e| e.message().to_string())?;
+
+    let callbacks = make_callbacks(&token);
+    let mut opts = PushOptions::new();
+    opts.remote_callbacks(callbacks);
+
+    let refspec = format!(":refs/heads/{}", name);
+    remote_obj
+        .push(&[&refspec], Some(&mut opts))
+        .map_err(|e| {
+            let msg = e.message().to_string();
+            if msg.contains("authentication") || msg.contains("401") || msg.contains("403") {
+                "Authentication failed. Please reconnect your GitHub account.".to_string()
+            } else {
+                format!("Deleting remote branch failed: {}", msg)
+            }
+        })?;
+
+    if let Ok(mut tracking) = repo.find_branch(&format!("{}/{}", remote, name), git2::BranchType::Remote) {
+        let _ = tracking.delete();
+    }
+    Ok(())
 }
 
 #[derive(Serialize, Clone)]
@@ -194,13 +925,7 @@ pub async fn git_log(
         .map_err(|e| e.message().to_string())?;
     revwalk.set_sorting(Sort::TIME).map_err(|e| e.message().to_string())?;
 
-    let rel_path = file_path.as_ref().map(|fp| {
-        if fp.starts_with(&repo_path) {
-            fp[repo_path.len()..].trim_start_matches('/').to_string()
-        } else {
-            fp.clone()
-        }
-    });
+    let rel_path = file_path.as_ref().map(|fp| relative_path(&repo_path, fp));
 
     let mut entries = Vec::new();
     let mut prev_blob_id: Option<Oid> = None;
@@ -238,16 +963,9 @@ pub async fn git_log(
             }
         }
 
-        let time = commit.time();
-        let secs = time.seconds();
-        let offset_mins = time.offset_minutes();
-        let dt = chrono::DateTime::from_timestamp(secs, 0)
-            .unwrap_or_default()
-            .with_timezone(&chrono::FixedOffset::east_opt(offset_mins * 60).unwrap_or(chrono::FixedOffset::east_opt(0).unwrap()));
-
         entries.push(LogEntry {
             hash: oid.to_string(),
-            date: dt.to_rfc3339(),
+            date: commit_date(&commit),
             message: commit.summary().unwrap_or("").to_string(),
         });
     }
@@ -263,11 +981,7 @@ pub async fn git_show_file(
 ) -> Result<String, String> {
     let repo = open_repo(&repo_path)?;
 
-    let rel_path = if file_path.starts_with(&repo_path) {
-        file_path[repo_path.len()..].trim_start_matches('/').to_string()
-    } else {
-        file_path.clone()
-    };
+    let rel_path = relative_path(&repo_path, &file_path);
 
     let oid = Oid::from_str(&commit_hash).map_err(|e| e.message().to_string())?;
     let commit = repo
@@ -293,11 +1007,7 @@ pub async fn git_show_file_base64(
 ) -> Result<String, String> {
     let repo = open_repo(&repo_path)?;
 
-    let rel_path = if file_path.starts_with(&repo_path) {
-        file_path[repo_path.len()..].trim_start_matches('/').to_string()
-    } else {
-        file_path.clone()
-    };
+    let rel_path = relative_path(&repo_path, &file_path);
 
     let oid = Oid::from_str(&commit_hash).map_err(|e| e.message().to_string())?;
     let commit = repo
@@ -539,6 +1249,336 @@ fn get_head_parent_diff(repo: &Repository) -> Result<Option<git2::Diff<'_>>, git
     Ok(Some(diff))
 }
 
+// ── Staging ──
+
+#[derive(Serialize, Clone)]
+pub struct DiffLine {
+    /// '+', '-' or ' '
+    pub origin: char,
+    pub content: String,
+    pub old_lineno: Option<u32>,
+    pub new_lineno: Option<u32>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DiffHunk {
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct FileHunks {
+    pub path: String,
+    /// Binary files have no hunks; stage them whole with `git_stage_paths`
+    pub binary: bool,
+    pub hunks: Vec<DiffHunk>,
+}
+
+/// Part of a file's diff to stage or unstage: hunk `hunk` of `git_file_hunks`,
+/// and optionally only some of its lines (indices into `DiffHunk.lines`;
+/// context lines in the list are ignored).
+#[derive(Deserialize, Clone)]
+pub struct HunkSelection {
+    pub hunk: usize,
+    pub lines: Option<Vec<usize>>,
+}
+
+/// Context used for staging diffs. `git_file_hunks` and the stage/unstage
+/// commands must agree on it, or hunk indices would point at different hunks.
+const STAGE_CONTEXT_LINES: u32 = 3;
+
+fn relative_path(repo_path: &str, file_path: &str) -> String {
+    match file_path.strip_prefix(repo_path) {
+        Some(rest) => rest.trim_start_matches('/').to_string(),
+        None => file_path.to_string(),
+    }
+}
+
+/// Content of `rel` in HEAD, or `None` if HEAD doesn't have it.
+fn head_content(repo: &Repository, rel: &str) -> Result<Option<Vec<u8>>, String> {
+    let tree = match repo.head().and_then(|h| h.peel_to_tree()) {
+        Ok(tree) => tree,
+        Err(_) => return Ok(None),
+    };
+    let entry = match tree.get_path(Path::new(rel)) {
+        Ok(entry) => entry,
+        Err(_) => return Ok(None),
+    };
+    let blob = repo.find_blob(entry.id()).map_err(|e| e.message().to_string())?;
+    Ok(Some(blob.content().to_vec()))
+}
+
+/// Staged entry for `rel` and its content, or `None` if the index doesn't have it.
+fn index_content(repo: &Repository, index: &git2::Index, rel: &str) -> Result<Option<(git2::IndexEntry, Vec<u8>)>, String> {
+    let Some(entry) = index.get_path(Path::new(rel), 0) else { return Ok(None) };
+    let blob = repo.find_blob(entry.id).map_err(|e| e.message().to_string())?;
+    let content = blob.content().to_vec();
+    Ok(Some((entry, content)))
+}
+
+/// A file's staging diff: index → working tree when staging, HEAD → index when
+/// unstaging. `old` is that diff's old side, which `apply_selected` edits.
+struct StagingDiff<'r> {
+    old: Vec<u8>,
+    /// `None` when the file has no changes on this side
+    patch: Option<git2::Patch<'r>>,
+    binary: bool,
+}
+
+/// Diff `rel` the way `git diff` / `git diff --cached` would. Working tree
+/// content goes through the repo's clean filters (autocrlf, gitattributes,
+/// LFS), so the staged result matches what `git add` would store.
+fn staging_diff<'r>(repo: &'r Repository, rel: &str, staged: bool) -> Result<StagingDiff<'r>, String> {
+    let index = repo.index().map_err(|e| e.message().to_string())?;
+    let mut opts = DiffOptions::new();
+    opts.context_lines(STAGE_CONTEXT_LINES)
+        .pathspec(rel)
+        .disable_pathspec_match(true)
+        .include_untracked(true)
+        .show_untracked_content(true);
+
+    let (old, diff) = if staged {
+        let head_tree = repo.head().and_then(|h| h.peel_to_tree()).ok();
+        let diff = repo
+            .diff_tree_to_index(head_tree.as_ref(), Some(&index), Some(&mut opts))
+            .map_err(|e| e.message().to_string())?;
+        (head_content(repo, rel)?.unwrap_or_default(), diff)
+    } else {
+        let old = index_content(repo, &index, rel)?.map(|(_, content)| content).unwrap_or_default();
+        let diff = repo
+            .diff_index_to_workdir(Some(&index), Some(&mut opts))
+            .map_err(|e| e.message().to_string())?;
+        (old, diff)
+    };
+
+    let patch = match diff.deltas().len() {
+        0 => None,
+        _ => git2::Patch::from_diff(&diff, 0).map_err(|e| e.message().to_string())?,
+    };
+    let binary = match &patch {
+        Some(patch) => patch.delta().flags().is_binary(),
+        None => diff.get_delta(0).is_some_and(|d| d.flags().is_binary()),
+    };
+    Ok(StagingDiff { old, patch, binary })
+}
+
+/// Whether a diff line is one `git_file_hunks` returns. The others are
+/// "\ No newline at end of file" markers, which selections don't count.
+fn is_listed_line(origin: char) -> bool {
+    matches!(origin, '+' | '-' | ' ')
+}
+
+/// Apply the changes `take(hunk, line)` accepts from `patch` to `old`. Rejected
+/// deletions keep the old line and rejected additions are dropped, so taking
+/// nothing returns `old` and taking everything returns the patch's new side.
+/// `line` counts only the lines `git_file_hunks` lists.
+fn apply_selected(patch: &git2::Patch, old: &[u8], take: impl Fn(usize, usize) -> bool) -> Result<Vec<u8>, String> {
+    let old_lines: Vec<&[u8]> = old.split_inclusive(|&b| b == b'\n').collect();
+    let mut result = Vec::with_capacity(old.len());
+    // 1-based number of the next old line not yet copied
+    let mut next_old = 1usize;
+    let copy_until = |result: &mut Vec<u8>, next_old: &mut usize, until: usize| {
+        while *next_old < until && *next_old <= old_lines.len() {
+            result.extend_from_slice(old_lines[*next_old - 1]);
+            *next_old += 1;
+        }
+    };
+
+    for h in 0..patch.num_hunks() {
+        let (hunk, num_lines) = patch.hunk(h).map_err(|e| e.message().to_string())?;
+        // An insertion-only hunk (old_lines == 0) goes after line old_start
+        let start = hunk.old_start() as usize + usize::from(hunk.old_lines() == 0);
+        copy_until(&mut result, &mut next_old, start);
+
+        let mut listed = 0usize;
+        for l in 0..num_lines {
+            let line = patch.line_in_hunk(h, l).map_err(|e| e.message().to_string())?;
+            let index = listed;
+            if is_listed_line(line.origin()) {
+                listed += 1;
+            }
+            match line.origin() {
+                ' ' => {
+                    let lineno = line.old_lineno().unwrap_or(0) as usize;
+                    copy_until(&mut result, &mut next_old, lineno + 1);
+                }
+                '-' => {
+                    let lineno = line.old_lineno().unwrap_or(0) as usize;
+                    copy_until(&mut result, &mut next_old, lineno);
+                    if take(h, index) {
+                        next_old = lineno + 1;
+                    } else {
+                        copy_until(&mut result, &mut next_old, lineno + 1);
+                    }
+                }
+                '+' if take(h, index) => {
+                    // A kept old last line without a newline no longer ends the file
+                    if result.last().is_some_and(|&b| b != b'\n') {
+                        result.push(b'\n');
+                    }
+                    result.extend_from_slice(line.content());
+                }
+                // "\ No newline at end of file" markers: line content is already exact
+                _ => {}
+            }
+        }
+    }
+    copy_until(&mut result, &mut next_old, old_lines.len() + 1);
+    Ok(result)
+}
+
+fn is_selected(selection: &[HunkSelection], hunk: usize, line: usize) -> bool {
+    selection.iter().any(|s| s.hunk == hunk && s.lines.as_ref().is_none_or(|lines| lines.contains(&line)))
+}
+
+/// Write `content` as the staged version of `rel`, keeping the entry's mode.
+fn write_index_content(repo: &Repository, rel: &str, content: &[u8]) -> Result<(), String> {
+    let mut index = repo.index().map_err(|e| e.message().to_string())?;
+    let entry = match index_content(repo, &index, rel)? {
+        Some((entry, _)) => entry,
+        None => git2::IndexEntry {
+            ctime: git2::IndexTime::new(0, 0),
+            mtime: git2::IndexTime::new(0, 0),
+            dev: 0,
+            ino: 0,
+            mode: 0o100644,
+            uid: 0,
+            gid: 0,
+            file_size: 0,
+            id: Oid::zero(),
+            flags: rel.len().min(0xfff) as u16,
+            flags_extended: 0,
+            path: rel.as_bytes().to_vec(),
+        },
+    };
+    index
+        .add_frombuffer(&entry, content)
+        .map_err(|e| e.message().to_string())?;
+    index.write().map_err(|e| e.message().to_string())?;
+    Ok(())
+}
+
+/// Stage whole files or folders, including deletions.
+#[tauri::command]
+pub async fn git_stage_paths(repo_path: String, paths: Vec<String>) -> Result<(), String> {
+    let repo = open_repo(&repo_path)?;
+    let rels: Vec<String> = paths.iter().map(|p| relative_path(&repo_path, p)).collect();
+    let mut index = repo.index().map_err(|e| e.message().to_string())?;
+    index
+        .add_all(rels.iter(), IndexAddOption::DEFAULT, None)
+        .map_err(|e| e.message().to_string())?;
+    // Also stage deletions
+    index
+        .update_all(rels.iter(), None)
+        .map_err(|e| e.message().to_string())?;
+    index.write().map_err(|e| e.message().to_string())?;
+    Ok(())
+}
+
+/// Unstage files or folders: their index entries go back to HEAD (or are
+/// removed when HEAD doesn't have them). The working tree is untouched.
+#[tauri::command]
+pub async fn git_unstage_paths(repo_path: String, paths: Vec<String>) -> Result<(), String> {
+    let repo = open_repo(&repo_path)?;
+    let rels: Vec<String> = paths.iter().map(|p| relative_path(&repo_path, p)).collect();
+    let head = repo.head().and_then(|h| h.peel_to_commit()).ok();
+    repo.reset_default(head.as_ref().map(|c| c.as_object()), rels.iter())
+        .map_err(|e| e.message().to_string())?;
+    Ok(())
+}
+
+/// Hunks of one file's unstaged changes (index → working tree), or of its
+/// staged changes (HEAD → index) with `staged`. Indices into `hunks` and
+/// `lines` are what `git_stage_hunks` / `git_unstage_hunks` take.
+#[tauri::command]
+pub async fn git_file_hunks(repo_path: String, path: String, staged: Option<bool>) -> Result<FileHunks, String> {
+    let repo = open_repo(&repo_path)?;
+    let rel = relative_path(&repo_path, &path);
+    let diff = staging_diff(&repo, &rel, staged.unwrap_or(false))?;
+    let mut hunks = Vec::new();
+    let Some(patch) = diff.patch.filter(|_| !diff.binary) else {
+        return Ok(FileHunks { path: rel, binary: diff.binary, hunks });
+    };
+    for h in 0..patch.num_hunks() {
+        let (hunk, num_lines) = patch.hunk(h).map_err(|e| e.message().to_string())?;
+        let mut lines = Vec::new();
+        for l in 0..num_lines {
+            let line = patch.line_in_hunk(h, l).map_err(|e| e.message().to_string())?;
+            let origin = line.origin();
+            if !is_listed_line(origin) {
+                continue;
+            }
+            lines.push(DiffLine {
+                origin,
+                content: String::from_utf8_lossy(line.content()).trim_end_matches('\n').to_string(),
+                old_lineno: line.old_lineno(),
+                new_lineno: line.new_lineno(),
+            });
+        }
+        hunks.push(DiffHunk {
+            header: std::str::from_utf8(hunk.header()).unwrap_or("").trim_end().to_string(),
+            old_start: hunk.old_start(),
+            old_lines: hunk.old_lines(),
+            new_start: hunk.new_start(),
+            new_lines: hunk.new_lines(),
+            lines,
+        });
+    }
+
+    Ok(FileHunks { path: rel, binary: false, hunks })
+}
+
+/// Stage the selected hunks or lines of a file's unstaged changes.
+#[tauri::command]
+pub async fn git_stage_hunks(repo_path: String, path: String, selection: Vec<HunkSelection>) -> Result<(), String> {
+    let repo = open_repo(&repo_path)?;
+    let rel = relative_path(&repo_path, &path);
+    let diff = staging_diff(&repo, &rel, false)?;
+    if diff.binary {
+        return Err(format!("{} is binary; stage the whole file instead", rel));
+    }
+    let Some(patch) = diff.patch else { return Ok(()) };
+    let staged = apply_selected(&patch, &diff.old, |h, l| is_selected(&selection, h, l))?;
+    if staged.is_empty() && patch.delta().status() == git2::Delta::Deleted {
+        // Every line of a file deleted from the working tree: stage the deletion
+        return remove_from_index(&repo, &rel);
+    }
+    write_index_content(&repo, &rel, &staged)
+}
+
+/// Unstage the selected hunks or lines of a file's staged changes, leaving the
+/// working tree as it is.
+#[tauri::command]
+pub async fn git_unstage_hunks(repo_path: String, path: String, selection: Vec<HunkSelection>) -> Result<(), String> {
+    let repo = open_repo(&repo_path)?;
+    let rel = relative_path(&repo_path, &path);
+    let diff = staging_diff(&repo, &rel, true)?;
+    if diff.binary {
+        return Err(format!("{} is binary; unstage the whole file instead", rel));
+    }
+    let Some(patch) = diff.patch else { return Ok(()) };
+    // Keep every staged change except the selected ones
+    let staged = apply_selected(&patch, &diff.old, |h, l| !is_selected(&selection, h, l))?;
+    if staged.is_empty() && head_content(&repo, &rel)?.is_none() {
+        // Nothing left of a newly added file: drop it from the index
+        return remove_from_index(&repo, &rel);
+    }
+    write_index_content(&repo, &rel, &staged)
+}
+
+fn remove_from_index(repo: &Repository, rel: &str) -> Result<(), String> {
+    let mut index = repo.index().map_err(|e| e.message().to_string())?;
+    index
+        .remove_path(Path::new(rel))
+        .map_err(|e| e.message().to_string())?;
+    index.write().map_err(|e| e.message().to_string())?;
+    Ok(())
+}
+
 // ── Remote management ──
 
 #[tauri::command]
@@ -777,85 +1817,318 @@ pub async fn git_pull_ff(
     Ok(())
 }
 
+#[derive(Serialize, Clone)]
+pub struct MergeConflict {
+    /// Relative to the repo root
+    pub path: String,
+    /// Common ancestor content; `None` if the file was added on both sides
+    pub base: Option<String>,
+    /// Local content; `None` if deleted locally
+    pub ours: Option<String>,
+    /// Incoming content; `None` if deleted on the remote
+    pub theirs: Option<String>,
+    /// Any side is not valid UTF-8. Its content is left out; resolve with
+    /// "ours" or "theirs".
+    pub binary: bool,
+}
+
+#[derive(Serialize, Clone)]
+pub struct MergeResult {
+    /// "merged" or "conflicts"
+    pub status: String,
+    /// The merge commit when `status` is "merged"
+    pub commit: Option<String>,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct MergeState {
+    /// The repo has an unfinished merge (MERGE_HEAD exists)
+    pub merging: bool,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+fn read_conflicts(repo: &Repository) -> Result<Vec<MergeConflict>, String> {
+    let index = repo.index().map_err(|e| e.message().to_string())?;
+    let conflicts = index.conflicts().map_err(|e| e.message().to_string())?;
+
+    let mut result = Vec::new();
+    for conflict in conflicts {
+        let conflict = conflict.map_err(|e| e.message().to_string())?;
+        let path = [&conflict.our, &conflict.their, &conflict.ancestor]
+            .into_iter()
+            .flatten()
+            .next()
+            .map(|entry| String::from_utf8_lossy(&entry.path).to_string())
+            .unwrap_or_default();
+
+        let mut binary = false;
+        let mut read = |entry: &Option<git2::IndexEntry>| -> Result<Option<String>, String> {
+            let Some(entry) = entry else { return Ok(None) };
+            let blob = repo.find_blob(entry.id).map_err(|e| e.message().to_string())?;
+            match std::str::from_utf8(blob.content()) {
+                Ok(text) if !blob.is_binary() => Ok(Some(text.to_string())),
+                _ => {
+                    binary = true;
+                    Ok(None)
+                }
+            }
+        };
+        let base = read(&conflict.ancestor)?;
+        let ours = read(&conflict.our)?;
+        let theirs = read(&conflict.their)?;
+
+        result.push(MergeConflict { path, base, ours, theirs, binary });
+    }
+    Ok(result)
+}
+
+/// Commit the index as a merge of HEAD and every MERGE_HEAD, then clear the
+/// merge state. `sign` works as in `git_commit`: unset follows `commit.gpgsign`.
+async fn commit_merge(repo: &mut Repository, message: &str, sign: Option<bool>) -> Result<String, String> {
+    let created = {
+        let mut merge_heads = Vec::new();
+        repo.mergehead_foreach(|oid| {
+            merge_heads.push(*oid);
+            true
+        })
+        .map_err(|e| e.message().to_string())?;
+
+        let sig = config_signature(repo)?;
+        let mut index = repo.index().map_err(|e| e.message().to_string())?;
+        let tree_oid = index
+            .write_tree()
+            .map_err(|e| e.message().to_string())?;
+        let tree = repo
+            .find_tree(tree_oid)
+            .map_err(|e| e.message().to_string())?;
+
+        let head_commit = repo
+            .head()
+            .map_err(|e| e.message().to_string())?
+            .peel_to_commit()
+            .map_err(|e| e.message().to_string())?;
+        let mut parents = vec![head_commit];
+        for oid in merge_heads {
+            parents.push(repo.find_commit(oid).map_err(|e| e.message().to_string())?);
+        }
+        let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+
+        create_commit(repo, &sig, &sig, message, &tree, &parent_refs, sign)?
+    };
+
+    let oid = match created {
+        Ok(oid) => oid,
+        Err(unsigned) => write_signed_commit(repo, unsigned, message.lines().next().unwrap_or("")).await?,
+    };
+
+    // Clean up merge state files
+    let _ = repo.cleanup_state();
+
+    Ok(oid.to_string())
+}
+
+/// Merge `remote/branch` into HEAD. A clean merge is committed. On conflicts,
+/// the default is to reset to HEAD and fail with "CONFLICT: …", as sync
+/// expects. With `keep_conflicts`, the repo stays in merging state with
+/// conflict markers in the working tree, and the conflicts are returned for
+/// `git_merge_resolve` / `git_merge_continue` / `git_merge_abort`.
 #[tauri::command]
 pub async fn git_merge_remote(
     repo_path: String,
     remote: String,
     branch: String,
-) -> Result<(), String> {
-    let repo = open_repo(&repo_path)?;
+    keep_conflicts: Option<bool>,
+    sign: Option<bool>,
+) -> Result<MergeResult, String> {
+    let mut repo = open_repo(&repo_path)?;
+    let message = format!("Merge remote changes from {}/{}", remote, branch);
 
-    let fetch_ref = format!("refs/remotes/{}/{}", remote, branch);
-    let remote_ref = repo
-        .find_reference(&fetch_ref)
-        .map_err(|e| format!("Could not find remote branch: {}", e.message()))?;
-    let remote_commit = remote_ref
-        .peel_to_commit()
-        .map_err(|e| e.message().to_string())?;
+    // git2 merge state isn't Send, so it must be gone before the (possibly signed) commit awaits
+    {
+        let fetch_ref = format!("refs/remotes/{}/{}", remote, branch);
+        let remote_oid = repo
+            .find_reference(&fetch_ref)
+            .map_err(|e| format!("Could not find remote branch: {}", e.message()))?
+            .peel_to_commit()
+            .map_err(|e| e.message().to_string())?
+            .id();
+
+        let annotated = repo
+            .find_annotated_commit(remote_oid)
+            .map_err(|e| e.message().to_string())?;
 
-    let annotated = repo
-        .find_annotated_commit(remote_commit.id())
-        .map_err(|e| e.message().to_string())?;
+        // Perform merge (updates index + workdir)
+        let mut merge_opts = git2::MergeOptions::new();
+        let mut checkout_opts = git2::build::CheckoutBuilder::new();
+        checkout_opts.safe();
+        let their_label = format!("{}/{}", remote, branch);
+        if keep_conflicts.unwrap_or(false) {
+            checkout_opts
+                .allow_conflicts(true)
+                .conflict_style_merge(true)
+                .our_label("local")
+                .their_label(&their_label);
+        }
 
-    // Perform merge (updates index + workdir)
-    let mut merge_opts = git2::MergeOptions::new();
-    let mut checkout_opts = git2::build::CheckoutBuilder::new();
-    checkout_opts.safe();
+        repo.merge(
+            &[&annotated],
+            Some(&mut merge_opts),
+            Some(&mut checkout_opts),
+        )
+        .map_err(|e| format!("CONFLICT: {}", e.message()))?;
+        drop(annotated);
+
+        // Check for conflicts in the index
+        let index = repo.index().map_err(|e| e.message().to_string())?;
+        if index.has_conflicts() {
+            if keep_conflicts.unwrap_or(false) {
+                // Picked up by git_merge_continue instead of libgit2's "Merge commit '<sha>'"
+                let _ = std::fs::write(repo.path().join("MERGE_MSG"), format!("{}\n", message));
+                return Ok(MergeResult {
+                    status: "conflicts".to_string(),
+                    commit: None,
+                    conflicts: read_conflicts(&repo)?,
+                });
+            }
+            // Abort: reset to HEAD so repo isn't left in merging state
+            if let Ok(head) = repo.head() {
+                if let Ok(commit) = head.peel_to_commit() {
+                    let _ = repo.reset(commit.as_object(), git2::ResetType::Hard, None);
+                }
+            }
+            let _ = repo.cleanup_state();
+            return Err(
+                "CONFLICT: Files have conflicting changes that need manual resolution.".to_string(),
+            );
+        }
+    }
 
-    repo.merge(
-        &[&annotated],
-        Some(&mut merge_opts),
-        Some(&mut checkout_opts),
-    )
-    .map_err(|e| format!("CONFLICT: {}", e.message()))?;
+    let commit = commit_merge(&mut repo, &message, sign).await?;
+    Ok(MergeResult {
+        status: "merged".to_string(),
+        commit: Some(commit),
+        conflicts: Vec::new(),
+    })
+}
 
-    // Check for conflicts in the index
-    let index = repo.index().map_err(|e| e.message().to_string())?;
-    if index.has_conflicts() {
-        // Abort: reset to HEAD so repo isn't left in merging state
-        if let Ok(head) = repo.head() {
-            if let Ok(commit) = head.peel_to_commit() {
-                let _ = repo.reset(commit.as_object(), git2::ResetType::Hard, None);
+/// Whether a merge is in progress and which files are still conflicted, e.g.
+/// to reopen the resolver after a restart.
+#[tauri::command]
+pub async fn git_merge_state(repo_path: String) -> Result<MergeState, String> {
+    let repo = open_repo(&repo_path)?;
+    Ok(MergeState {
+        merging: repo.state() == git2::RepositoryState::Merge,
+        conflicts: read_conflicts(&repo)?,
+    })
+}
+
+/// Resolve one conflicted file with "ours", "theirs" or "custom" (`content`),
+/// writing the result to the working tree and staging it. Taking a side that
+/// deleted the file deletes it.
+#[tauri::command]
+pub async fn git_merge_resolve(
+    repo_path: String,
+    path: String,
+    resolution: String,
+    content: Option<String>,
+) -> Result<(), String> {
+    let repo = open_repo(&repo_path)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| "Repository has no working directory".to_string())?;
+    let rel = Path::new(&path);
+    let full = workdir.join(rel);
+
+    let mut index = repo.index().map_err(|e| e.message().to_string())?;
+    let conflict = index
+        .conflicts()
+        .map_err(|e| e.message().to_string())?
+        .flatten()
+        .find(|c| {
+            [&c.our, &c.their, &c.ancestor]
+                .into_iter()
+                .flatten()
+                .any(|entry| entry.path == path.as_bytes())
+        })
+        .ok_or_else(|| format!("'{}' has no merge conflict", path))?;
+
+    let data = match resolution.as_str() {
+        "ours" | "theirs" => {
+            let side = if resolution == "ours" { &conflict.our } else { &conflict.their };
+            match side {
+                Some(entry) => Some(
+                    repo.find_blob(entry.id)
+                        .map_err(|e| e.message().to_string())?
+                        .content()
+                        .to_vec(),
+                ),
+                None => None,
+            }
+        }
+        "custom" => Some(
+            content
+                .ok_or_else(|| "A custom resolution needs content".to_string())?
+                .into_bytes(),
+        ),
+        other => return Err(format!("Unknown resolution '{}': use ours, theirs or custom", other)),
+    };
+
+    match data {
+        Some(data) => {
+            if let Some(parent) = full.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
             }
+            std::fs::write(&full, data).map_err(|e| format!("Cannot write {}: {}", path, e))?;
+            index.add_path(rel).map_err(|e| e.message().to_string())?;
+        }
+        None => {
+            if full.exists() {
+                std::fs::remove_file(&full).map_err(|e| format!("Cannot delete {}: {}", path, e))?;
+            }
+            index.remove_path(rel).map_err(|e| e.message().to_string())?;
         }
-        let _ = repo.cleanup_state();
-        return Err(
-            "CONFLICT: Files have conflicting changes that need manual resolution.".to_string(),
-        );
     }
+    index.write().map_err(|e| e.message().to_string())?;
+    Ok(())
+}
 
-    // Create merge commit
-    let sig = repo
-        .signature()
-        .unwrap_or_else(|_| Signature::now("Shoulders", "shoulders@local").unwrap());
+/// Commit a merge once every conflict is resolved. Returns the commit hash.
+#[tauri::command]
+pub async fn git_merge_continue(repo_path: String, message: Option<String>) -> Result<String, String> {
+    let mut repo = open_repo(&repo_path)?;
+    if repo.state() != git2::RepositoryState::Merge {
+        return Err("No merge in progress".to_string());
+    }
+    let remaining = read_conflicts(&repo)?;
+    if !remaining.is_empty() {
+        let paths: Vec<&str> = remaining.iter().map(|c| c.path.as_str()).collect();
+        return Err(format!("Unresolved conflicts: {}", paths.join(", ")));
+    }
 
-    let mut index = repo.index().map_err(|e| e.message().to_string())?;
-    let tree_oid = index
-        .write_tree()
-        .map_err(|e| e.message().to_string())?;
-    let tree = repo
-        .find_tree(tree_oid)
-        .map_err(|e| e.message().to_string())?;
+    // git_merge_remote leaves its message in MERGE_MSG
+    let message = message
+        .or_else(|| repo.message().ok().map(|m| m.trim().to_string()))
+        .filter(|m| !m.is_empty())
+        .unwrap_or_else(|| "Merge remote changes".to_string());
+    commit_merge(&mut repo, &message, None).await
+}
 
-    let head_commit = repo
+/// Abandon a merge: reset the index and working tree to HEAD and clear the merge state.
+#[tauri::command]
+pub async fn git_merge_abort(repo_path: String) -> Result<(), String> {
+    let repo = open_repo(&repo_path)?;
+    if repo.state() != git2::RepositoryState::Merge {
+        return Err("No merge in progress".to_string());
+    }
+    let head = repo
         .head()
         .map_err(|e| e.message().to_string())?
         .peel_to_commit()
         .map_err(|e| e.message().to_string())?;
-
-    repo.commit(
-        Some("HEAD"),
-        &sig,
-        &sig,
-        &format!("Merge remote changes from {}/{}", remote, branch),
-        &tree,
-        &[&head_commit, &remote_commit],
-    )
-    .map_err(|e| e.message().to_string())?;
-
-    // Clean up merge state files
-    let _ = repo.cleanup_state();
-
+    repo.reset(head.as_object(), git2::ResetType::Hard, None)
+        .map_err(|e| e.message().to_string())?;
+    repo.cleanup_state().map_err(|e| e.message().to_string())?;
     Ok(())
 }
 
@@ -903,3 +2176,76 @@ pub async fn git_clone_authenticated(
     })?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\neleven\ntwelve\n";
+    // Two hunks: "two" → "TWO" near the top, and "twelve" → "twelve" + "thirteen"
+    const NEW: &str = "one\nTWO\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\neleven\ntwelve\nthirteen\n";
+
+    fn patch<'a>(old: &'a str, new: &'a str) -> git2::Patch<'a> {
+        let mut opts = DiffOptions::new();
+        opts.context_lines(STAGE_CONTEXT_LINES);
+        git2::Patch::from_buffers(old.as_bytes(), None, new.as_bytes(), None, Some(&mut opts))
+            .unwrap()
+    }
+
+    fn apply(old: &str, new: &str, selection: &[HunkSelection]) -> String {
+        let patch = patch(old, new);
+        let result = apply_selected(&patch, old.as_bytes(), |h, l| is_selected(selection, h, l)).unwrap();
+        String::from_utf8(result).unwrap()
+    }
+
+    fn hunk(hunk: usize, lines: Option<Vec<usize>>) -> HunkSelection {
+        HunkSelection { hunk, lines }
+    }
+
+    #[test]
+    fn nothing_or_everything() {
+        assert_eq!(patch(OLD, NEW).num_hunks(), 2);
+        assert_eq!(apply(OLD, NEW, &[]), OLD);
+        assert_eq!(apply(OLD, NEW, &[hunk(0, None), hunk(1, None)]), NEW);
+    }
+
+    #[test]
+    fn single_hunk() {
+        assert_eq!(apply(OLD, NEW, &[hunk(0, None)]), OLD.replace("two", "TWO"));
+        assert_eq!(apply(OLD, NEW, &[hunk(1, None)]), format!("{}thirteen\n", OLD));
+    }
+
+    #[test]
+    fn single_lines() {
+        // Hunk 0 lists: " one", "-two", "+TWO", " three", " four", " five"
+        let old = "one\ntwo\nthree\nfour\nfive\n";
+        let new = "one\nTWO\nthree\nfour\nfive\n";
+        assert_eq!(apply(old, new, &[hunk(0, Some(vec![1]))]), "one\nthree\nfour\nfive\n");
+        assert_eq!(apply(old, new, &[hunk(0, Some(vec![2]))]), "one\ntwo\nTWO\nthree\nfour\nfive\n");
+        assert_eq!(apply(old, new, &[hunk(0, Some(vec![0, 3]))]), old);
+    }
+
+    #[test]
+    fn insertion_at_start() {
+        assert_eq!(apply("a\nb\n", "new\na\nb\n", &[hunk(0, None)]), "new\na\nb\n");
+        assert_eq!(apply("", "a\nb\n", &[hunk(0, Some(vec![1]))]), "b\n");
+    }
+
+    #[test]
+    fn missing_final_newline() {
+        // The "\ No newline" markers aren't listed: line 2 is still "+b"
+        let old = "a\nb";
+        let new = "a\nB";
+        assert_eq!(apply(old, new, &[hunk(0, None)]), new);
+        // Keeping the unterminated "a" and adding after it
+        assert_eq!(apply("a", "a\nb\n", &[hunk(0, Some(vec![2]))]), "a\nb\n");
+        assert_eq!(apply("a", "a\nb\n", &[hunk(0, Some(vec![1, 2]))]), "a\na\nb\n");
+        assert_eq!(apply(old, new, &[hunk(0, Some(vec![1]))]), "a\n");
+    }
+
+    #[test]
+    fn deleting_every_line() {
+        assert_eq!(apply("a\nb\n", "", &[hunk(0, None)]), "");
+        assert_eq!(apply("a\nb\n", "", &[hunk(0, Some(vec![0]))]), "b\n");
+    }
+}
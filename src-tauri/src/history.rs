@@ -0,0 +1,464 @@
+use crate::fs_commands::{content_hash, write_temp_sibling, FileVersion, WriteError};
+use crate::sandbox::SandboxState;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+
+/// Local file history, independent of git. Layout under `<workspace>/.shoulders/history`:
+///
+/// ```text
+/// config.json              # HistoryConfig
+/// objects/ab/cdef…         # zlib-compressed content, named by SHA-256
+/// files/<sha256 of path>.jsonl   # one Snapshot per line, oldest first
+/// ```
+///
+/// Content is deduplicated across snapshots and files. Objects are only
+/// deleted by `history_prune`, which checks every log for references. Recording
+/// and pruning hold the workspace's history lock, so a prune can't delete an
+/// object a concurrent write is about to log.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Snapshot {
+    /// `<created_ms>-<first 8 hash chars>`
+    pub id: String,
+    /// Relative to the workspace root, `/`-separated
+    pub path: String,
+    /// SHA-256 of the content, as in `FileVersion.hash`
+    pub hash: String,
+    pub size: u64,
+    /// Unix milliseconds
+    pub created_ms: u64,
+    /// "write" for content saved through `write_file`; "external" for content
+    /// found on disk before a write that differed from the last snapshot
+    pub source: String,
+}
+
+/// Which snapshots survive pruning. Per file, the newest `keep_recent` are
+/// always kept. Older ones thin out to the newest per hour for `hourly_hours`,
+/// then the newest per day until `daily_days`. Anything older is dropped.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HistoryConfig {
+    pub enabled: bool,
+    pub keep_recent: usize,
+    pub hourly_hours: u64,
+    pub daily_days: u64,
+    /// Larger files are not snapshotted
+    pub max_file_bytes: u64,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            keep_recent: 20,
+            hourly_hours: 24,
+            daily_days: 30,
+            max_file_bytes: 10 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct HistoryDiff {
+    pub from: Snapshot,
+    /// `None` when comparing against the file on disk
+    pub to: Option<Snapshot>,
+    /// Unified diff, empty for binary content
+    pub patch: String,
+    pub additions: usize,
+    pub deletions: usize,
+    pub binary: bool,
+}
+
+#[derive(Serialize)]
+pub struct PruneStats {
+    pub snapshots_removed: usize,
+    pub objects_removed: usize,
+    pub bytes_freed: u64,
+}
+
+const HOUR_MS: u64 = 60 * 60 * 1000;
+const DAY_MS: u64 = 24 * HOUR_MS;
+
+/// One lock per workspace, shared by `PendingSnapshot::record` and `history_prune`.
+/// A static rather than managed state because `write_atomic` has no app handle.
+static LOCKS: LazyLock<Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>> = LazyLock::new(Default::default);
+
+fn workspace_lock(workspace: &Path) -> Arc<Mutex<()>> {
+    let mut locks = LOCKS.lock().unwrap_or_else(|e| e.into_inner());
+    locks.entry(workspace.to_path_buf()).or_default().clone()
+}
+
+/// Object names are SHA-256 hex digests. A log line is data anyone can edit, so
+/// its hash is checked before it becomes a path.
+fn is_valid_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn history_dir(workspace: &Path) -> PathBuf {
+    workspace.join(".shoulders").join("history")
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Canonical form of a path that may no longer exist (a deleted file's history).
+fn canonical(path: &Path) -> PathBuf {
+    if let Ok(canonical) = fs::canonicalize(path) {
+        return canonical;
+    }
+    match (path.parent().and_then(|p| fs::canonicalize(p).ok()), path.file_name()) {
+        (Some(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// The workspace a file belongs to (nearest ancestor with `.shoulders`) and its
+/// relative path. Files inside `.shoulders` and `.git` have no history.
+fn locate(path: &Path) -> Option<(PathBuf, String)> {
+    let path = canonical(path);
+    let workspace = path.ancestors().skip(1).find(|a| a.join(".shoulders").is_dir())?;
+    let rel = path.strip_prefix(workspace).ok()?;
+    let rel = rel.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+    if rel.starts_with(".shoulders/") || rel.starts_with(".git/") {
+        return None;
+    }
+    Some((workspace.to_path_buf(), rel))
+}
+
+fn load_config(workspace: &Path) -> HistoryConfig {
+    fs::read_to_string(history_dir(workspace).join("config.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn log_path(workspace: &Path, rel: &str) -> PathBuf {
+    history_dir(workspace).join("files").join(format!("{}.jsonl", content_hash(rel.as_bytes())))
+}
+
+fn object_path(workspace: &Path, hash: &str) -> PathBuf {
+    history_dir(workspace).join("objects").join(&hash[..2]).join(&hash[2..])
+}
+
+/// Snapshots in a log, skipping unparseable lines and invalid hashes.
+fn read_log(path: &Path) -> Vec<Snapshot> {
+    fs::read_to_string(path)
+        .map(|content| {
+            content
+                .lines()
+                .filter_map(|line| serde_json::from_str::<Snapshot>(line).ok())
+                .filter(|s| is_valid_hash(&s.hash))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn write_log(path: &Path, snapshots: &[Snapshot]) -> Result<(), String> {
+    let mut content = String::new();
+    for snapshot in snapshots {
+        content.push_str(&serde_json::to_string(snapshot).map_err(|e| e.to_string())?);
+        content.push('\n');
+    }
+    let tmp = write_temp_sibling(path, content.as_bytes()).map_err(|e| format!("Cannot write history: {}", e))?;
+    fs::rename(&tmp, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        format!("Cannot write history: {}", e)
+    })
+}
+
+fn store_object(workspace: &Path, hash: &str, data: &[u8]) -> Result<(), String> {
+    let path = object_path(workspace, hash);
+    if path.exists() {
+        return Ok(());
+    }
+    let err = |e: std::io::Error| format!("Cannot store snapshot: {}", e);
+    fs::create_dir_all(path.parent().unwrap()).map_err(err)?;
+    let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).map_err(err)?;
+    let compressed = encoder.finish().map_err(err)?;
+    let tmp = write_temp_sibling(&path, &compressed).map_err(err)?;
+    fs::rename(&tmp, &path).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        err(e)
+    })
+}
+
+fn read_object(workspace: &Path, hash: &str) -> Result<Vec<u8>, String> {
+    let file = fs::File::open(object_path(workspace, hash))
+        .map_err(|e| format!("Snapshot content is missing: {}", e))?;
+    let mut data = Vec::new();
+    ZlibDecoder::new(file)
+        .read_to_end(&mut data)
+        .map_err(|e| format!("Snapshot content is corrupt: {}", e))?;
+    Ok(data)
+}
+
+/// Apply the retention policy to one file's snapshots (oldest first).
+fn retain(snapshots: Vec<Snapshot>, config: &HistoryConfig, now: u64) -> Vec<Snapshot> {
+    let mut hours = HashSet::new();
+    let mut days = HashSet::new();
+    let mut kept: Vec<Snapshot> = snapshots
+        .into_iter()
+        .rev()
+        .enumerate()
+        .filter(|(i, snapshot)| {
+            if *i < config.keep_recent {
+                return true;
+            }
+            let age = now.saturating_sub(snapshot.created_ms);
+            if age <= config.hourly_hours * HOUR_MS {
+                hours.insert(snapshot.created_ms / HOUR_MS)
+            } else if age <= config.daily_days * DAY_MS {
+                days.insert(snapshot.created_ms / DAY_MS)
+            } else {
+                false
+            }
+        })
+        .map(|(_, snapshot)| snapshot)
+        .collect();
+    kept.reverse();
+    kept
+}
+
+/// A write about to happen to a tracked file, holding what was on disk before it.
+pub(crate) struct PendingSnapshot {
+    workspace: PathBuf,
+    rel: String,
+    config: HistoryConfig,
+    previous: Option<Vec<u8>>,
+}
+
+impl PendingSnapshot {
+    /// `None` if the file has no history: outside a workspace, history disabled
+    /// or the file is too large.
+    pub(crate) fn before_write(target: &Path, new_len: usize) -> Option<Self> {
+        let (workspace, rel) = locate(target)?;
+        let config = load_config(&workspace);
+        if !config.enabled || new_len as u64 > config.max_file_bytes {
+            return None;
+        }
+        let previous = fs::metadata(target)
+            .ok()
+            .filter(|m| m.is_file() && m.len() <= config.max_file_bytes)
+            .and_then(|_| fs::read(target).ok());
+        Some(Self { workspace, rel, config, previous })
+    }
+
+    /// Record the content on disk before the write if the history doesn't have
+    /// it yet, then the written content. Failures are logged, never returned:
+    /// history must not break saving.
+    pub(crate) fn after_write(self, data: &[u8]) {
+        let lock = workspace_lock(&self.workspace);
+        let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = self.record(data) {
+            eprintln!("[history] {}: {}", self.rel, e);
+        }
+    }
+
+    fn record(&self, data: &[u8]) -> Result<(), String> {
+        let log = log_path(&self.workspace, &self.rel);
+        fs::create_dir_all(log.parent().unwrap()).map_err(|e| format!("Cannot create history: {}", e))?;
+        let mut snapshots = read_log(&log);
+        let before = snapshots.len();
+        let now = now_ms();
+
+        let versions = [(self.previous.as_deref(), "external"), (Some(data), "write")];
+        for (content, source) in versions {
+            let Some(content) = content else { continue };
+            let hash = content_hash(content);
+            if snapshots.last().is_some_and(|s| s.hash == hash) {
+                continue;
+            }
+            store_object(&self.workspace, &hash, content)?;
+            snapshots.push(Snapshot {
+                id: format!("{}-{}", now, &hash[..8]),
+                path: self.rel.clone(),
+                size: content.len() as u64,
+                created_ms: now,
+                source: source.to_string(),
+                hash,
+            });
+        }
+        if snapshots.len() == before {
+            return Ok(());
+        }
+
+        let kept = retain(snapshots.clone(), &self.config, now);
+        if kept.len() < snapshots.len() || before == 0 {
+            return write_log(&log, &kept);
+        }
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(&log)
+            .map_err(|e| format!("Cannot write history: {}", e))?;
+        for snapshot in &snapshots[before..] {
+            let line = serde_json::to_string(snapshot).map_err(|e| e.to_string())?;
+            writeln!(file, "{}", line).map_err(|e| format!("Cannot write history: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+fn locate_or_err(path: &str) -> Result<(PathBuf, String), String> {
+    locate(Path::new(path)).ok_or_else(|| format!("{} is not inside a workspace with history", path))
+}
+
+fn find_snapshot(workspace: &Path, rel: &str, id: &str) -> Result<Snapshot, String> {
+    read_log(&log_path(workspace, rel))
+        .into_iter()
+        .find(|s| s.id == id)
+        .ok_or_else(|| format!("Snapshot not found: {}", id))
+}
+
+/// Snapshots of one file, newest first.
+#[tauri::command]
+pub async fn history_list(sandbox: tauri::State<'_, SandboxState>, path: String) -> Result<Vec<Snapshot>, String> {
+    sandbox.check(&path)?;
+    let (workspace, rel) = locate_or_err(&path)?;
+    let mut snapshots = read_log(&log_path(&workspace, &rel));
+    snapshots.reverse();
+    Ok(snapshots)
+}
+
+/// Content of one snapshot, as text (invalid UTF-8 is replaced).
+#[tauri::command]
+pub async fn history_read(sandbox: tauri::State<'_, SandboxState>, path: String, id: String) -> Result<String, String> {
+    sandbox.check(&path)?;
+    let (workspace, rel) = locate_or_err(&path)?;
+    let snapshot = find_snapshot(&workspace, &rel, &id)?;
+    Ok(String::from_utf8_lossy(&read_object(&workspace, &snapshot.hash)?).to_string())
+}
+
+/// Unified diff from snapshot `from` to snapshot `to`, or to the file on disk
+/// when `to` is omitted.
+#[tauri::command]
+pub async fn history_diff(
+    sandbox: tauri::State<'_, SandboxState>,
+    path: String,
+    from: String,
+    to: Option<String>,
+) -> Result<HistoryDiff, String> {
+    sandbox.check(&path)?;
+    let (workspace, rel) = locate_or_err(&path)?;
+    let from = find_snapshot(&workspace, &rel, &from)?;
+    let old = read_object(&workspace, &from.hash)?;
+    let (to, new) = match to {
+        Some(id) => {
+            let snapshot = find_snapshot(&workspace, &rel, &id)?;
+            let content = read_object(&workspace, &snapshot.hash)?;
+            (Some(snapshot), content)
+        }
+        // A deleted file diffs as empty
+        None => (None, fs::read(&path).unwrap_or_default()),
+    };
+
+    let mut opts = git2::DiffOptions::new();
+    opts.context_lines(3);
+    let mut patch = git2::Patch::from_buffers(&old, Some(Path::new(&rel)), &new, Some(Path::new(&rel)), Some(&mut opts))
+        .map_err(|e| e.message().to_string())?;
+    let binary = patch.delta().flags().is_binary();
+    let (_, additions, deletions) = patch.line_stats().map_err(|e| e.message().to_string())?;
+    let text = if binary {
+        String::new()
+    } else {
+        let buf = patch.to_buf().map_err(|e| e.message().to_string())?;
+        buf.as_str().unwrap_or("").to_string()
+    };
+
+    Ok(HistoryDiff { from, to, patch: text, additions, deletions, binary })
+}
+
+/// Write a snapshot's content back to the file. The current content is
+/// snapshotted first, so a restore can itself be undone.
+#[tauri::command]
+pub async fn history_restore(
+    sandbox: tauri::State<'_, SandboxState>,
+    path: String,
+    id: String,
+) -> Result<FileVersion, WriteError> {
+    sandbox.check_write(&path)?;
+    let (workspace, rel) = locate_or_err(&path)?;
+    let snapshot = find_snapshot(&workspace, &rel, &id)?;
+    let data = read_object(&workspace, &snapshot.hash)?;
+    if let Some(parent) = Path::new(&path).parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Cannot create directory: {}", e))?;
+    }
+    crate::fs_commands::write_atomic(Path::new(&path), &data, None)
+}
+
+/// Apply the retention policy to every file in a workspace and delete content
+/// no snapshot refers to any more.
+#[tauri::command]
+pub async fn history_prune(sandbox: tauri::State<'_, SandboxState>, workspace: String) -> Result<PruneStats, String> {
+    sandbox.check(&workspace)?;
+    let workspace = PathBuf::from(workspace);
+    let config = load_config(&workspace);
+    let dir = history_dir(&workspace);
+    // locate() yields canonical workspace paths; key the lock the same way
+    let lock = workspace_lock(&fs::canonicalize(&workspace).unwrap_or_else(|_| workspace.clone()));
+    let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+    let now = now_ms();
+    let mut stats = PruneStats { snapshots_removed: 0, objects_removed: 0, bytes_freed: 0 };
+
+    let mut referenced = HashSet::new();
+    for entry in fs::read_dir(dir.join("files")).into_iter().flatten().flatten() {
+        let log = entry.path();
+        let snapshots = read_log(&log);
+        let total = snapshots.len();
+        let kept = retain(snapshots, &config, now);
+        if kept.is_empty() {
+            fs::remove_file(&log).map_err(|e| format!("Cannot prune history: {}", e))?;
+        } else if kept.len() < total {
+            write_log(&log, &kept)?;
+        }
+        stats.snapshots_removed += total - kept.len();
+        referenced.extend(kept.into_iter().map(|s| s.hash));
+    }
+
+    for bucket in fs::read_dir(dir.join("objects")).into_iter().flatten().flatten() {
+        let prefix = bucket.file_name().to_string_lossy().to_string();
+        for object in fs::read_dir(bucket.path()).into_iter().flatten().flatten() {
+            let hash = format!("{}{}", prefix, object.file_name().to_string_lossy());
+            if referenced.contains(&hash) {
+                continue;
+            }
+            let size = object.metadata().map(|m| m.len()).unwrap_or(0);
+            if fs::remove_file(object.path()).is_ok() {
+                stats.objects_removed += 1;
+                stats.bytes_freed += size;
+            }
+        }
+        let _ = fs::remove_dir(bucket.path());
+    }
+
+    Ok(stats)
+}
+
+#[tauri::command]
+pub async fn history_get_config(sandbox: tauri::State<'_, SandboxState>, workspace: String) -> Result<HistoryConfig, String> {
+    sandbox.check(&workspace)?;
+    Ok(load_config(Path::new(&workspace)))
+}
+
+#[tauri::command]
+pub async fn history_set_config(
+    sandbox: tauri::State<'_, SandboxState>,
+    workspace: String,
+    config: HistoryConfig,
+) -> Result<(), String> {
+    sandbox.check(&workspace)?;
+    let dir = history_dir(Path::new(&workspace));
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create history: {}", e))?;
+    let content = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    fs::write(dir.join("config.json"), content).map_err(|e| format!("Cannot write history config: {}", e))
+}
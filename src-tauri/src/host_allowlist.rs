@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use url::{Host, Url};
+
+/// Hosts the app always talks to, over https on the default port.
+pub const ALLOWED_HOSTS: &[&str] = &[
+    "api.anthropic.com",
+    "api.openai.com",
+    "generativelanguage.googleapis.com",
+    "shoulde.rs",
+    "api.github.com",
+    "api.crossref.org",
+    "api.exa.ai",
+    "api.openalex.org",
+];
+
+const ALLOWLIST_FILE: &str = "allowed-hosts.json";
+
+/// User additions to the allowlist, stored in `~/.shoulders/allowed-hosts.json`.
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct HostAllowlist {
+    /// Origins as `[scheme://]host[:port]`. The scheme defaults to https and
+    /// the port to the scheme's default; `*.example.com` matches subdomains.
+    pub hosts: Vec<String>,
+    /// Loopback hosts (localhost, 127.0.0.0/8, ::1) are refused unless this is set,
+    /// even when listed in `hosts`
+    pub allow_loopback: bool,
+}
+
+struct Origin {
+    scheme: String,
+    host: String,
+    port: Option<u16>,
+    wildcard: bool,
+}
+
+impl Origin {
+    fn matches(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else { return false };
+        let host_matches = if self.wildcard {
+            host.strip_suffix(self.host.as_str()).is_some_and(|sub| sub.ends_with('.') && sub.len() > 1)
+        } else {
+            host == self.host
+        };
+        host_matches && url.scheme() == self.scheme && url.port_or_known_default() == self.port
+    }
+}
+
+fn parse_origin(entry: &str) -> Result<Origin, String> {
+    let entry = entry.trim().trim_end_matches('/');
+    let (scheme, rest) = match entry.split_once("://") {
+        Some((scheme, rest)) => (scheme.to_ascii_lowercase(), rest),
+        None => ("https".to_string(), entry),
+    };
+    if scheme != "https" && scheme != "http" {
+        return Err(format!("{}: only http and https are supported", entry));
+    }
+    let (wildcard, rest) = match rest.strip_prefix("*.") {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    let url = Url::parse(&format!("{}://{}", scheme, rest)).map_err(|e| format!("{}: {}", entry, e))?;
+    if url.path() != "/" || url.query().is_some() || url.fragment().is_some() || !url.username().is_empty() {
+        return Err(format!("{}: expected scheme://host[:port] without a path", entry));
+    }
+    let host = url.host_str().ok_or_else(|| format!("{}: no host", entry))?;
+    if wildcard && !matches!(url.host(), Some(Host::Domain(_))) {
+        return Err(format!("{}: wildcards only apply to domain names", entry));
+    }
+    Ok(Origin {
+        scheme,
+        host: host.to_string(),
+        port: url.port_or_known_default(),
+        wildcard,
+    })
+}
+
+fn is_loopback(url: &Url) -> bool {
+    match url.host() {
+        Some(Host::Domain(domain)) => {
+            let domain = domain.to_ascii_lowercase();
+            domain == "localhost" || domain.ends_with(".localhost")
+        }
+        Some(Host::Ipv4(ip)) => ip.is_loopback() || ip.is_unspecified(),
+        Some(Host::Ipv6(ip)) => {
+            ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.to_ipv4_mapped().is_some_and(|v4| v4.is_loopback() || v4.is_unspecified())
+        }
+        None => false,
+    }
+}
+
+fn allowlist_path() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Cannot find home directory")?;
+    Ok(home.join(".shoulders").join(ALLOWLIST_FILE))
+}
+
+fn load() -> Result<HostAllowlist, String> {
+    match std::fs::read_to_string(allowlist_path()?) {
+        Ok(content) => serde_json::from_str(&content).map_err(|e| format!("{} is invalid: {}", ALLOWLIST_FILE, e)),
+        Err(_) => Ok(HostAllowlist::default()),
+    }
+}
+
+/// Check a URL against the built-in hosts and the user's allowlist. Used by
+/// every command that sends requests with credentials.
+pub fn validate_url_host(raw_url: &str) -> Result<(), String> {
+    let parsed = Url::parse(raw_url)
+        .map_err(|e| format!("Invalid URL: {}", e))?;
+    let host = parsed.host_str()
+        .ok_or_else(|| "URL has no host".to_string())?;
+
+    // Allow localhost / 127.0.0.1 in debug builds for the local web backend
+    #[cfg(debug_assertions)]
+    {
+        if host == "localhost" || host == "127.0.0.1" {
+            return Ok(());
+        }
+    }
+
+    if parsed.scheme() == "https" && parsed.port().is_none() && ALLOWED_HOSTS.contains(&host) {
+        return Ok(());
+    }
+
+    let allowlist = load()?;
+    if !allowlist.hosts.iter().filter_map(|entry| parse_origin(entry).ok()).any(|origin| origin.matches(&parsed)) {
+        return Err(format!("URL host not in allowlist: {}", origin_of(&parsed)));
+    }
+    if is_loopback(&parsed) && !allowlist.allow_loopback {
+        return Err(format!("Loopback host {} is allowlisted but loopback access is not enabled", origin_of(&parsed)));
+    }
+    Ok(())
+}
+
+fn origin_of(url: &Url) -> String {
+    url.origin().ascii_serialization()
+}
+
+#[tauri::command]
+pub async fn host_allowlist_get() -> Result<HostAllowlist, String> {
+    load()
+}
+
+/// Whether `url` would pass `validate_url_host`, so the UI can hide actions
+/// that would only fail.
+#[tauri::command]
+pub async fn host_allowlist_check(url: String) -> Result<bool, String> {
+    Ok(validate_url_host(&url).is_ok())
+}
+
+/// Validate and save the user's allowlist. Entries are trimmed; blank ones are dropped.
+#[tauri::command]
+pub async fn host_allowlist_set(allowlist: HostAllowlist) -> Result<(), String> {
+    let hosts: Vec<String> = allowlist
+        .hosts
+        .iter()
+        .map(|entry| entry.trim().to_string())
+        .filter(|entry| !entry.is_empty())
+        .collect();
+    for entry in &hosts {
+        parse_origin(entry)?;
+    }
+
+    let path = allowlist_path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create ~/.shoulders: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(&HostAllowlist { hosts, ..allowlist })
+        .map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", ALLOWLIST_FILE, e))
+}
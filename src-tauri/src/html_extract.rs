@@ -0,0 +1,810 @@
+use kuchikiki::traits::*;
+use kuchikiki::NodeRef;
+use serde::Serialize;
+
+/// Bibliographic metadata from `citation_*` meta tags (Highwire/Google Scholar),
+/// with Dublin Core and PRISM tags as fallbacks.
+#[derive(Serialize, Default, Clone)]
+pub struct CitationMeta {
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    pub doi: Option<String>,
+    pub journal: Option<String>,
+    pub publisher: Option<String>,
+    /// As given by the page, usually `YYYY/MM/DD` or `YYYY-MM-DD`
+    pub date: Option<String>,
+    pub pdf_url: Option<String>,
+}
+
+/// Main content of an HTML page.
+pub struct Article {
+    pub title: Option<String>,
+    /// Headings, lists, links, tables and emphasis as Markdown
+    pub markdown: String,
+    /// `None` when the page has no bibliographic meta tags
+    pub citation: Option<CitationMeta>,
+}
+
+/// Element nesting `render` recurses into; anything deeper is flattened to its
+/// text, so a hostile page can't overflow the stack.
+const MAX_DEPTH: usize = 200;
+
+/// Never content, wherever they appear.
+const JUNK_TAGS: &[&str] = &[
+    "script", "style", "noscript", "template", "svg", "canvas", "iframe", "object", "embed", "button",
+    "input", "select", "textarea", "nav", "aside", "dialog", "menu",
+];
+
+const JUNK_ROLES: &[&str] = &[
+    "navigation", "banner", "contentinfo", "dialog", "alertdialog", "complementary", "search", "menu",
+    "menubar", "toolbar",
+];
+
+/// Class/id words marking page chrome. Matched against the `-`/`_` separated
+/// parts of each class name, so `cookie-banner` and `share_links` both hit.
+const NEGATIVE_WORDS: &[&str] = &[
+    "ad", "ads", "advert", "advertisement", "banner", "breadcrumb", "breadcrumbs", "comment", "comments",
+    "consent", "cookie", "cookies", "gdpr", "login", "masthead", "menu", "modal", "navbar", "newsletter",
+    "pagination", "popup", "promo", "recommended", "related", "share", "sharing", "sidebar", "signup",
+    "skip", "social", "sponsor", "sponsored", "subscribe", "toolbar", "widget",
+];
+
+/// Class/id words that keep an element despite a negative word (`sidebar-content`
+/// is rare, `article-comments-toggle` inside the body is not).
+const POSITIVE_WORDS: &[&str] = &[
+    "abstract", "article", "body", "content", "entry", "fulltext", "post", "story", "text",
+];
+
+/// Extract the readable content of `html`. `base_url` resolves relative links.
+pub(crate) fn extract(html: &str, base_url: Option<&str>) -> Article {
+    let document = kuchikiki::parse_html().one(html).document_node;
+
+    let base = document
+        .select_first("base[href]")
+        .ok()
+        .and_then(|b| b.attributes.borrow().get("href").map(str::to_string))
+        .and_then(|href| match base_url.and_then(|u| url::Url::parse(u).ok()) {
+            Some(page) => page.join(&href).ok(),
+            None => url::Url::parse(&href).ok(),
+        })
+        .or_else(|| base_url.and_then(|u| url::Url::parse(u).ok()));
+
+    let citation = citation_meta(&document, base.as_ref());
+    let title = citation
+        .as_ref()
+        .and_then(|c| c.title.clone())
+        .or_else(|| meta_content(&document, "og:title"))
+        .or_else(|| first_text(&document, "title"))
+        .or_else(|| first_text(&document, "h1"));
+
+    remove_junk(&document);
+    let root = content_root(&document);
+
+    let mut writer = Writer::new(base.as_ref());
+    render(&root, &mut writer);
+    let mut markdown = writer.finish();
+    if markdown.is_empty() {
+        markdown = collapse_whitespace(&document.text_contents()).trim().to_string();
+    }
+
+    Article { title, markdown, citation }
+}
+
+// ─── Metadata ────────────────────────────────────────────────────────
+
+fn element_name(node: &NodeRef) -> Option<String> {
+    node.as_element().map(|e| e.name.local.to_string())
+}
+
+fn attr(node: &NodeRef, name: &str) -> Option<String> {
+    node.as_element()?.attributes.borrow().get(name).map(str::to_string)
+}
+
+fn first_text(document: &NodeRef, selector: &str) -> Option<String> {
+    let text = document.select_first(selector).ok()?.text_contents();
+    let text = collapse_whitespace(&text).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// All non-empty `content` values of `<meta name=...>` / `<meta property=...>`
+/// tags with the given name, compared case-insensitively.
+fn meta_values(document: &NodeRef, name: &str) -> Vec<String> {
+    let Ok(metas) = document.select("meta") else { return Vec::new() };
+    metas
+        .filter(|m| {
+            let attrs = m.attributes.borrow();
+            let key = attrs.get("name").or_else(|| attrs.get("property")).unwrap_or("");
+            key.eq_ignore_ascii_case(name)
+        })
+        .filter_map(|m| m.attributes.borrow().get("content").map(|c| c.trim().to_string()))
+        .filter(|c| !c.is_empty())
+        .collect()
+}
+
+fn meta_content(document: &NodeRef, name: &str) -> Option<String> {
+    meta_values(document, name).into_iter().next()
+}
+
+fn first_meta(document: &NodeRef, names: &[&str]) -> Option<String> {
+    names.iter().find_map(|n| meta_content(document, n))
+}
+
+/// `10.xxxx/...` from a bare DOI, a `doi:` prefix or a doi.org URL.
+fn normalize_doi(value: &str) -> Option<String> {
+    let lower = value.to_ascii_lowercase();
+    let start = lower.find("10.")?;
+    let prefix = lower[..start].trim();
+    let known_prefix = prefix.is_empty()
+        || prefix.ends_with("doi:")
+        || prefix.ends_with("doi.org/")
+        || prefix.ends_with("info:doi/");
+    known_prefix.then(|| value[start..].trim().to_string())
+}
+
+fn citation_meta(document: &NodeRef, base: Option<&url::Url>) -> Option<CitationMeta> {
+    let mut authors = meta_values(document, "citation_author");
+    if authors.is_empty() {
+        authors = meta_values(document, "dc.creator");
+    }
+    // Some publishers put every author in one tag, separated by semicolons
+    if authors.len() == 1 && authors[0].contains(';') {
+        authors = authors[0].split(';').map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect();
+    }
+
+    let doi = ["citation_doi", "prism.doi", "dc.identifier", "citation_identifier"]
+        .iter()
+        .flat_map(|n| meta_values(document, n))
+        .find_map(|v| normalize_doi(&v));
+
+    let citation = CitationMeta {
+        title: first_meta(document, &["citation_title", "dc.title"]),
+        authors,
+        doi,
+        journal: first_meta(
+            document,
+            &["citation_journal_title", "citation_conference_title", "prism.publicationname"],
+        ),
+        publisher: first_meta(document, &["citation_publisher", "dc.publisher"]),
+        date: first_meta(
+            document,
+            &["citation_publication_date", "citation_date", "citation_online_date", "dc.date"],
+        ),
+        pdf_url: meta_content(document, "citation_pdf_url")
+            .map(|u| base.and_then(|b| b.join(&u).ok()).map_or(u, |u| u.to_string())),
+    };
+
+    let empty = citation.title.is_none()
+        && citation.authors.is_empty()
+        && citation.doi.is_none()
+        && citation.journal.is_none()
+        && citation.pdf_url.is_none();
+    (!empty).then_some(citation)
+}
+
+// ─── Content selection ───────────────────────────────────────────────
+
+fn class_words(node: &NodeRef) -> Vec<String> {
+    let class = attr(node, "class").unwrap_or_default();
+    let id = attr(node, "id").unwrap_or_default();
+    format!("{} {}", class, id)
+        .split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_ascii_lowercase())
+        .collect()
+}
+
+fn is_junk(node: &NodeRef, name: &str) -> bool {
+    if matches!(name, "html" | "body" | "article" | "main") {
+        return false;
+    }
+    if JUNK_TAGS.contains(&name) {
+        return true;
+    }
+    let element = node.as_element().unwrap();
+    let attrs = element.attributes.borrow();
+    if attrs.contains("hidden") || attrs.get("aria-hidden") == Some("true") {
+        return true;
+    }
+    if attrs.get("role").is_some_and(|r| JUNK_ROLES.contains(&r)) {
+        return true;
+    }
+    if let Some(style) = attrs.get("style") {
+        let style: String = style.chars().filter(|c| !c.is_whitespace()).collect();
+        if style.contains("display:none") || style.contains("visibility:hidden") {
+            return true;
+        }
+    }
+    drop(attrs);
+
+    // Page-level header/footer; an article's own header holds its title
+    if matches!(name, "header" | "footer")
+        && !node.ancestors().any(|a| matches!(element_name(&a).as_deref(), Some("article" | "main")))
+    {
+        return true;
+    }
+
+    let words = class_words(node);
+    words.iter().any(|w| NEGATIVE_WORDS.contains(&w.as_str()))
+        && !words.iter().any(|w| POSITIVE_WORDS.contains(&w.as_str()))
+        // A layout wrapper like `has-sidebar` around the whole page
+        && !node.descendants().any(|d| {
+            matches!(element_name(&d).as_deref(), Some("article" | "main" | "h1"))
+                || attr(&d, "role").as_deref() == Some("main")
+        })
+}
+
+fn remove_junk(document: &NodeRef) {
+    let junk: Vec<NodeRef> = document
+        .descendants()
+        .filter(|n| match element_name(n) {
+            Some(name) => is_junk(n, &name),
+            None => n.as_comment().is_some(),
+        })
+        .collect();
+    for node in junk {
+        node.detach();
+    }
+}
+
+fn text_len(node: &NodeRef) -> usize {
+    node.descendants()
+        .filter_map(|n| n.as_text().map(|t| t.borrow().split_whitespace().map(|w| w.len() + 1).sum::<usize>()))
+        .sum()
+}
+
+fn link_density(node: &NodeRef) -> f64 {
+    let total = text_len(node);
+    if total == 0 {
+        return 0.0;
+    }
+    let links: usize = node
+        .descendants()
+        .filter(|n| element_name(n).as_deref() == Some("a"))
+        .map(|a| text_len(&a))
+        .sum();
+    links as f64 / total as f64
+}
+
+fn class_weight(node: &NodeRef) -> f64 {
+    let words = class_words(node);
+    let mut weight = 0.0;
+    if words.iter().any(|w| POSITIVE_WORDS.contains(&w.as_str())) {
+        weight += 25.0;
+    }
+    if words.iter().any(|w| NEGATIVE_WORDS.contains(&w.as_str())) {
+        weight -= 25.0;
+    }
+    weight
+}
+
+/// The element holding the main content: a dominant `<article>` or `<main>`,
+/// otherwise the best-scoring paragraph container (the Readability heuristic:
+/// paragraphs score their parent fully and their grandparent by half, weighted
+/// by length and commas, discounted by link density).
+fn content_root(document: &NodeRef) -> NodeRef {
+    let body = document
+        .select_first("body")
+        .map(|b| b.as_node().clone())
+        .unwrap_or_else(|_| document.clone());
+    let body_len = text_len(&body).max(1);
+
+    for selector in ["article", "main, [role=main]"] {
+        let Ok(found) = body.select(selector) else { continue };
+        let best = found.map(|e| e.as_node().clone()).max_by_key(text_len);
+        if let Some(node) = best {
+            if text_len(&node) * 2 >= body_len {
+                return node;
+            }
+        }
+    }
+
+    let mut scores: Vec<(NodeRef, f64)> = Vec::new();
+    let mut add = |node: NodeRef, points: f64| {
+        if let Some(entry) = scores.iter_mut().find(|(n, _)| *n == node) {
+            entry.1 += points;
+        } else {
+            let initial = class_weight(&node)
+                + match element_name(&node).as_deref() {
+                    Some("article") | Some("main") => 10.0,
+                    Some("div") | Some("section") => 5.0,
+                    Some("pre") | Some("td") | Some("blockquote") => 3.0,
+                    _ => 0.0,
+                };
+            scores.push((node, initial + points));
+        }
+    };
+
+    for paragraph in body.descendants() {
+        if !matches!(element_name(&paragraph).as_deref(), Some("p" | "pre" | "td")) {
+            continue;
+        }
+        let text = paragraph.text_contents();
+        let len = text.trim().chars().count();
+        if len < 25 {
+            continue;
+        }
+        let points = 1.0 + text.matches(',').count() as f64 + (len as f64 / 100.0).min(3.0);
+        let Some(parent) = paragraph.parent().filter(|p| p.as_element().is_some()) else { continue };
+        if let Some(grandparent) = parent.parent().filter(|g| g.as_element().is_some()) {
+            add(grandparent, points / 2.0);
+        }
+        add(parent, points);
+    }
+
+    scores
+        .into_iter()
+        .map(|(node, score)| {
+            let density = link_density(&node);
+            (node, score * (1.0 - density))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(node, _)| node)
+        .unwrap_or(body)
+}
+
+// ─── Markdown rendering ──────────────────────────────────────────────
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Builds Markdown with HTML whitespace semantics: runs of whitespace become
+/// one space, and no space is emitted at the start of a line or right after
+/// an opening marker.
+struct Writer<'a> {
+    out: String,
+    base: Option<&'a url::Url>,
+    pending_space: bool,
+    /// Inside `<pre>`: text is copied verbatim
+    preformatted: bool,
+    /// Inside a heading or table cell, where line breaks become spaces
+    single_line: bool,
+    /// Element nesting of the node being rendered
+    depth: usize,
+}
+
+impl<'a> Writer<'a> {
+    fn new(base: Option<&'a url::Url>) -> Self {
+        Self { out: String::new(), base, pending_space: false, preformatted: false, single_line: false, depth: 0 }
+    }
+
+    /// A fresh writer for content rendered separately (list items, cells, quotes).
+    fn nested(&self) -> Writer<'a> {
+        Writer { single_line: self.single_line, depth: self.depth, ..Writer::new(self.base) }
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.out.is_empty() || self.out.ends_with('\n')
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.preformatted {
+            self.out.push_str(text);
+            return;
+        }
+        if text.starts_with(char::is_whitespace) {
+            self.pending_space = true;
+        }
+        for (i, word) in text.split_whitespace().enumerate() {
+            if i > 0 {
+                self.pending_space = true;
+            }
+            self.word(word);
+        }
+        if text.ends_with(char::is_whitespace) {
+            self.pending_space = true;
+        }
+    }
+
+    fn word(&mut self, word: &str) {
+        if self.pending_space && !self.at_line_start() && !self.out.ends_with(' ') {
+            self.out.push(' ');
+        }
+        self.pending_space = false;
+        self.out.push_str(word);
+    }
+
+    /// Opening marker (`**`, `[`): takes the pending space before it.
+    fn open(&mut self, marker: &str) -> usize {
+        if self.pending_space && !self.at_line_start() && !self.out.ends_with(' ') {
+            self.out.push(' ');
+        }
+        self.pending_space = false;
+        let start = self.out.len();
+        self.out.push_str(marker);
+        start
+    }
+
+    /// Closing marker; drops the whole span if nothing was written since `open`.
+    /// Trailing whitespace is still pending, so it lands after the marker.
+    fn close(&mut self, start: usize, open_len: usize, marker: &str) {
+        if self.out.len() == start + open_len {
+            self.out.truncate(start);
+        } else {
+            self.out.push_str(marker);
+        }
+    }
+
+    fn line_break(&mut self) {
+        if self.single_line {
+            self.pending_space = true;
+            return;
+        }
+        self.trim_end_spaces();
+        self.out.push('\n');
+        self.pending_space = false;
+    }
+
+    fn block_break(&mut self) {
+        if self.single_line {
+            self.pending_space = true;
+            return;
+        }
+        self.trim_end_spaces();
+        if !self.out.is_empty() {
+            while !self.out.ends_with("\n\n") {
+                self.out.push('\n');
+            }
+        }
+        self.pending_space = false;
+    }
+
+    /// Append an already-rendered block.
+    fn block(&mut self, text: &str) {
+        if text.trim().is_empty() {
+            return;
+        }
+        self.block_break();
+        if self.single_line {
+            self.word(&collapse_whitespace(text));
+        } else {
+            self.out.push_str(text.trim_end());
+        }
+        self.block_break();
+    }
+
+    fn trim_end_spaces(&mut self) {
+        let trimmed = self.out.trim_end_matches([' ', '\t']).len();
+        self.out.truncate(trimmed);
+    }
+
+    fn resolve(&self, href: &str) -> Option<String> {
+        let href = href.trim();
+        if href.is_empty() || href.starts_with('#') || href.to_ascii_lowercase().starts_with("javascript:") {
+            return None;
+        }
+        match self.base {
+            Some(base) => base.join(href).ok().map(|u| u.to_string()),
+            None => Some(href.to_string()),
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.trim_end_spaces();
+        let mut result = String::new();
+        let mut blank_lines = 0;
+        for line in self.out.lines() {
+            if line.trim().is_empty() {
+                blank_lines += 1;
+                continue;
+            }
+            if !result.is_empty() {
+                result.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+            }
+            blank_lines = 0;
+            result.push_str(line.trim_end());
+        }
+        result
+    }
+}
+
+fn render_children(node: &NodeRef, w: &mut Writer) {
+    if w.depth >= MAX_DEPTH {
+        w.text(&node.text_contents());
+        return;
+    }
+    w.depth += 1;
+    for child in node.children() {
+        render(&child, w);
+    }
+    w.depth -= 1;
+}
+
+fn render_nested(node: &NodeRef, w: &Writer) -> String {
+    let mut nested = w.nested();
+    render_children(node, &mut nested);
+    nested.finish()
+}
+
+fn render(node: &NodeRef, w: &mut Writer) {
+    if let Some(text) = node.as_text() {
+        w.text(&text.borrow());
+        return;
+    }
+    let Some(name) = element_name(node) else {
+        // Document and fragment nodes
+        render_children(node, w);
+        return;
+    };
+
+    match name.as_str() {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = name[1..].parse::<usize>().unwrap_or(1);
+            let mut nested = w.nested();
+            nested.single_line = true;
+            render_children(node, &mut nested);
+            let heading = nested.finish();
+            if w.single_line {
+                w.block(&heading);
+            } else if !heading.is_empty() {
+                w.block(&format!("{} {}", "#".repeat(level), heading));
+            }
+        }
+        "br" => w.line_break(),
+        "hr" => w.block("---"),
+        "img" | "picture" | "video" | "audio" | "source" | "track" | "map" | "area" | "head" | "title"
+        | "meta" | "link" => {}
+        "pre" => {
+            let mut nested = w.nested();
+            nested.preformatted = true;
+            render_children(node, &mut nested);
+            let code = nested.out.trim_matches('\n');
+            if code.trim().is_empty() {
+                return;
+            }
+            let language = node
+                .select_first("code")
+                .ok()
+                .and_then(|c| c.attributes.borrow().get("class").map(str::to_string))
+                .and_then(|class| {
+                    class.split_whitespace().find_map(|c| {
+                        c.strip_prefix("language-").or_else(|| c.strip_prefix("lang-")).map(str::to_string)
+                    })
+                })
+                .unwrap_or_default();
+            let fence = if code.contains("```") { "~~~" } else { "```" };
+            w.block(&format!("{}{}\n{}\n{}", fence, language, code, fence));
+        }
+        "code" | "kbd" | "samp" | "tt" if !w.preformatted => {
+            let code = collapse_whitespace(&node.text_contents());
+            if !code.is_empty() {
+                let tick = if code.contains('`') { "``" } else { "`" };
+                let start = w.open(tick);
+                w.out.push_str(&code);
+                w.close(start, tick.len(), tick);
+            }
+        }
+        "strong" | "b" => {
+            let start = w.open("**");
+            render_children(node, w);
+            w.close(start, 2, "**");
+        }
+        "em" | "i" | "cite" | "dfn" => {
+            let start = w.open("*");
+            render_children(node, w);
+            w.close(start, 1, "*");
+        }
+        "a" => match attr(node, "href").and_then(|h| w.resolve(&h)) {
+            Some(url) => {
+                let start = w.open("[");
+                render_children(node, w);
+                w.close(start, 1, &format!("]({})", url));
+            }
+            None => render_children(node, w),
+        },
+        "ul" | "ol" => render_list(node, w, name == "ol"),
+        "blockquote" => {
+            let quoted = render_nested(node, w);
+            let quoted: Vec<String> = quoted
+                .lines()
+                .map(|l| if l.is_empty() { ">".to_string() } else { format!("> {}", l) })
+                .collect();
+            w.block(&quoted.join("\n"));
+        }
+        "table" => render_table(node, w),
+        "dt" => {
+            let term = render_nested(node, w);
+            if !term.is_empty() {
+                w.block(&format!("**{}**", term));
+            }
+        }
+        "p" | "div" | "section" | "article" | "main" | "header" | "footer" | "figure" | "figcaption"
+        | "address" | "details" | "summary" | "dl" | "dd" | "caption" | "center" | "li" | "tr" | "body"
+        | "html" => {
+            w.block_break();
+            render_children(node, w);
+            w.block_break();
+        }
+        _ => render_children(node, w),
+    }
+}
+
+fn render_list(node: &NodeRef, w: &mut Writer, ordered: bool) {
+    let start = attr(node, "start").and_then(|s| s.parse::<usize>().ok()).unwrap_or(1);
+    let mut items = Vec::new();
+    for (i, item) in node.children().filter(|c| c.as_element().is_some()).enumerate() {
+        let text = render_nested(&item, w);
+        if text.is_empty() {
+            continue;
+        }
+        let marker = if ordered { format!("{}. ", start + i) } else { "- ".to_string() };
+        let indent = " ".repeat(marker.len());
+        // Tight list: paragraphs inside an item are kept on consecutive lines
+        let lines: Vec<String> = text
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .enumerate()
+            .map(|(j, l)| if j == 0 { format!("{}{}", marker, l) } else { format!("{}{}", indent, l) })
+            .collect();
+        items.push(lines.join("\n"));
+    }
+    if !items.is_empty() {
+        w.block(&items.join("\n"));
+    }
+}
+
+fn element_children<'a>(node: &'a NodeRef, names: &'a [&str]) -> impl Iterator<Item = NodeRef> + 'a {
+    node.children().filter(move |c| element_name(c).is_some_and(|n| names.contains(&n.as_str())))
+}
+
+fn render_table(node: &NodeRef, w: &mut Writer) {
+    // Layout tables (nested tables, or no real rows) are rendered as plain blocks
+    let nested_table = node.descendants().any(|d| element_name(&d).as_deref() == Some("table"));
+    let mut rows: Vec<NodeRef> = element_children(node, &["tr"]).collect();
+    for section in element_children(node, &["thead", "tbody", "tfoot"]) {
+        rows.extend(element_children(&section, &["tr"]));
+    }
+    if nested_table || rows.is_empty() || w.single_line {
+        w.block_break();
+        render_children(node, w);
+        w.block_break();
+        return;
+    }
+
+    let mut cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            element_children(row, &["td", "th"])
+                .map(|cell| {
+                    let mut nested = w.nested();
+                    nested.single_line = true;
+                    render_children(&cell, &mut nested);
+                    nested.finish().replace('|', "\\|")
+                })
+                .collect()
+        })
+        .filter(|row: &Vec<String>| row.iter().any(|c| !c.is_empty()))
+        .collect();
+    let columns = cells.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return;
+    }
+    for row in &mut cells {
+        row.resize(columns, String::new());
+    }
+
+    let mut lines = Vec::new();
+    if let Some(caption) = element_children(node, &["caption"]).next() {
+        let caption = collapse_whitespace(&caption.text_contents());
+        if !caption.is_empty() {
+            lines.push(format!("*{}*", caption));
+            lines.push(String::new());
+        }
+    }
+    for (i, row) in cells.iter().enumerate() {
+        lines.push(format!("| {} |", row.join(" | ")));
+        if i == 0 {
+            lines.push(format!("|{}", " --- |".repeat(columns)));
+        }
+    }
+    w.block(&lines.join("\n"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn markdown(body: &str) -> String {
+        extract(&format!("<html><body>{}</body></html>", body), Some("https://example.com/a/page.html")).markdown
+    }
+
+    #[test]
+    fn drops_page_chrome() {
+        let md = markdown(
+            r#"<nav><a href="/">Home</a></nav>
+            <div class="cookie-banner">We use cookies</div>
+            <header>Site name</header>
+            <article><header><h1>Title</h1></header><p>Body text.</p></article>
+            <div style="display: none">Hidden</div>
+            <script>alert(1)</script>
+            <footer>Copyright</footer>"#,
+        );
+        assert_eq!(md, "# Title\n\nBody text.");
+    }
+
+    #[test]
+    fn picks_the_paragraph_container() {
+        let long = "A sentence long enough to count, with a comma, and another one. ".repeat(3);
+        let md = markdown(&format!(
+            r#"<div class="links"><p><a href="/x">{0}</a></p></div>
+            <div id="story"><p>{0}</p><p>{0}</p></div>"#,
+            long
+        ));
+        assert_eq!(md, format!("{0}\n\n{0}", long.trim()));
+    }
+
+    #[test]
+    fn renders_inline_markup() {
+        assert_eq!(
+            markdown(r#"<p>Some <b>bold</b>, <em>emphasis</em> and <a href="../b.html">a link</a> with <code>x = 1</code>.</p>"#),
+            "Some **bold**, *emphasis* and [a link](https://example.com/b.html) with `x = 1`."
+        );
+        assert_eq!(markdown("<p>Empty <b> </b><a href='#top'>anchor</a></p>"), "Empty anchor");
+    }
+
+    #[test]
+    fn renders_blocks() {
+        assert_eq!(
+            markdown("<h2>Steps</h2><ol start='3'><li>One</li><li><p>Two</p><p>more</p></li></ol><ul><li>Item</li></ul>"),
+            "## Steps\n\n3. One\n4. Two\n   more\n\n- Item"
+        );
+        assert_eq!(
+            markdown("<pre><code class='language-rust'>fn main() {\n    run();\n}</code></pre>"),
+            "```rust\nfn main() {\n    run();\n}\n```"
+        );
+        assert_eq!(markdown("<blockquote><p>Quoted</p><p>twice</p></blockquote>"), "> Quoted\n>\n> twice");
+    }
+
+    #[test]
+    fn renders_tables() {
+        assert_eq!(
+            markdown("<table><caption>Results</caption><tr><th>Name</th><th>Value</th></tr><tr><td>a|b</td></tr></table>"),
+            "*Results*\n\n| Name | Value |\n| --- | --- |\n| a\\|b |  |"
+        );
+    }
+
+    #[test]
+    fn flattens_deep_nesting() {
+        let html = format!("{}deep{}", "<span>".repeat(MAX_DEPTH * 5), "</span>".repeat(MAX_DEPTH * 5));
+        assert_eq!(markdown(&html), "deep");
+    }
+
+    #[test]
+    fn reads_citation_meta() {
+        let article = extract(
+            r#"<html><head>
+            <base href="/papers/">
+            <meta name="citation_title" content="A Study">
+            <meta name="citation_author" content="Doe, Jane">
+            <meta name="citation_author" content="Roe, Rick">
+            <meta name="dc.identifier" content="https://doi.org/10.1234/abc.5">
+            <meta name="citation_journal_title" content="Journal">
+            <meta name="citation_publication_date" content="2020/05/01">
+            <meta name="citation_pdf_url" content="study.pdf">
+            <title>Ignored</title></head><body><p>Text</p></body></html>"#,
+            Some("https://example.com/a/page.html"),
+        );
+        assert_eq!(article.title.as_deref(), Some("A Study"));
+        let citation = article.citation.unwrap();
+        assert_eq!(citation.authors, ["Doe, Jane", "Roe, Rick"]);
+        assert_eq!(citation.doi.as_deref(), Some("10.1234/abc.5"));
+        assert_eq!(citation.journal.as_deref(), Some("Journal"));
+        assert_eq!(citation.date.as_deref(), Some("2020/05/01"));
+        assert_eq!(citation.pdf_url.as_deref(), Some("https://example.com/papers/study.pdf"));
+    }
+
+    #[test]
+    fn normalizes_dois() {
+        assert_eq!(normalize_doi("doi:10.1/x").as_deref(), Some("10.1/x"));
+        assert_eq!(normalize_doi("10.1/x").as_deref(), Some("10.1/x"));
+        assert_eq!(normalize_doi("info:doi/10.1/x").as_deref(), Some("10.1/x"));
+        assert_eq!(normalize_doi("ISBN 10.1/x"), None);
+    }
+
+    #[test]
+    fn falls_back_to_page_title() {
+        let article = extract("<html><head><title> Page  title </title></head><body></body></html>", None);
+        assert_eq!(article.title.as_deref(), Some("Page title"));
+        assert!(article.citation.is_none());
+    }
+}
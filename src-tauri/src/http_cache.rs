@@ -0,0 +1,550 @@
+use reqwest::header::{HeaderMap, HeaderValue, CACHE_CONTROL, CONTENT_TYPE, ETAG, EXPIRES, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+pub struct HttpCacheState {
+    pub conn: Mutex<Option<Connection>>,
+}
+
+impl Default for HttpCacheState {
+    fn default() -> Self {
+        Self {
+            conn: Mutex::new(None),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HttpCacheConfig {
+    pub enabled: bool,
+    /// How long a response stays fresh when the server sends no
+    /// Cache-Control max-age or Expires
+    pub ttl_secs: i64,
+    /// Total body size kept on disk; least recently used entries go first
+    pub max_bytes: i64,
+}
+
+impl Default for HttpCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ttl_secs: 24 * 60 * 60,
+            max_bytes: 100 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct HttpCacheStats {
+    pub entries: i64,
+    pub bytes: i64,
+    /// Entries past their freshness lifetime, kept for revalidation and offline use
+    pub stale_entries: i64,
+    pub hits: i64,
+    pub revalidated: i64,
+    pub misses: i64,
+    pub offline_hits: i64,
+    pub config: HttpCacheConfig,
+}
+
+/// A response, from the network or the cache.
+pub(crate) struct CachedResponse {
+    pub status: u16,
+    pub final_url: String,
+    /// Raw Content-Type header value
+    pub content_type: String,
+    /// Response headers; for cache hits, only Content-Type, ETag and Last-Modified
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+struct Entry {
+    final_url: String,
+    content_type: String,
+    body: Vec<u8>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    expires_at: i64,
+}
+
+fn get_db_path() -> Result<String, String> {
+    let home = dirs::home_dir().ok_or("Cannot find home directory")?;
+    let shoulders_dir = home.join(".shoulders");
+    if !shoulders_dir.exists() {
+        std::fs::create_dir_all(&shoulders_dir)
+            .map_err(|e| format!("Failed to create ~/.shoulders: {}", e))?;
+    }
+    Ok(shoulders_dir.join("http-cache.db").to_string_lossy().to_string())
+}
+
+fn ensure_connection(state: &HttpCacheState) -> Result<(), String> {
+    let mut guard = state.conn.lock().map_err(|e| e.to_string())?;
+    if guard.is_some() {
+        return Ok(());
+    }
+
+    let path = get_db_path()?;
+    let conn = Connection::open(&path).map_err(|e| format!("Failed to open HTTP cache: {}", e))?;
+
+    conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA busy_timeout=5000;")
+        .map_err(|e| format!("Failed to set pragmas: {}", e))?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS cache_entries (
+            key TEXT PRIMARY KEY,
+            url TEXT NOT NULL,
+            final_url TEXT NOT NULL,
+            content_type TEXT NOT NULL,
+            body BLOB NOT NULL,
+            size INTEGER NOT NULL,
+            etag TEXT,
+            last_modified TEXT,
+            stored_at INTEGER NOT NULL,
+            expires_at INTEGER NOT NULL,
+            last_used INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_cache_last_used ON cache_entries(last_used);
+        CREATE TABLE IF NOT EXISTS cache_settings (
+            key TEXT PRIMARY KEY,
+            value TEXT
+        );"
+    ).map_err(|e| format!("Failed to create schema: {}", e))?;
+
+    *guard = Some(conn);
+    Ok(())
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// Cache key for a request. Request headers are part of the key, so responses
+/// for different Accept types or credentials never mix.
+pub(crate) fn cache_key<'a>(url: &str, headers: impl IntoIterator<Item = (&'a str, &'a str)>) -> String {
+    use sha2::{Digest, Sha256};
+    let mut headers: Vec<(String, &str)> = headers
+        .into_iter()
+        .map(|(name, value)| (name.to_ascii_lowercase(), value))
+        .collect();
+    headers.sort();
+    let mut hasher = Sha256::new();
+    hasher.update(b"GET ");
+    hasher.update(url.as_bytes());
+    for (name, value) in headers {
+        hasher.update(b"\n");
+        hasher.update(name.as_bytes());
+        hasher.update(b": ");
+        hasher.update(value.as_bytes());
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Whether a request sends credentials. Such responses are per-user and never
+/// cached, even though the key would keep them apart: the cache is a plain
+/// file under `~/.shoulders` and should not hold authenticated content.
+pub(crate) fn has_credentials<'a>(headers: impl IntoIterator<Item = (&'a str, &'a str)>) -> bool {
+    headers.into_iter().any(|(name, _)| {
+        let name = name.to_ascii_lowercase();
+        matches!(name.as_str(), "authorization" | "proxy-authorization" | "cookie") || name.ends_with("api-key")
+    })
+}
+
+/// When a response stops being fresh, or `None` if it must not be stored.
+/// `no-store` and `private` responses are not stored; `no-cache` responses are
+/// stored but revalidated on every use.
+fn expires_at(headers: &HeaderMap, ttl_secs: i64, now: i64) -> Option<i64> {
+    if let Some(cc) = headers.get(CACHE_CONTROL).and_then(|v| v.to_str().ok()) {
+        let mut max_age = None;
+        for directive in cc.split(',').map(|d| d.trim().to_ascii_lowercase()) {
+            if directive == "no-store" || directive == "private" || directive.starts_with("private=") {
+                return None;
+            }
+            if directive == "no-cache" {
+                return Some(now);
+            }
+            if let Some(secs) = directive.strip_prefix("max-age=") {
+                max_age = secs.trim_matches('"').parse::<i64>().ok();
+            }
+        }
+        if let Some(secs) = max_age {
+            return Some(now + secs.max(0));
+        }
+    }
+    if let Some(expires) = headers.get(EXPIRES).and_then(|v| v.to_str().ok()) {
+        // An unparseable Expires (often "0" or "-1") means already expired
+        return Some(match chrono::DateTime::parse_from_rfc2822(expires) {
+            Ok(date) => date.timestamp(),
+            Err(_) => now,
+        });
+    }
+    Some(now + ttl_secs)
+}
+
+fn header_string(headers: &HeaderMap, name: reqwest::header::HeaderName) -> Option<String> {
+    headers.get(name).and_then(|v| v.to_str().ok()).map(|v| v.to_string())
+}
+
+fn load_config(conn: &Connection) -> HttpCacheConfig {
+    conn.query_row("SELECT value FROM cache_settings WHERE key = 'config'", [], |row| row.get::<_, String>(0))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn bump_counter(conn: &Connection, name: &str) {
+    let _ = conn.execute(
+        "INSERT INTO cache_settings (key, value) VALUES (?1, '1')
+         ON CONFLICT(key) DO UPDATE SET value = CAST(value AS INTEGER) + 1",
+        params![format!("stat_{}", name)],
+    );
+}
+
+fn read_counter(conn: &Connection, name: &str) -> i64 {
+    conn.query_row(
+        "SELECT CAST(value AS INTEGER) FROM cache_settings WHERE key = ?1",
+        params![format!("stat_{}", name)],
+        |row| row.get(0),
+    )
+    .unwrap_or(0)
+}
+
+fn lookup(state: &HttpCacheState, key: &str) -> Result<(HttpCacheConfig, Option<Entry>), String> {
+    ensure_connection(state)?;
+    let guard = state.conn.lock().map_err(|e| e.to_string())?;
+    let conn = guard.as_ref().ok_or("DB not initialized")?;
+
+    let config = load_config(conn);
+    if !config.enabled {
+        return Ok((config, None));
+    }
+    let entry = conn
+        .query_row(
+            "SELECT final_url, content_type, body, etag, last_modified, expires_at FROM cache_entries WHERE key = ?1",
+            params![key],
+            |row| {
+                Ok(Entry {
+                    final_url: row.get(0)?,
+                    content_type: row.get(1)?,
+                    body: row.get(2)?,
+                    etag: row.get(3)?,
+                    last_modified: row.get(4)?,
+                    expires_at: row.get(5)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| format!("Cache lookup failed: {}", e))?;
+    Ok((config, entry))
+}
+
+/// Record a cache hit: refresh the LRU position and, after a 304, the expiry.
+fn touch(state: &HttpCacheState, key: &str, expires_at: Option<i64>, counter: &str) -> Result<(), String> {
+    let guard = state.conn.lock().map_err(|e| e.to_string())?;
+    let conn = guard.as_ref().ok_or("DB not initialized")?;
+    conn.execute(
+        "UPDATE cache_entries SET last_used = ?2, expires_at = COALESCE(?3, expires_at) WHERE key = ?1",
+        params![key, now(), expires_at],
+    )
+    .map_err(|e| format!("Cache update failed: {}", e))?;
+    bump_counter(conn, counter);
+    Ok(())
+}
+
+fn store(
+    state: &HttpCacheState,
+    config: &HttpCacheConfig,
+    key: &str,
+    url: &str,
+    response: &CachedResponse,
+) -> Result<(), String> {
+    let guard = state.conn.lock().map_err(|e| e.to_string())?;
+    let conn = guard.as_ref().ok_or("DB not initialized")?;
+    bump_counter(conn, "misses");
+
+    let now = now();
+    let size = response.body.len() as i64;
+    // One large download should not flush everything else
+    let headers = &response.headers;
+    let Some(expires_at) = expires_at(headers, config.ttl_secs, now).filter(|_| size <= config.max_bytes / 4) else {
+        conn.execute("DELETE FROM cache_entries WHERE key = ?1", params![key])
+            .map_err(|e| format!("Cache update failed: {}", e))?;
+        return Ok(());
+    };
+
+    conn.execute(
+        "INSERT OR REPLACE INTO cache_entries
+         (key, url, final_url, content_type, body, size, etag, last_modified, stored_at, expires_at, last_used)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?9)",
+        params![
+            key,
+            url,
+            response.final_url,
+            response.content_type,
+            response.body,
+            size,
+            header_string(headers, ETAG),
+            header_string(headers, LAST_MODIFIED),
+            now,
+            expires_at,
+        ],
+    )
+    .map_err(|e| format!("Cache insert failed: {}", e))?;
+    evict(conn, config.max_bytes)
+}
+
+/// Drop least recently used entries until the cache fits in `max_bytes`.
+fn evict(conn: &Connection, max_bytes: i64) -> Result<(), String> {
+    let total: i64 = conn
+        .query_row("SELECT COALESCE(SUM(size), 0) FROM cache_entries", [], |row| row.get(0))
+        .map_err(|e| format!("Query failed: {}", e))?;
+    if total <= max_bytes {
+        return Ok(());
+    }
+    conn.execute(
+        "DELETE FROM cache_entries WHERE key IN (
+            SELECT key FROM (
+                SELECT key, size, SUM(size) OVER (ORDER BY last_used, stored_at) AS running
+                FROM cache_entries
+            ) WHERE running - size < ?1
+        )",
+        params![total - max_bytes],
+    )
+    .map_err(|e| format!("Cache eviction failed: {}", e))?;
+    Ok(())
+}
+
+async fn read_response(response: reqwest::Response, max_body: usize) -> Result<CachedResponse, String> {
+    let status = response.status().as_u16();
+    let final_url = response.url().to_string();
+    let content_type = header_string(response.headers(), CONTENT_TYPE).unwrap_or_default();
+    let headers = response.headers().clone();
+    let body = crate::fs_commands::read_body_limited(response, max_body).await?;
+    Ok(CachedResponse { status, final_url, content_type, headers, body })
+}
+
+fn from_entry(entry: Entry) -> CachedResponse {
+    let mut headers = HeaderMap::new();
+    let stored = [
+        (CONTENT_TYPE, Some(&entry.content_type)),
+        (ETAG, entry.etag.as_ref()),
+        (LAST_MODIFIED, entry.last_modified.as_ref()),
+    ];
+    for (name, value) in stored {
+        if let Some(value) = value.and_then(|v| HeaderValue::from_str(v).ok()) {
+            headers.insert(name, value);
+        }
+    }
+    CachedResponse {
+        status: 200,
+        final_url: entry.final_url,
+        content_type: entry.content_type,
+        headers,
+        body: entry.body,
+    }
+}
+
+/// Send a GET through the cache. A fresh entry is returned without touching
+/// the network; a stale one is revalidated with its ETag / Last-Modified, and
+/// served as-is when the network is unreachable. Only 200 responses are
+/// stored. Cache failures never fail the request; they just bypass the cache.
+pub(crate) async fn get(
+    state: &HttpCacheState,
+    key: &str,
+    url: &str,
+    mut request: reqwest::RequestBuilder,
+    max_body: usize,
+) -> Result<CachedResponse, String> {
+    let (config, entry) = match lookup(state, key) {
+        Ok(found) => found,
+        Err(e) => {
+            eprintln!("[http-cache] {}", e);
+            let response = request.send().await.map_err(|e| e.to_string())?;
+            return read_response(response, max_body).await;
+        }
+    };
+    if !config.enabled {
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        return read_response(response, max_body).await;
+    }
+
+    let entry = match entry {
+        Some(entry) if entry.expires_at > now() => {
+            if let Err(e) = touch(state, key, None, "hits") {
+                eprintln!("[http-cache] {}", e);
+            }
+            return Ok(from_entry(entry));
+        }
+        entry => entry,
+    };
+    if let Some(ref entry) = entry {
+        if let Some(ref etag) = entry.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(ref last_modified) = entry.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            return match entry {
+                Some(entry) if e.is_connect() || e.is_timeout() => {
+                    if let Err(e) = touch(state, key, None, "offline_hits") {
+                        eprintln!("[http-cache] {}", e);
+                    }
+                    Ok(from_entry(entry))
+                }
+                _ => Err(e.to_string()),
+            };
+        }
+    };
+
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        if let Some(entry) = entry {
+            let expires_at = expires_at(response.headers(), config.ttl_secs, now());
+            if let Err(e) = touch(state, key, expires_at, "revalidated") {
+                eprintln!("[http-cache] {}", e);
+            }
+            return Ok(from_entry(entry));
+        }
+    }
+
+    let result = read_response(response, max_body).await?;
+    if result.status == 200 {
+        if let Err(e) = store(state, &config, key, url, &result) {
+            eprintln!("[http-cache] {}", e);
+        }
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn http_cache_stats(state: tauri::State<'_, HttpCacheState>) -> Result<HttpCacheStats, String> {
+    ensure_connection(&state)?;
+    let guard = state.conn.lock().map_err(|e| e.to_string())?;
+    let conn = guard.as_ref().ok_or("DB not initialized")?;
+
+    let (entries, bytes, stale_entries) = conn
+        .query_row(
+            "SELECT COUNT(*), COALESCE(SUM(size), 0), COUNT(CASE WHEN expires_at <= ?1 THEN 1 END) FROM cache_entries",
+            params![now()],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .map_err(|e| format!("Query failed: {}", e))?;
+
+    Ok(HttpCacheStats {
+        entries,
+        bytes,
+        stale_entries,
+        hits: read_counter(conn, "hits"),
+        revalidated: read_counter(conn, "revalidated"),
+        misses: read_counter(conn, "misses"),
+        offline_hits: read_counter(conn, "offline_hits"),
+        config: load_config(conn),
+    })
+}
+
+/// Remove cached responses. With `url_prefix`, only entries whose request URL
+/// starts with it (e.g. `https://api.crossref.org/`). Returns the number removed.
+#[tauri::command]
+pub fn http_cache_clear(
+    state: tauri::State<'_, HttpCacheState>,
+    url_prefix: Option<String>,
+) -> Result<usize, String> {
+    ensure_connection(&state)?;
+    let guard = state.conn.lock().map_err(|e| e.to_string())?;
+    let conn = guard.as_ref().ok_or("DB not initialized")?;
+
+    let removed = if let Some(prefix) = url_prefix {
+        conn.execute(
+            "DELETE FROM cache_entries WHERE substr(url, 1, length(?1)) = ?1",
+            params![prefix],
+        )
+    } else {
+        conn.execute("DELETE FROM cache_entries", [])
+            .and_then(|n| conn.execute("DELETE FROM cache_settings WHERE key LIKE 'stat_%'", []).map(|_| n))
+    }
+    .map_err(|e| format!("Failed to clear cache: {}", e))?;
+
+    Ok(removed)
+}
+
+#[tauri::command]
+pub fn http_cache_set_config(
+    state: tauri::State<'_, HttpCacheState>,
+    config: HttpCacheConfig,
+) -> Result<(), String> {
+    if config.ttl_secs < 0 || config.max_bytes < 0 {
+        return Err("TTL and size cap must not be negative".to_string());
+    }
+    ensure_connection(&state)?;
+    let guard = state.conn.lock().map_err(|e| e.to_string())?;
+    let conn = guard.as_ref().ok_or("DB not initialized")?;
+
+    let json = serde_json::to_string(&config).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO cache_settings (key, value) VALUES ('config', ?1)",
+        params![json],
+    )
+    .map_err(|e| format!("Failed to set config: {}", e))?;
+    if !config.enabled {
+        conn.execute("DELETE FROM cache_entries", [])
+            .map_err(|e| format!("Failed to clear cache: {}", e))?;
+        return Ok(());
+    }
+    evict(conn, config.max_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+    const TTL: i64 = 3600;
+
+    fn expiry(headers: &[(reqwest::header::HeaderName, &str)]) -> Option<i64> {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.insert(name.clone(), HeaderValue::from_str(value).unwrap());
+        }
+        expires_at(&map, TTL, NOW)
+    }
+
+    #[test]
+    fn freshness_from_cache_control() {
+        assert_eq!(expiry(&[]), Some(NOW + TTL));
+        assert_eq!(expiry(&[(CACHE_CONTROL, "public, max-age=60")]), Some(NOW + 60));
+        assert_eq!(expiry(&[(CACHE_CONTROL, "max-age=\"60\"")]), Some(NOW + 60));
+        assert_eq!(expiry(&[(CACHE_CONTROL, "no-cache, max-age=60")]), Some(NOW));
+        assert_eq!(expiry(&[(CACHE_CONTROL, "max-age=60, No-Store")]), None);
+        assert_eq!(expiry(&[(CACHE_CONTROL, "private")]), None);
+        assert_eq!(expiry(&[(CACHE_CONTROL, "max-age=-5")]), Some(NOW));
+    }
+
+    #[test]
+    fn freshness_from_expires() {
+        assert_eq!(expiry(&[(EXPIRES, "Tue, 14 Nov 2023 22:13:20 GMT")]), Some(NOW));
+        assert_eq!(expiry(&[(EXPIRES, "0")]), Some(NOW));
+        // max-age takes precedence over Expires
+        assert_eq!(expiry(&[(CACHE_CONTROL, "max-age=60"), (EXPIRES, "0")]), Some(NOW + 60));
+    }
+
+    #[test]
+    fn keys_depend_on_headers_not_their_order() {
+        let url = "https://api.example.com/works";
+        let key = cache_key(url, [("Accept", "application/json"), ("X-Mailto", "a@b.c")]);
+        assert_eq!(key, cache_key(url, [("x-mailto", "a@b.c"), ("accept", "application/json")]));
+        assert_ne!(key, cache_key(url, [("Accept", "text/html"), ("X-Mailto", "a@b.c")]));
+        assert_ne!(key, cache_key(url, []));
+    }
+
+    #[test]
+    fn detects_credentials() {
+        assert!(has_credentials([("Authorization", "Bearer x")]));
+        assert!(has_credentials([("Accept", "*/*"), ("Cookie", "a=b")]));
+        assert!(has_credentials([("X-Api-Key", "k")]));
+        assert!(!has_credentials([("Accept", "*/*"), ("User-Agent", "app")]));
+    }
+}
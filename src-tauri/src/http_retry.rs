@@ -0,0 +1,204 @@
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Requests in flight per host when the caller does not say otherwise
+pub const DEFAULT_HOST_CONCURRENCY: usize = 4;
+
+/// How `proxy_api_call` retries and paces a request. Every field is optional.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying
+    pub max_retries: u32,
+    /// Backoff before the first retry, doubled for each further one
+    pub base_delay_ms: u64,
+    /// Longest single wait. A `Retry-After` beyond this fails the request
+    /// instead of stalling it.
+    pub max_delay_ms: u64,
+    /// Per-attempt timeout
+    pub timeout_secs: u64,
+    /// Retry POST requests on 5xx and network errors. Off by default, since
+    /// the server may already have acted on them; 429 is always retried.
+    pub retry_non_idempotent: bool,
+    /// Requests in flight to this host at once, shared with every caller.
+    /// Unset keeps the host's current limit.
+    pub max_concurrent: Option<usize>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 30_000,
+            timeout_secs: 30,
+            retry_non_idempotent: false,
+            max_concurrent: None,
+        }
+    }
+}
+
+/// What to do after a failed attempt.
+pub enum Retry {
+    After(Duration),
+    GiveUp,
+}
+
+impl RetryPolicy {
+    /// Exponential backoff with jitter: a random wait between half and all of
+    /// `base * 2^attempt`, capped at `max_delay_ms`.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base_delay_ms
+            .saturating_mul(1u64 << attempt.min(20))
+            .min(self.max_delay_ms);
+        let half = ceiling / 2;
+        let jitter = (uuid::Uuid::new_v4().as_u128() % (half as u128 + 1)) as u64;
+        Duration::from_millis(half + jitter)
+    }
+
+    /// Decide on a response status. `attempt` counts from 0.
+    pub fn on_status(&self, attempt: u32, status: u16, headers: &HeaderMap, idempotent: bool) -> Retry {
+        let retryable = status == 429 || (matches!(status, 500 | 502 | 503 | 504) && (idempotent || self.retry_non_idempotent));
+        if !retryable || attempt >= self.max_retries {
+            return Retry::GiveUp;
+        }
+        match retry_after(headers) {
+            Some(wait) if wait > Duration::from_millis(self.max_delay_ms) => Retry::GiveUp,
+            Some(wait) => Retry::After(wait),
+            None => Retry::After(self.backoff(attempt)),
+        }
+    }
+
+    /// Decide on a request that got no response (connection refused, timeout, reset).
+    pub fn on_error(&self, attempt: u32, idempotent: bool) -> Retry {
+        if attempt >= self.max_retries || !(idempotent || self.retry_non_idempotent) {
+            return Retry::GiveUp;
+        }
+        Retry::After(self.backoff(attempt))
+    }
+}
+
+/// Parse `Retry-After` as delay-seconds or an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.timestamp() - chrono::Utc::now().timestamp();
+    Some(Duration::from_secs(wait.max(0) as u64))
+}
+
+pub fn is_idempotent(method: &str) -> bool {
+    matches!(method, "GET" | "HEAD" | "PUT" | "DELETE" | "OPTIONS")
+}
+
+/// Per-host request limits. A host starts at `DEFAULT_HOST_CONCURRENCY`; a request
+/// that asks for a different limit resizes it, and requests already waiting keep the old one.
+pub struct HostLimiterState {
+    pub hosts: Mutex<HashMap<String, (usize, Arc<Semaphore>)>>,
+}
+
+impl Default for HostLimiterState {
+    fn default() -> Self {
+        Self {
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl HostLimiterState {
+    pub async fn acquire(&self, host: &str, limit: Option<usize>) -> Result<OwnedSemaphorePermit, String> {
+        let semaphore = {
+            let mut hosts = self.hosts.lock().unwrap();
+            let entry = hosts.entry(host.to_string()).or_insert_with(|| {
+                let limit = limit.unwrap_or(DEFAULT_HOST_CONCURRENCY).max(1);
+                (limit, Arc::new(Semaphore::new(limit)))
+            });
+            if let Some(limit) = limit.map(|l| l.max(1)).filter(|&l| l != entry.0) {
+                *entry = (limit, Arc::new(Semaphore::new(limit)));
+            }
+            entry.1.clone()
+        };
+        semaphore.acquire_owned().await.map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(retry_after: Option<&str>) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        if let Some(value) = retry_after {
+            map.insert(RETRY_AFTER, value.parse().unwrap());
+        }
+        map
+    }
+
+    fn wait(retry: Retry) -> Option<Duration> {
+        match retry {
+            Retry::After(wait) => Some(wait),
+            Retry::GiveUp => None,
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_with_jitter_and_cap() {
+        let policy = RetryPolicy { base_delay_ms: 100, max_delay_ms: 1000, ..RetryPolicy::default() };
+        for (attempt, ceiling) in [(0, 100), (1, 200), (2, 400), (3, 800), (4, 1000), (40, 1000)] {
+            for _ in 0..50 {
+                let ms = policy.backoff(attempt).as_millis() as u64;
+                assert!((ceiling / 2..=ceiling).contains(&ms), "attempt {}: {}ms", attempt, ms);
+            }
+        }
+    }
+
+    #[test]
+    fn retries_only_transient_statuses() {
+        let policy = RetryPolicy::default();
+        let none = headers(None);
+        assert!(wait(policy.on_status(0, 503, &none, true)).is_some());
+        assert!(wait(policy.on_status(0, 429, &none, false)).is_some());
+        assert!(wait(policy.on_status(0, 503, &none, false)).is_none());
+        assert!(wait(policy.on_status(0, 404, &none, true)).is_none());
+        assert!(wait(policy.on_status(3, 503, &none, true)).is_none());
+
+        let post = RetryPolicy { retry_non_idempotent: true, ..RetryPolicy::default() };
+        assert!(wait(post.on_status(0, 502, &none, false)).is_some());
+    }
+
+    #[test]
+    fn honours_retry_after() {
+        let policy = RetryPolicy::default();
+        assert_eq!(wait(policy.on_status(0, 429, &headers(Some("7")), true)), Some(Duration::from_secs(7)));
+        // Longer than max_delay_ms: fail instead of stalling
+        assert_eq!(wait(policy.on_status(0, 429, &headers(Some("120")), true)), None);
+        assert_eq!(
+            wait(policy.on_status(0, 429, &headers(Some("Wed, 21 Oct 2015 07:28:00 GMT")), true)),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn network_errors() {
+        let policy = RetryPolicy::default();
+        assert!(wait(policy.on_error(0, true)).is_some());
+        assert!(wait(policy.on_error(0, false)).is_none());
+        assert!(wait(policy.on_error(3, true)).is_none());
+        assert!(wait(RetryPolicy { max_retries: 0, ..policy }.on_error(0, true)).is_none());
+    }
+
+    #[test]
+    fn idempotent_methods() {
+        assert!(is_idempotent("GET"));
+        assert!(is_idempotent("PUT"));
+        assert!(!is_idempotent("POST"));
+        assert!(!is_idempotent("PATCH"));
+    }
+}
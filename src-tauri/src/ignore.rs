@@ -0,0 +1,81 @@
+use git2::Repository;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Workspace ignore rules: every `.gitignore` in the tree, `.git/info/exclude`,
+/// the user's global excludes file and `.shoulders/ignore`. Matching is done by
+/// libgit2 (`status_should_ignore`), so results agree with `git_status`.
+///
+/// Folders that are not git repositories are matched through a scratch bare
+/// repository pointed at the workspace, so their `.gitignore` files still apply.
+pub(crate) struct IgnoreMatcher {
+    root: PathBuf,
+    repo: Option<Repository>,
+}
+
+/// Nearest ancestor (inclusive) that looks like a workspace root.
+fn find_root(dir: &Path) -> PathBuf {
+    dir.ancestors()
+        .find(|a| a.join(".git").exists() || a.join(".shoulders").is_dir())
+        .unwrap_or(dir)
+        .to_path_buf()
+}
+
+/// Whether `path` is a file `IgnoreMatcher` reads rules from, so a matcher
+/// built before it changed is stale. (The global excludes file lives outside
+/// the workspace and isn't watched.)
+pub(crate) fn is_ignore_source(path: &Path) -> bool {
+    path.file_name().is_some_and(|n| n == ".gitignore")
+        || path.ends_with(Path::new(".shoulders").join("ignore"))
+        || path.ends_with(Path::new(".git").join("info").join("exclude"))
+}
+
+/// The scratch repo lives in the user's own `~/.shoulders`, not a shared temp
+/// dir where another user could plant one with a hostile config.
+fn scratch_repo(root: &Path) -> Option<Repository> {
+    let git_dir = dirs::home_dir()?.join(".shoulders").join("ignore-matcher.git");
+    // Two matchers may race to create it: whoever loses the init opens the winner's
+    let repo = Repository::open_bare(&git_dir)
+        .or_else(|_| Repository::init_bare(&git_dir))
+        .or_else(|_| Repository::open_bare(&git_dir))
+        .ok()?;
+    // In-memory only; nothing is written to the workspace
+    repo.set_workdir(root, false).ok()?;
+    Some(repo)
+}
+
+impl IgnoreMatcher {
+    /// Matcher for walking `dir`. Rules are anchored at the enclosing workspace
+    /// root. If `dir` is itself ignored (e.g. `.shoulders/chats`), the caller
+    /// asked for it explicitly and nothing inside it is filtered.
+    pub(crate) fn new(dir: &Path) -> Self {
+        let root = find_root(dir);
+        let repo = Repository::open(&root).ok().or_else(|| scratch_repo(&root));
+
+        if let Some(ref repo) = repo {
+            if let Ok(rules) = fs::read_to_string(root.join(".shoulders").join("ignore")) {
+                if let Err(e) = repo.add_ignore_rule(&rules) {
+                    eprintln!("[ignore] invalid .shoulders/ignore: {}", e.message());
+                }
+            }
+        }
+
+        let mut matcher = Self { root, repo };
+        if matcher.is_ignored(dir) {
+            matcher.repo = None;
+        }
+        matcher
+    }
+
+    pub(crate) fn is_ignored(&self, path: &Path) -> bool {
+        let repo = match &self.repo {
+            Some(r) => r,
+            None => return false,
+        };
+        let rel = match path.strip_prefix(&self.root) {
+            Ok(r) if !r.as_os_str().is_empty() => r,
+            _ => return false,
+        };
+        repo.status_should_ignore(rel).unwrap_or(false)
+    }
+}
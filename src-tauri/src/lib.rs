@@ -1,9 +1,24 @@
 mod chat;
+mod command_policy;
+mod download;
 mod fs_commands;
+mod fs_events;
 mod git;
+mod history;
+mod host_allowlist;
+mod html_extract;
+mod http_cache;
+mod http_retry;
+mod ignore;
 mod kernel;
 mod latex;
+mod pdf_text;
 mod pty;
+mod replace;
+mod sandbox;
+mod search_index;
+mod shell;
+mod trash;
 mod typst_export;
 mod usage_db;
 
@@ -166,19 +181,42 @@ pub fn run() {
         .manage(pty::PtyState::default())
         .manage(fs_commands::WatcherState::default())
         .manage(chat::ChatState::default())
+        .manage(shell::ShellState::default())
         .manage(kernel::KernelState::default())
         .manage(latex::LatexState::default())
         .manage(usage_db::UsageDbState::default())
+        .manage(search_index::SearchIndexState::default())
+        .manage(sandbox::SandboxState::default())
+        .manage(http_cache::HttpCacheState::default())
+        .manage(http_retry::HostLimiterState::default())
+        .manage(download::DownloadState::default())
         .invoke_handler(tauri::generate_handler![
             fs_commands::read_dir_recursive,
+            fs_commands::read_dir,
+            fs_commands::read_dir_page,
             fs_commands::read_file,
             fs_commands::read_file_base64,
             fs_commands::write_file,
+            fs_commands::file_version,
             fs_commands::write_file_base64,
             fs_commands::create_file,
             fs_commands::create_dir,
             fs_commands::rename_path,
             fs_commands::delete_path,
+            trash::trash_list,
+            trash::trash_restore,
+            trash::trash_purge,
+            history::history_list,
+            history::history_read,
+            history::history_diff,
+            history::history_restore,
+            history::history_prune,
+            history::history_get_config,
+            history::history_set_config,
+            sandbox::sandbox_add_root,
+            sandbox::sandbox_remove_root,
+            sandbox::sandbox_roots,
+            sandbox::sandbox_allow_path,
             fs_commands::copy_file,
             fs_commands::copy_dir,
             fs_commands::is_directory,
@@ -186,18 +224,33 @@ pub fn run() {
             fs_commands::watch_directory,
             fs_commands::unwatch_directory,
             fs_commands::proxy_api_call,
+            fs_commands::proxy_api_request,
+            download::download_to_file,
+            download::download_cancel,
             git::git_clone,
             git::git_init,
             git::git_add_all,
             git::git_commit,
             git::git_status,
             git::git_branch,
+            git::git_branch_list,
+            git::git_branch_create,
+            git::git_checkout_branch,
+            git::git_branch_rename,
+            git::git_branch_delete,
+            git::git_branch_set_upstream,
+            git::git_branch_delete_remote,
             git::git_log,
             git::git_show_file,
             git::git_show_file_base64,
             git::git_diff,
             git::git_diff_stat,
             git::git_diff_summary,
+            git::git_stage_paths,
+            git::git_unstage_paths,
+            git::git_file_hunks,
+            git::git_stage_hunks,
+            git::git_unstage_hunks,
             git::git_remote_add,
             git::git_remote_get_url,
             git::git_remote_remove,
@@ -207,10 +260,22 @@ pub fn run() {
             git::git_ahead_behind,
             git::git_pull_ff,
             git::git_merge_remote,
+            git::git_merge_state,
+            git::git_merge_resolve,
+            git::git_merge_continue,
+            git::git_merge_abort,
             git::git_set_user,
             git::git_clone_authenticated,
             fs_commands::search_file_contents,
+            search_index::search_index_build,
+            search_index::search_index_query,
+            replace::replace_in_files,
             fs_commands::run_shell_command,
+            shell::shell_spawn,
+            shell::shell_kill,
+            command_policy::command_policy_check,
+            command_policy::command_policy_get,
+            command_policy::command_policy_set,
             fs_commands::fetch_url_content,
             fs_commands::get_global_config_dir,
             pty::pty_spawn,
@@ -241,6 +306,12 @@ pub fn run() {
             usage_db::usage_query_daily_trend,
             usage_db::usage_get_setting,
             usage_db::usage_set_setting,
+            http_cache::http_cache_stats,
+            http_cache::http_cache_clear,
+            http_cache::http_cache_set_config,
+            host_allowlist::host_allowlist_check,
+            host_allowlist::host_allowlist_get,
+            host_allowlist::host_allowlist_set,
             keychain_get,
             keychain_set,
             keychain_delete,
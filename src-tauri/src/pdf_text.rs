@@ -0,0 +1,1527 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Plain text of a PDF, page by page.
+pub struct PdfText {
+    /// `/Title` from the document info dictionary
+    pub title: Option<String>,
+    pub pages: usize,
+    /// Pages separated by `--- Page N ---` lines
+    pub text: String,
+}
+
+/// Cap on any one decompressed stream, against zip bombs
+const MAX_STREAM_BYTES: usize = 64 * 1024 * 1024;
+/// How deep references, page trees and form XObjects are followed
+const MAX_DEPTH: usize = 16;
+/// Content operators run per document, across all pages and form XObjects
+const MAX_OPERATORS: usize = 5_000_000;
+/// Decoded content stream bytes run per document, across all pages and forms
+const MAX_CONTENT_BYTES: usize = 256 * 1024 * 1024;
+
+/// Extract the text of a PDF. Handles classic and compressed (object stream)
+/// files, Flate/ASCIIHex/ASCII85 streams, ToUnicode maps, the standard simple
+/// font encodings with `/Differences`, and text inside form XObjects. Encrypted
+/// files and image-only (scanned) pages yield an error or no text.
+pub(crate) fn extract_text(data: &[u8]) -> Result<PdfText, String> {
+    if !data.starts_with(b"%PDF") {
+        return Err("Not a PDF file".to_string());
+    }
+    let doc = Document::parse(data);
+    if doc.trailer.contains_key(&b"Encrypt"[..]) {
+        return Err("This PDF is encrypted; its text cannot be extracted".to_string());
+    }
+
+    let pages = doc.pages();
+    if pages.is_empty() {
+        return Err("No pages found in PDF".to_string());
+    }
+
+    let mut text = String::new();
+    let mut interpreter = Interpreter::new(&doc);
+    for (i, page) in pages.iter().enumerate() {
+        let mut out = TextOutput::default();
+        let resources = page.resources.clone().unwrap_or_default();
+        for content in doc.page_contents(&page.dict) {
+            interpreter.run(&content, &resources, IDENTITY, &mut out, 0);
+        }
+        let page_text = out.finish();
+        if !page_text.is_empty() {
+            if !text.is_empty() {
+                text.push_str("\n\n");
+            }
+            text.push_str(&format!("--- Page {} ---\n\n{}", i + 1, page_text));
+        }
+    }
+
+    if text.is_empty() {
+        return Err("The PDF contains no extractable text (it may be scanned)".to_string());
+    }
+
+    let title = doc
+        .trailer
+        .get(&b"Info"[..])
+        .and_then(|info| doc.resolve(info).as_dict())
+        .and_then(|info| info.get(&b"Title"[..]))
+        .and_then(|t| doc.resolve(t).as_bytes())
+        .map(decode_text_string)
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty());
+
+    Ok(PdfText { title, pages: pages.len(), text })
+}
+
+// ─── Objects ─────────────────────────────────────────────────────────
+
+type Dict = HashMap<Vec<u8>, Obj>;
+
+#[derive(Clone, Debug)]
+enum Obj {
+    Null,
+    /// `true`/`false`; no caller needs the value
+    Bool,
+    Int(i64),
+    Real(f64),
+    Name(Vec<u8>),
+    Str(Vec<u8>),
+    Array(Vec<Obj>),
+    Dict(Dict),
+    /// Indirect reference; the generation number is ignored
+    Ref(u32),
+    /// Dictionary and still-encoded data
+    Stream(Dict, Vec<u8>),
+    /// Bare keyword: a content stream operator, `obj`, `endobj`, ...
+    Op(Vec<u8>),
+}
+
+impl Obj {
+    fn as_dict(&self) -> Option<&Dict> {
+        match self {
+            Obj::Dict(d) | Obj::Stream(d, _) => Some(d),
+            _ => None,
+        }
+    }
+
+    fn as_name(&self) -> Option<&[u8]> {
+        match self {
+            Obj::Name(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Obj::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Obj::Int(i) => Some(*i as f64),
+            Obj::Real(r) => Some(*r),
+            _ => None,
+        }
+    }
+
+    fn as_array(&self) -> Option<&[Obj]> {
+        match self {
+            Obj::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, 0 | 9 | 10 | 12 | 13 | 32)
+}
+
+fn is_delimiter(b: u8) -> bool {
+    matches!(b, b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%')
+}
+
+fn is_regular(b: u8) -> bool {
+    !is_whitespace(b) && !is_delimiter(b)
+}
+
+struct Lexer<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b) = self.peek() {
+            if is_whitespace(b) {
+                self.pos += 1;
+            } else if b == b'%' {
+                while self.peek().is_some_and(|b| b != b'\n' && b != b'\r') {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn regular_run(&mut self) -> &'a [u8] {
+        let start = self.pos;
+        while self.peek().is_some_and(is_regular) {
+            self.pos += 1;
+        }
+        &self.data[start..self.pos]
+    }
+
+    fn unsigned_int(&mut self) -> Option<u32> {
+        let start = self.pos;
+        while self.peek().is_some_and(|b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.data[start..self.pos]).ok()?.parse().ok()
+    }
+
+    /// The next object, or `None` at the end of the data. Never fails on
+    /// malformed input: stray closers come back as `Op` tokens.
+    fn object(&mut self, depth: usize) -> Option<Obj> {
+        self.skip_whitespace();
+        let b = self.peek()?;
+        if depth > 64 {
+            self.pos += 1;
+            return Some(Obj::Null);
+        }
+        match b {
+            b'/' => {
+                self.pos += 1;
+                Some(Obj::Name(decode_name(self.regular_run())))
+            }
+            b'(' => {
+                self.pos += 1;
+                Some(Obj::Str(self.literal_string()))
+            }
+            b'<' if self.data.get(self.pos + 1) == Some(&b'<') => {
+                self.pos += 2;
+                let mut dict = Dict::new();
+                loop {
+                    self.skip_whitespace();
+                    match self.peek() {
+                        None => break,
+                        Some(b'>') => {
+                            self.pos += if self.data.get(self.pos + 1) == Some(&b'>') { 2 } else { 1 };
+                            break;
+                        }
+                        _ => {}
+                    }
+                    match self.object(depth + 1)? {
+                        Obj::Name(key) => {
+                            let value = self.object(depth + 1).unwrap_or(Obj::Null);
+                            dict.insert(key, value);
+                        }
+                        // Junk in place of a key; skip it
+                        _ => continue,
+                    }
+                }
+                Some(Obj::Dict(dict))
+            }
+            b'<' => {
+                self.pos += 1;
+                Some(Obj::Str(self.hex_string()))
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                loop {
+                    self.skip_whitespace();
+                    match self.peek() {
+                        None => break,
+                        Some(b']') => {
+                            self.pos += 1;
+                            break;
+                        }
+                        _ => items.push(self.object(depth + 1)?),
+                    }
+                }
+                Some(Obj::Array(items))
+            }
+            b'+' | b'-' | b'.' | b'0'..=b'9' => Some(self.number()),
+            _ if is_delimiter(b) => {
+                self.pos += 1;
+                Some(Obj::Op(vec![b]))
+            }
+            _ => {
+                let word = self.regular_run();
+                Some(match word {
+                    b"true" | b"false" => Obj::Bool,
+                    b"null" => Obj::Null,
+                    _ => Obj::Op(word.to_vec()),
+                })
+            }
+        }
+    }
+
+    /// A number, or an `N G R` reference starting with one.
+    fn number(&mut self) -> Obj {
+        let start = self.pos;
+        while self.peek().is_some_and(|b| matches!(b, b'+' | b'-' | b'.' | b'0'..=b'9')) {
+            self.pos += 1;
+        }
+        let text = std::str::from_utf8(&self.data[start..self.pos]).unwrap_or("0");
+        if text.contains('.') {
+            return Obj::Real(text.parse().unwrap_or(0.0));
+        }
+        let Ok(value) = text.parse::<i64>() else {
+            return Obj::Real(text.parse().unwrap_or(0.0));
+        };
+
+        if value >= 0 && !text.starts_with('+') {
+            let after_number = self.pos;
+            self.skip_whitespace();
+            if self.unsigned_int().is_some() {
+                self.skip_whitespace();
+                if self.peek() == Some(b'R') && self.data.get(self.pos + 1).is_none_or(|b| !is_regular(*b)) {
+                    self.pos += 1;
+                    return Obj::Ref(value as u32);
+                }
+            }
+            self.pos = after_number;
+        }
+        Obj::Int(value)
+    }
+
+    fn literal_string(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut depth = 1;
+        while let Some(b) = self.peek() {
+            self.pos += 1;
+            match b {
+                b'(' => {
+                    depth += 1;
+                    out.push(b);
+                }
+                b')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                    out.push(b);
+                }
+                b'\\' => {
+                    let Some(next) = self.peek() else { break };
+                    self.pos += 1;
+                    match next {
+                        b'n' => out.push(b'\n'),
+                        b'r' => out.push(b'\r'),
+                        b't' => out.push(b'\t'),
+                        b'b' => out.push(8),
+                        b'f' => out.push(12),
+                        b'0'..=b'7' => {
+                            let mut value = (next - b'0') as u32;
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(d @ b'0'..=b'7') => {
+                                        value = value * 8 + (d - b'0') as u32;
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            out.push(value as u8);
+                        }
+                        // Line continuation
+                        b'\r' => {
+                            if self.peek() == Some(b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => {}
+                        other => out.push(other),
+                    }
+                }
+                _ => out.push(b),
+            }
+        }
+        out
+    }
+
+    fn hex_string(&mut self) -> Vec<u8> {
+        let mut digits = Vec::new();
+        while let Some(b) = self.peek() {
+            self.pos += 1;
+            if b == b'>' {
+                break;
+            }
+            if let Some(d) = (b as char).to_digit(16) {
+                digits.push(d as u8);
+            }
+        }
+        if digits.len() % 2 == 1 {
+            digits.push(0);
+        }
+        digits.chunks(2).map(|pair| pair[0] << 4 | pair[1]).collect()
+    }
+
+    /// Skip inline image data after an `ID` operator, up to and including `EI`.
+    fn skip_inline_image(&mut self) {
+        let data = self.data;
+        let mut i = self.pos + 1;
+        while i + 2 <= data.len() {
+            if &data[i..i + 2] == b"EI"
+                && is_whitespace(data[i - 1])
+                && data.get(i + 2).is_none_or(|b| !is_regular(*b))
+            {
+                self.pos = i + 2;
+                return;
+            }
+            i += 1;
+        }
+        self.pos = data.len();
+    }
+}
+
+fn decode_name(raw: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(raw.len());
+    let mut i = 0;
+    while i < raw.len() {
+        if raw[i] == b'#' {
+            if let Some(byte) = std::str::from_utf8(raw.get(i + 1..i + 3).unwrap_or_default())
+                .ok()
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+            {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(raw[i]);
+        i += 1;
+    }
+    out
+}
+
+/// Text strings are UTF-16BE with a BOM, otherwise (roughly) Latin-1.
+fn decode_text_string(bytes: &[u8]) -> String {
+    if let Some(utf16) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = utf16.chunks(2).map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)])).collect();
+        return String::from_utf16_lossy(&units);
+    }
+    if let Some(utf8) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return String::from_utf8_lossy(utf8).to_string();
+    }
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+// ─── Document ────────────────────────────────────────────────────────
+
+struct Document {
+    objects: HashMap<u32, Obj>,
+    trailer: Dict,
+}
+
+struct Page {
+    dict: Dict,
+    /// Own or inherited `/Resources`
+    resources: Option<Dict>,
+}
+
+impl Document {
+    /// Collect objects by scanning for `N G obj` rather than trusting the xref
+    /// table, which is often broken. Later definitions win, as with incremental
+    /// updates. Objects inside object streams are added afterwards.
+    fn parse(data: &[u8]) -> Self {
+        let mut objects = HashMap::new();
+        let mut trailer = Dict::new();
+
+        let mut i = 0;
+        while i + 3 <= data.len() {
+            if data[i..].starts_with(b"trailer") {
+                let mut lexer = Lexer::new(data, i + 7);
+                if let Some(Obj::Dict(dict)) = lexer.object(0) {
+                    merge_trailer(&mut trailer, dict);
+                }
+                i = lexer.pos.max(i + 1);
+                continue;
+            }
+            if &data[i..i + 3] != b"obj" || data.get(i + 3).is_some_and(|b| is_regular(*b)) {
+                i += 1;
+                continue;
+            }
+            let Some(number) = object_number_before(data, i) else {
+                i += 1;
+                continue;
+            };
+
+            let mut lexer = Lexer::new(data, i + 3);
+            let Some(mut obj) = lexer.object(0) else { break };
+            if let Obj::Dict(dict) = &obj {
+                let mut after = Lexer::new(data, lexer.pos);
+                after.skip_whitespace();
+                if data[after.pos..].starts_with(b"stream") {
+                    let (raw, end) = stream_data(data, after.pos + 6, dict);
+                    if dict.get(&b"Type"[..]).and_then(Obj::as_name) == Some(b"XRef") {
+                        merge_trailer(&mut trailer, dict.clone());
+                    }
+                    obj = Obj::Stream(dict.clone(), raw);
+                    lexer.pos = end;
+                }
+            }
+            objects.insert(number, obj);
+            i = lexer.pos.max(i + 3);
+        }
+
+        let mut doc = Self { objects, trailer };
+        doc.expand_object_streams();
+        doc
+    }
+
+    fn expand_object_streams(&mut self) {
+        let streams: Vec<Obj> = self
+            .objects
+            .values()
+            .filter(|o| matches!(o, Obj::Stream(d, _) if d.get(&b"Type"[..]).and_then(Obj::as_name) == Some(b"ObjStm")))
+            .cloned()
+            .collect();
+
+        for stream in streams {
+            let Some(data) = self.stream_bytes(&stream) else { continue };
+            let dict = stream.as_dict().unwrap();
+            let count = dict.get(&b"N"[..]).and_then(|n| self.resolve(n).as_f64()).unwrap_or(0.0) as usize;
+            let first = dict.get(&b"First"[..]).and_then(|n| self.resolve(n).as_f64()).unwrap_or(0.0) as usize;
+            if first > data.len() {
+                continue;
+            }
+
+            let mut header = Lexer::new(&data[..first], 0);
+            for _ in 0..count {
+                let (Some(Obj::Int(number)), Some(Obj::Int(offset))) = (header.object(0), header.object(0)) else {
+                    break;
+                };
+                let Ok(number) = u32::try_from(number) else { continue };
+                if self.objects.contains_key(&number) {
+                    continue;
+                }
+                // Offsets come from the file: reject negative, overflowing or out-of-range ones
+                let Some(start) = usize::try_from(offset).ok().and_then(|o| first.checked_add(o)) else { continue };
+                if start >= data.len() {
+                    continue;
+                }
+                let mut lexer = Lexer::new(&data, start);
+                if let Some(obj) = lexer.object(0) {
+                    self.objects.insert(number, obj);
+                }
+            }
+        }
+    }
+
+    fn resolve<'a>(&'a self, obj: &'a Obj) -> &'a Obj {
+        let mut current = obj;
+        for _ in 0..MAX_DEPTH {
+            match current {
+                Obj::Ref(n) => current = self.objects.get(n).unwrap_or(&Obj::Null),
+                _ => return current,
+            }
+        }
+        &Obj::Null
+    }
+
+    fn get<'a>(&'a self, dict: &'a Dict, key: &[u8]) -> Option<&'a Obj> {
+        dict.get(key).map(|o| self.resolve(o))
+    }
+
+    /// Decoded data of a stream object (following a reference if needed).
+    fn stream_bytes(&self, obj: &Obj) -> Option<Vec<u8>> {
+        let Obj::Stream(dict, raw) = self.resolve(obj) else { return None };
+        let filters: Vec<Vec<u8>> = match self.get(dict, b"Filter") {
+            None => Vec::new(),
+            Some(Obj::Name(n)) => vec![n.clone()],
+            Some(Obj::Array(items)) => items.iter().filter_map(|i| self.resolve(i).as_name().map(<[u8]>::to_vec)).collect(),
+            Some(_) => return None,
+        };
+
+        let mut data = raw.clone();
+        for filter in filters {
+            data = match filter.as_slice() {
+                b"FlateDecode" | b"Fl" => inflate(&data)?,
+                b"ASCIIHexDecode" | b"AHx" => Lexer::new(&data, 0).hex_string(),
+                b"ASCII85Decode" | b"A85" => ascii85(&data),
+                // Image and LZW filters never wrap text we can read
+                _ => return None,
+            };
+        }
+        Some(data)
+    }
+
+    fn pages(&self) -> Vec<Page> {
+        let root = self
+            .trailer
+            .get(&b"Root"[..])
+            .map(|r| self.resolve(r))
+            .and_then(Obj::as_dict)
+            .or_else(|| {
+                // No usable trailer: any catalog will do
+                self.objects
+                    .values()
+                    .filter_map(Obj::as_dict)
+                    .find(|d| d.get(&b"Type"[..]).and_then(Obj::as_name) == Some(b"Catalog"))
+            });
+
+        let mut pages = Vec::new();
+        if let Some(tree) = root.and_then(|r| self.get(r, b"Pages")).and_then(Obj::as_dict) {
+            self.collect_pages(tree, None, &mut pages, 0);
+        }
+        pages
+    }
+
+    fn collect_pages(&self, node: &Dict, inherited: Option<&Dict>, pages: &mut Vec<Page>, depth: usize) {
+        if depth > MAX_DEPTH {
+            return;
+        }
+        let resources = self.get(node, b"Resources").and_then(Obj::as_dict).or(inherited);
+        match self.get(node, b"Kids").and_then(Obj::as_array) {
+            Some(kids) => {
+                for kid in kids {
+                    if let Some(kid) = self.resolve(kid).as_dict() {
+                        self.collect_pages(kid, resources, pages, depth + 1);
+                    }
+                }
+            }
+            None => pages.push(Page { dict: node.clone(), resources: resources.cloned() }),
+        }
+    }
+
+    fn page_contents(&self, page: &Dict) -> Vec<Vec<u8>> {
+        match page.get(&b"Contents"[..]).map(|c| (c, self.resolve(c))) {
+            Some((_, Obj::Array(parts))) => parts.iter().filter_map(|p| self.stream_bytes(p)).collect(),
+            Some((c, _)) => self.stream_bytes(c).into_iter().collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+fn merge_trailer(trailer: &mut Dict, dict: Dict) {
+    for key in [&b"Root"[..], b"Info", b"Encrypt"] {
+        if let Some(value) = dict.get(key) {
+            trailer.insert(key.to_vec(), value.clone());
+        }
+    }
+}
+
+/// The object number in `N G obj`, given the offset of `obj`.
+fn object_number_before(data: &[u8], obj_at: usize) -> Option<u32> {
+    let mut i = obj_at;
+    let skip_back = |i: &mut usize, pred: fn(u8) -> bool| -> usize {
+        let end = *i;
+        while *i > 0 && pred(data[*i - 1]) {
+            *i -= 1;
+        }
+        end - *i
+    };
+    if skip_back(&mut i, is_whitespace) == 0 || skip_back(&mut i, |b| b.is_ascii_digit()) == 0 {
+        return None;
+    }
+    if skip_back(&mut i, is_whitespace) == 0 {
+        return None;
+    }
+    let end = i;
+    if skip_back(&mut i, |b| b.is_ascii_digit()) == 0 || (i > 0 && is_regular(data[i - 1])) {
+        return None;
+    }
+    std::str::from_utf8(&data[i..end]).ok()?.parse().ok()
+}
+
+/// Raw data of a stream whose `stream` keyword ends at `start`, and the offset
+/// after `endstream`. A direct `/Length` is trusted when `endstream` follows it;
+/// otherwise the data runs to the next `endstream`.
+fn stream_data(data: &[u8], mut start: usize, dict: &Dict) -> (Vec<u8>, usize) {
+    if data.get(start) == Some(&b'\r') {
+        start += 1;
+    }
+    if data.get(start) == Some(&b'\n') {
+        start += 1;
+    }
+    let start = start.min(data.len());
+
+    if let Some(Obj::Int(length)) = dict.get(&b"Length"[..]) {
+        let end = start.saturating_add(*length as usize);
+        if end <= data.len() {
+            let mut after = Lexer::new(data, end);
+            after.skip_whitespace();
+            if data[after.pos..].starts_with(b"endstream") {
+                return (data[start..end].to_vec(), after.pos + 9);
+            }
+        }
+    }
+
+    match data[start..].windows(9).position(|w| w == b"endstream") {
+        Some(offset) => {
+            let mut end = start + offset;
+            if end > start && data[end - 1] == b'\n' {
+                end -= 1;
+            }
+            if end > start && data[end - 1] == b'\r' {
+                end -= 1;
+            }
+            (data[start..end].to_vec(), start + offset + 9)
+        }
+        None => (data[start..].to_vec(), data.len()),
+    }
+}
+
+/// Inflate zlib (or raw deflate) data, keeping whatever decodes before an
+/// error: truncated and slightly corrupt streams are common.
+fn inflate(data: &[u8]) -> Option<Vec<u8>> {
+    use flate2::{Decompress, FlushDecompress, Status};
+
+    let run = |zlib: bool| {
+        let mut decoder = Decompress::new(zlib);
+        let mut out = Vec::with_capacity(data.len() * 4);
+        loop {
+            if out.capacity() - out.len() < 64 * 1024 {
+                out.reserve(256 * 1024);
+            }
+            let (before_in, before_out) = (decoder.total_in(), decoder.total_out());
+            let input = &data[(decoder.total_in() as usize).min(data.len())..];
+            match decoder.decompress_vec(input, &mut out, FlushDecompress::None) {
+                Ok(Status::StreamEnd) | Err(_) => break,
+                Ok(_) if decoder.total_in() == before_in && decoder.total_out() == before_out => break,
+                Ok(_) => {}
+            }
+            if out.len() > MAX_STREAM_BYTES {
+                break;
+            }
+        }
+        out
+    };
+
+    let out = run(true);
+    let out = if out.is_empty() { run(false) } else { out };
+    (!out.is_empty()).then_some(out)
+}
+
+fn ascii85(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut group = Vec::with_capacity(5);
+    let flush = |group: &[u8], out: &mut Vec<u8>| {
+        let mut value: u32 = 0;
+        for i in 0..5 {
+            let digit = group.get(i).copied().unwrap_or(84) as u32;
+            value = value.wrapping_mul(85).wrapping_add(digit);
+        }
+        let bytes = value.to_be_bytes();
+        out.extend_from_slice(&bytes[..group.len().saturating_sub(1)]);
+    };
+
+    for &b in data.strip_prefix(b"<~").unwrap_or(data) {
+        match b {
+            b'~' => break,
+            b'z' if group.is_empty() => out.extend_from_slice(&[0; 4]),
+            b'!'..=b'u' => {
+                group.push(b - b'!');
+                if group.len() == 5 {
+                    flush(&group, &mut out);
+                    group.clear();
+                }
+            }
+            _ => {}
+        }
+    }
+    if !group.is_empty() {
+        flush(&group, &mut out);
+    }
+    out
+}
+
+// ─── Fonts ───────────────────────────────────────────────────────────
+
+struct Font {
+    /// Bytes per character code: 2 for most composite (Type0) fonts
+    code_len: usize,
+    to_unicode: HashMap<u32, String>,
+    /// Simple fonts: base encoding with `/Differences` applied
+    encoding: Option<Vec<Option<String>>>,
+    /// Composite fonts with a UCS-2 CMap: codes are UTF-16 units
+    ucs2: bool,
+    widths: HashMap<u32, f64>,
+    default_width: f64,
+}
+
+impl Font {
+    fn load(doc: &Document, dict: &Dict) -> Font {
+        let composite = doc.get(dict, b"Subtype").and_then(Obj::as_name) == Some(b"Type0");
+        let mut font = Font {
+            code_len: if composite { 2 } else { 1 },
+            to_unicode: HashMap::new(),
+            encoding: None,
+            ucs2: false,
+            widths: HashMap::new(),
+            default_width: if composite { 1000.0 } else { 500.0 },
+        };
+
+        if let Some(cmap) = dict.get(&b"ToUnicode"[..]).and_then(|c| doc.stream_bytes(c)) {
+            if let Some(len) = parse_cmap(&cmap, &mut font.to_unicode) {
+                font.code_len = len;
+            }
+        }
+
+        if composite {
+            if let Some(name) = doc.get(dict, b"Encoding").and_then(Obj::as_name) {
+                font.ucs2 = name.windows(4).any(|w| w == b"UCS2") || name.windows(5).any(|w| w == b"UTF16");
+            }
+            let descendant = doc
+                .get(dict, b"DescendantFonts")
+                .and_then(Obj::as_array)
+                .and_then(|a| a.first())
+                .and_then(|d| doc.resolve(d).as_dict());
+            if let Some(descendant) = descendant {
+                if let Some(dw) = doc.get(descendant, b"DW").and_then(Obj::as_f64) {
+                    font.default_width = dw;
+                }
+                if let Some(w) = doc.get(descendant, b"W").and_then(Obj::as_array) {
+                    font.load_cid_widths(doc, w);
+                }
+            }
+        } else {
+            font.encoding = Some(simple_encoding(doc, dict));
+            let first = doc.get(dict, b"FirstChar").and_then(Obj::as_f64).unwrap_or(0.0) as u32;
+            if let Some(widths) = doc.get(dict, b"Widths").and_then(Obj::as_array) {
+                for (i, w) in widths.iter().enumerate() {
+                    let code = u32::try_from(i).ok().and_then(|i| first.checked_add(i));
+                    if let (Some(code), Some(w)) = (code, doc.resolve(w).as_f64()) {
+                        font.widths.insert(code, w);
+                    }
+                }
+            }
+        }
+        font
+    }
+
+    /// `/W` entries: `c [w1 w2 ...]` or `c_first c_last w`.
+    fn load_cid_widths(&mut self, doc: &Document, w: &[Obj]) {
+        let mut i = 0;
+        while i < w.len() {
+            let Some(first) = doc.resolve(&w[i]).as_f64() else { break };
+            match w.get(i + 1).map(|o| doc.resolve(o)) {
+                Some(Obj::Array(list)) => {
+                    for (j, width) in list.iter().enumerate() {
+                        let code = u32::try_from(j).ok().and_then(|j| (first as u32).checked_add(j));
+                        if let (Some(code), Some(width)) = (code, doc.resolve(width).as_f64()) {
+                            self.widths.insert(code, width);
+                        }
+                    }
+                    i += 2;
+                }
+                Some(last) => {
+                    let (Some(last), Some(width)) = (last.as_f64(), w.get(i + 2).and_then(|o| doc.resolve(o).as_f64())) else {
+                        break;
+                    };
+                    // Guard against absurd ranges in broken files
+                    for code in (first as u32..=last as u32).take(65536) {
+                        self.widths.insert(code, width);
+                    }
+                    i += 3;
+                }
+                None => break,
+            }
+        }
+    }
+
+    fn codes(&self, bytes: &[u8]) -> Vec<u32> {
+        bytes
+            .chunks(self.code_len)
+            .map(|c| c.iter().fold(0u32, |acc, &b| acc << 8 | b as u32))
+            .collect()
+    }
+
+    fn text(&self, code: u32) -> Option<String> {
+        if let Some(text) = self.to_unicode.get(&code) {
+            return Some(text.clone());
+        }
+        if self.ucs2 {
+            return char::from_u32(code).map(String::from);
+        }
+        match &self.encoding {
+            Some(table) => table.get(code as usize).cloned().flatten(),
+            // Composite font without a ToUnicode map: codes are glyph ids
+            None => None,
+        }
+    }
+
+    fn width(&self, code: u32) -> f64 {
+        self.widths.get(&code).copied().unwrap_or(self.default_width)
+    }
+}
+
+/// Code-to-text map of a ToUnicode CMap. Returns the code length in bytes
+/// from the first codespace range.
+fn parse_cmap(data: &[u8], map: &mut HashMap<u32, String>) -> Option<usize> {
+    let code = |bytes: &[u8]| bytes.iter().fold(0u32, |acc, &b| acc << 8 | b as u32);
+    let utf16 = |bytes: &[u8]| {
+        let units: Vec<u16> = bytes.chunks(2).map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)])).collect();
+        String::from_utf16_lossy(&units)
+    };
+
+    let mut lexer = Lexer::new(data, 0);
+    let mut code_len = None;
+    let mut tokens = Vec::new();
+    while let Some(token) = lexer.object(0) {
+        let Obj::Op(op) = &token else {
+            tokens.push(token);
+            continue;
+        };
+        match op.as_slice() {
+            b"endcodespacerange" => {
+                if let Some(Obj::Str(lo)) = tokens.first() {
+                    code_len.get_or_insert(lo.len().max(1));
+                }
+            }
+            b"endbfchar" => {
+                for pair in tokens.chunks(2) {
+                    if let [Obj::Str(src), Obj::Str(dst)] = pair {
+                        map.insert(code(src), utf16(dst));
+                    }
+                }
+            }
+            b"endbfrange" => {
+                for triple in tokens.chunks(3) {
+                    let [Obj::Str(lo), Obj::Str(hi), dst] = triple else { continue };
+                    let (lo, hi) = (code(lo), code(hi));
+                    if hi < lo || hi - lo > 65535 {
+                        continue;
+                    }
+                    match dst {
+                        Obj::Str(start) if !start.is_empty() => {
+                            let mut units: Vec<u16> = start
+                                .chunks(2)
+                                .map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]))
+                                .collect();
+                            for c in lo..=hi {
+                                map.insert(c, String::from_utf16_lossy(&units));
+                                if let Some(last) = units.last_mut() {
+                                    *last = last.wrapping_add(1);
+                                }
+                            }
+                        }
+                        Obj::Array(targets) => {
+                            for (c, target) in (lo..=hi).zip(targets) {
+                                if let Obj::Str(target) = target {
+                                    map.insert(c, utf16(target));
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+        tokens.clear();
+    }
+    code_len
+}
+
+fn simple_encoding(doc: &Document, font: &Dict) -> Vec<Option<String>> {
+    let encoding = doc.get(font, b"Encoding");
+    let base = match encoding {
+        Some(Obj::Name(name)) => Some(name.as_slice()),
+        Some(Obj::Dict(dict)) => dict.get(&b"BaseEncoding"[..]).and_then(Obj::as_name),
+        _ => None,
+    };
+
+    let mut table: Vec<Option<String>> = (0..256u32)
+        .map(|code| base_encoding(base, code as u8).map(String::from))
+        .collect();
+
+    if let Some(differences) = encoding.and_then(Obj::as_dict).and_then(|d| doc.get(d, b"Differences")).and_then(Obj::as_array) {
+        let mut code = 0usize;
+        for item in differences {
+            match doc.resolve(item) {
+                Obj::Int(n) => code = (*n).clamp(0, 255) as usize,
+                Obj::Name(glyph) => {
+                    if code < 256 {
+                        table[code] = glyph_to_unicode(&String::from_utf8_lossy(glyph));
+                    }
+                    code += 1;
+                }
+                _ => {}
+            }
+        }
+    }
+    table
+}
+
+const WIN_ANSI_80: &str = "€\u{81}‚ƒ„…†‡ˆ‰Š‹Œ\u{8d}Ž\u{8f}\u{90}‘’“”•–—˜™š›œ\u{9d}žŸ";
+const MAC_ROMAN_80: &str = "ÄÅÇÉÑÖÜáàâäãåçéèêëíìîïñóòôöõúùûü†°¢£§•¶ß®©™´¨≠ÆØ∞±≤≥¥µ∂∑∏π∫ªºΩæø¿¡¬√ƒ≈∆«»…\u{a0}ÀÃÕŒœ–—“”‘’÷◊ÿŸ⁄€‹›ﬁﬂ‡·‚„‰ÂÊÁËÈÍÎÏÌÓÔ\u{f8ff}ÒÚÛÙıˆ˜¯˘˙˚¸˝˛ˇ";
+/// StandardEncoding above 0x7F (Adobe's default for Type 1 fonts)
+const STANDARD_HIGH: &[(u8, char)] = &[
+    (0xA1, '¡'), (0xA2, '¢'), (0xA3, '£'), (0xA4, '⁄'), (0xA5, '¥'), (0xA6, 'ƒ'), (0xA7, '§'), (0xA8, '¤'),
+    (0xA9, '\''), (0xAA, '“'), (0xAB, '«'), (0xAC, '‹'), (0xAD, '›'), (0xAE, 'ﬁ'), (0xAF, 'ﬂ'), (0xB1, '–'),
+    (0xB2, '†'), (0xB3, '‡'), (0xB4, '·'), (0xB6, '¶'), (0xB7, '•'), (0xB8, '‚'), (0xB9, '„'), (0xBA, '”'),
+    (0xBB, '»'), (0xBC, '…'), (0xBD, '‰'), (0xBF, '¿'), (0xC1, '`'), (0xC2, '´'), (0xC3, 'ˆ'), (0xC4, '˜'),
+    (0xC5, '¯'), (0xC6, '˘'), (0xC7, '˙'), (0xC8, '¨'), (0xCA, '˚'), (0xCB, '¸'), (0xCD, '˝'), (0xCE, '˛'),
+    (0xCF, 'ˇ'), (0xD0, '—'), (0xE1, 'Æ'), (0xE3, 'ª'), (0xE8, 'Ł'), (0xE9, 'Ø'), (0xEA, 'Œ'), (0xEB, 'º'),
+    (0xF1, 'æ'), (0xF5, 'ı'), (0xF8, 'ł'), (0xF9, 'ø'), (0xFA, 'œ'), (0xFB, 'ß'),
+];
+
+fn base_encoding(name: Option<&[u8]>, code: u8) -> Option<char> {
+    match (name, code) {
+        (_, 0..=0x1F) => None,
+        (Some(b"WinAnsiEncoding"), 0x80..=0x9F) => WIN_ANSI_80.chars().nth(code as usize - 0x80),
+        (Some(b"WinAnsiEncoding"), _) => Some(code as char),
+        (Some(b"MacRomanEncoding"), 0x80..=0xFF) => MAC_ROMAN_80.chars().nth(code as usize - 0x80),
+        (Some(b"MacRomanEncoding"), _) => Some(code as char),
+        (_, 0x27) => Some('’'),
+        (_, 0x60) => Some('‘'),
+        (_, 0x20..=0x7E) => Some(code as char),
+        _ => STANDARD_HIGH.iter().find(|(c, _)| *c == code).map(|(_, ch)| *ch),
+    }
+}
+
+const GLYPH_NAMES: &[(&str, &str)] = &[
+    ("space", " "), ("exclam", "!"), ("quotedbl", "\""), ("numbersign", "#"), ("dollar", "$"), ("percent", "%"),
+    ("ampersand", "&"), ("quotesingle", "'"), ("quoteright", "’"), ("quoteleft", "‘"), ("parenleft", "("),
+    ("parenright", ")"), ("asterisk", "*"), ("plus", "+"), ("comma", ","), ("hyphen", "-"), ("period", "."),
+    ("slash", "/"), ("zero", "0"), ("one", "1"), ("two", "2"), ("three", "3"), ("four", "4"), ("five", "5"),
+    ("six", "6"), ("seven", "7"), ("eight", "8"), ("nine", "9"), ("colon", ":"), ("semicolon", ";"),
+    ("less", "<"), ("equal", "="), ("greater", ">"), ("question", "?"), ("at", "@"), ("bracketleft", "["),
+    ("backslash", "\\"), ("bracketright", "]"), ("asciicircum", "^"), ("underscore", "_"), ("grave", "`"),
+    ("braceleft", "{"), ("bar", "|"), ("braceright", "}"), ("asciitilde", "~"), ("quotedblleft", "“"),
+    ("quotedblright", "”"), ("quotesinglbase", "‚"), ("quotedblbase", "„"), ("endash", "–"), ("emdash", "—"),
+    ("bullet", "•"), ("ellipsis", "…"), ("dagger", "†"), ("daggerdbl", "‡"), ("section", "§"),
+    ("paragraph", "¶"), ("degree", "°"), ("plusminus", "±"), ("multiply", "×"), ("divide", "÷"),
+    ("minus", "−"), ("periodcentered", "·"), ("copyright", "©"), ("registered", "®"), ("trademark", "™"),
+    ("Euro", "€"), ("sterling", "£"), ("yen", "¥"), ("cent", "¢"), ("dotlessi", "ı"), ("germandbls", "ß"),
+    ("ae", "æ"), ("AE", "Æ"), ("oe", "œ"), ("OE", "Œ"), ("oslash", "ø"), ("Oslash", "Ø"), ("lslash", "ł"),
+    ("Lslash", "Ł"), ("eth", "ð"), ("Eth", "Ð"), ("thorn", "þ"), ("Thorn", "Þ"), ("exclamdown", "¡"),
+    ("questiondown", "¿"), ("guillemotleft", "«"), ("guillemotright", "»"), ("guilsinglleft", "‹"),
+    ("guilsinglright", "›"), ("perthousand", "‰"), ("mu", "µ"), ("fi", "fi"), ("fl", "fl"), ("ff", "ff"),
+    ("ffi", "ffi"), ("ffl", "ffl"), ("nbspace", " "), ("nonbreakingspace", " "), ("sfthyphen", "-"),
+    ("acute", "´"), ("dieresis", "¨"), ("circumflex", "ˆ"), ("tilde", "˜"), ("macron", "¯"), ("caron", "ˇ"),
+    ("ring", "˚"), ("cedilla", "¸"), ("breve", "˘"), ("dotaccent", "˙"), ("ogonek", "˛"), ("hungarumlaut", "˝"),
+    ("arrowright", "→"), ("arrowleft", "←"), ("arrowup", "↑"), ("arrowdown", "↓"), ("arrowboth", "↔"),
+    ("infinity", "∞"), ("lessequal", "≤"), ("greaterequal", "≥"), ("notequal", "≠"), ("approxequal", "≈"),
+    ("partialdiff", "∂"), ("summation", "∑"), ("product", "∏"), ("integral", "∫"), ("radical", "√"),
+    ("element", "∈"), ("proportional", "∝"), ("similar", "∼"), ("prime", "′"), ("logicalnot", "¬"),
+    ("alpha", "α"), ("beta", "β"), ("gamma", "γ"), ("delta", "δ"), ("epsilon", "ε"), ("zeta", "ζ"),
+    ("eta", "η"), ("theta", "θ"), ("iota", "ι"), ("kappa", "κ"), ("lambda", "λ"), ("nu", "ν"), ("xi", "ξ"),
+    ("omicron", "ο"), ("pi", "π"), ("rho", "ρ"), ("sigma", "σ"), ("tau", "τ"), ("upsilon", "υ"), ("phi", "φ"),
+    ("chi", "χ"), ("psi", "ψ"), ("omega", "ω"), ("Gamma", "Γ"), ("Delta", "Δ"), ("Theta", "Θ"), ("Lambda", "Λ"),
+    ("Xi", "Ξ"), ("Pi", "Π"), ("Sigma", "Σ"), ("Upsilon", "Υ"), ("Phi", "Φ"), ("Psi", "Ψ"), ("Omega", "Ω"),
+];
+
+/// Precomposed letters for `<letter><accent>` glyph names (`eacute`, `Scaron`).
+const ACCENTS: &[(&str, &str, &str)] = &[
+    ("acute", "AEIOUYaeiouyCcNnSsZzRrLl", "ÁÉÍÓÚÝáéíóúýĆćŃńŚśŹźŔŕĹĺ"),
+    ("grave", "AEIOUaeiou", "ÀÈÌÒÙàèìòù"),
+    ("circumflex", "AEIOUaeiouCcGgHhJjSsWwYy", "ÂÊÎÔÛâêîôûĈĉĜĝĤĥĴĵŜŝŴŵŶŷ"),
+    ("dieresis", "AEIOUaeiouyY", "ÄËÏÖÜäëïöüÿŸ"),
+    ("tilde", "ANOanoIiUu", "ÃÑÕãñõĨĩŨũ"),
+    ("ring", "AaUu", "ÅåŮů"),
+    ("cedilla", "CcSsTtGgKkLlNnRr", "ÇçŞşŢţĢģĶķĻļŅņŖŗ"),
+    ("caron", "CcDdEeNnRrSsTtZz", "ČčĎďĚěŇňŘřŠšŤťŽž"),
+];
+
+/// Unicode text for an Adobe glyph name: the common names, `uniXXXX`/`uXXXX`
+/// forms, accented letters, and suffixed variants like `a.sc` or `f_i`.
+fn glyph_to_unicode(name: &str) -> Option<String> {
+    let base = name.split('.').next().unwrap_or(name);
+    if base.contains('_') {
+        let parts: Option<Vec<String>> = base.split('_').map(glyph_to_unicode).collect();
+        return parts.map(|p| p.concat());
+    }
+    if base.len() == 1 && base.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Some(base.to_string());
+    }
+    if let Some((_, text)) = GLYPH_NAMES.iter().find(|(n, _)| *n == base) {
+        return Some(text.to_string());
+    }
+    if let Some(hex) = base.strip_prefix("uni") {
+        // Byte slicing below needs ASCII, or a multi-byte char could straddle a boundary
+        if hex.is_ascii() && hex.len() >= 4 && hex.len() % 4 == 0 {
+            let units: Option<Vec<u16>> = (0..hex.len())
+                .step_by(4)
+                .map(|i| u16::from_str_radix(&hex[i..i + 4], 16).ok())
+                .collect();
+            return units.map(|u| String::from_utf16_lossy(&u));
+        }
+    }
+    if let Some(hex) = base.strip_prefix('u') {
+        if (4..=6).contains(&hex.len()) {
+            return u32::from_str_radix(hex, 16).ok().and_then(char::from_u32).map(String::from);
+        }
+    }
+    let mut chars = base.chars();
+    let letter = chars.next()?;
+    let accent = chars.as_str();
+    ACCENTS.iter().find(|(name, _, _)| *name == accent).and_then(|(_, letters, composed)| {
+        letters.chars().position(|c| c == letter).and_then(|i| composed.chars().nth(i)).map(String::from)
+    })
+}
+
+// ─── Content streams ─────────────────────────────────────────────────
+
+type Matrix = [f64; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+fn multiply(m1: &Matrix, m2: &Matrix) -> Matrix {
+    [
+        m1[0] * m2[0] + m1[1] * m2[2],
+        m1[0] * m2[1] + m1[1] * m2[3],
+        m1[2] * m2[0] + m1[3] * m2[2],
+        m1[2] * m2[1] + m1[3] * m2[3],
+        m1[4] * m2[0] + m1[5] * m2[2] + m2[4],
+        m1[4] * m2[1] + m1[5] * m2[3] + m2[5],
+    ]
+}
+
+fn translate(tx: f64, ty: f64) -> Matrix {
+    [1.0, 0.0, 0.0, 1.0, tx, ty]
+}
+
+/// Turns positioned glyphs into lines and paragraphs.
+#[derive(Default)]
+struct TextOutput {
+    out: String,
+    /// End x, baseline y and size of the last glyph
+    last: Option<(f64, f64, f64)>,
+}
+
+impl TextOutput {
+    fn glyph(&mut self, text: &str, x: f64, y: f64, end_x: f64, size: f64) {
+        let size = size.abs().max(1.0);
+        if let Some((last_end, last_y, last_size)) = self.last {
+            let line = last_size.max(size);
+            let dy = last_y - y;
+            if dy.abs() > line * 0.5 {
+                // Moving up or a large jump down: new block (column, footnote, heading)
+                self.out.push_str(if dy < 0.0 || dy > line * 2.0 { "\n\n" } else { "\n" });
+            } else {
+                let gap = x - last_end;
+                if gap < -line * 2.0 {
+                    self.out.push('\n');
+                } else if gap > size * 0.15 && !self.out.ends_with(char::is_whitespace) && !text.starts_with(' ') {
+                    self.out.push(' ');
+                }
+            }
+        }
+        self.out.push_str(text);
+        self.last = Some((end_x, y, size));
+    }
+
+    fn finish(self) -> String {
+        let mut result = String::new();
+        let mut blank = false;
+        for line in self.out.lines() {
+            let line: String = line
+                .chars()
+                .filter(|c| !c.is_control() || *c == '\t')
+                .collect::<String>()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            if line.is_empty() {
+                blank = true;
+                continue;
+            }
+            if !result.is_empty() {
+                result.push_str(if blank { "\n\n" } else { "\n" });
+            }
+            blank = false;
+            result.push_str(&line);
+        }
+        result
+    }
+}
+
+struct TextState<'a> {
+    ctm: Matrix,
+    tm: Matrix,
+    tlm: Matrix,
+    font: Option<&'a Font>,
+    size: f64,
+    char_spacing: f64,
+    word_spacing: f64,
+    h_scale: f64,
+    leading: f64,
+    rise: f64,
+}
+
+impl TextState<'_> {
+    fn new_line(&mut self, tx: f64, ty: f64) {
+        self.tlm = multiply(&translate(tx, ty), &self.tlm);
+        self.tm = self.tlm;
+    }
+
+    fn show(&mut self, bytes: &[u8], out: &mut TextOutput) {
+        let Some(font) = self.font else {
+            // No font selected: best effort as Latin-1
+            let text: String = bytes.iter().map(|&b| b as char).collect();
+            let trm = multiply(&self.tm, &self.ctm);
+            out.glyph(&text, trm[4], trm[5], trm[4], self.size);
+            return;
+        };
+
+        for code in font.codes(bytes) {
+            let trm = multiply(&multiply(&[self.size * self.h_scale, 0.0, 0.0, self.size, 0.0, self.rise], &self.tm), &self.ctm);
+            let mut advance = font.width(code) / 1000.0 * self.size + self.char_spacing;
+            if font.code_len == 1 && code == 32 {
+                advance += self.word_spacing;
+            }
+            let advance = advance * self.h_scale;
+            let next = multiply(&translate(advance, 0.0), &self.tm);
+            let end = multiply(&next, &self.ctm);
+
+            if let Some(text) = font.text(code) {
+                let size = trm[2].hypot(trm[3]);
+                out.glyph(&text, trm[4], trm[5], end[4], size);
+            }
+            self.tm = next;
+        }
+    }
+}
+
+/// Runs a document's content streams. Fonts are loaded once per document, a
+/// form XObject is never entered while it is already running, and the
+/// `MAX_OPERATORS` / `MAX_CONTENT_BYTES` budgets are shared by every page and
+/// form, so forms that call each other many times can't run forever.
+struct Interpreter<'a> {
+    doc: &'a Document,
+    /// Fonts by object number
+    fonts: HashMap<u32, Rc<Font>>,
+    /// Object numbers of the form XObjects currently running
+    forms: Vec<u32>,
+    operators_left: usize,
+    bytes_left: usize,
+}
+
+impl<'a> Interpreter<'a> {
+    fn new(doc: &'a Document) -> Self {
+        Interpreter {
+            doc,
+            fonts: HashMap::new(),
+            forms: Vec::new(),
+            operators_left: MAX_OPERATORS,
+            bytes_left: MAX_CONTENT_BYTES,
+        }
+    }
+
+    fn font(&mut self, obj: &Obj) -> Option<Rc<Font>> {
+        let dict = self.doc.resolve(obj).as_dict()?;
+        match obj {
+            Obj::Ref(num) => Some(
+                self.fonts
+                    .entry(*num)
+                    .or_insert_with(|| Rc::new(Font::load(self.doc, dict)))
+                    .clone(),
+            ),
+            _ => Some(Rc::new(Font::load(self.doc, dict))),
+        }
+    }
+
+    /// Interpret one content stream, appending its text to `out`.
+    fn run(&mut self, content: &[u8], resources: &Dict, ctm: Matrix, out: &mut TextOutput, depth: usize) {
+        if depth > MAX_DEPTH {
+            return;
+        }
+        if content.len() > self.bytes_left {
+            self.bytes_left = 0;
+            return;
+        }
+        self.bytes_left -= content.len();
+        let doc = self.doc;
+        let font_dict = doc.get(resources, b"Font").and_then(Obj::as_dict);
+        let xobjects = doc.get(resources, b"XObject").and_then(Obj::as_dict);
+        let mut fonts: HashMap<Vec<u8>, Rc<Font>> = HashMap::new();
+        if let Some(font_dict) = font_dict {
+            for (name, font) in font_dict {
+                if let Some(font) = self.font(font) {
+                    fonts.insert(name.clone(), font);
+                }
+            }
+        }
+
+        let mut state = TextState {
+            ctm,
+            tm: IDENTITY,
+            tlm: IDENTITY,
+            font: None,
+            size: 0.0,
+            char_spacing: 0.0,
+            word_spacing: 0.0,
+            h_scale: 1.0,
+            leading: 0.0,
+            rise: 0.0,
+        };
+        let mut saved: Vec<Matrix> = Vec::new();
+        let mut operands: Vec<Obj> = Vec::new();
+        let mut lexer = Lexer::new(content, 0);
+        let num = |operands: &[Obj], i: usize| operands.get(i).and_then(Obj::as_f64).unwrap_or(0.0);
+
+        while let Some(token) = lexer.object(0) {
+            let Obj::Op(op) = token else {
+                operands.push(token);
+                continue;
+            };
+            if self.operators_left == 0 {
+                return;
+            }
+            self.operators_left -= 1;
+            let n = operands.len();
+            let arg = |i: usize| num(&operands, n.saturating_sub(i));
+            match op.as_slice() {
+                b"q" => saved.push(state.ctm),
+                b"Q" => state.ctm = saved.pop().unwrap_or(ctm),
+                b"cm" if n >= 6 => {
+                    let m = [arg(6), arg(5), arg(4), arg(3), arg(2), arg(1)];
+                    state.ctm = multiply(&m, &state.ctm);
+                }
+                b"BT" => {
+                    state.tm = IDENTITY;
+                    state.tlm = IDENTITY;
+                }
+                b"Tf" if n >= 2 => {
+                    state.font = operands[n - 2].as_name().and_then(|name| fonts.get(name)).map(Rc::as_ref);
+                    state.size = arg(1);
+                }
+                b"Tc" => state.char_spacing = arg(1),
+                b"Tw" => state.word_spacing = arg(1),
+                b"Tz" => state.h_scale = arg(1) / 100.0,
+                b"TL" => state.leading = arg(1),
+                b"Ts" => state.rise = arg(1),
+                b"Td" => state.new_line(arg(2), arg(1)),
+                b"TD" => {
+                    state.leading = -arg(1);
+                    state.new_line(arg(2), arg(1));
+                }
+                b"Tm" if n >= 6 => {
+                    state.tlm = [arg(6), arg(5), arg(4), arg(3), arg(2), arg(1)];
+                    state.tm = state.tlm;
+                }
+                b"T*" => state.new_line(0.0, -state.leading),
+                b"Tj" => {
+                    if let Some(bytes) = operands.last().and_then(Obj::as_bytes) {
+                        state.show(bytes, out);
+                    }
+                }
+                b"'" => {
+                    state.new_line(0.0, -state.leading);
+                    if let Some(bytes) = operands.last().and_then(Obj::as_bytes) {
+                        state.show(bytes, out);
+                    }
+                }
+                b"\"" if n >= 3 => {
+                    state.word_spacing = arg(3);
+                    state.char_spacing = arg(2);
+                    state.new_line(0.0, -state.leading);
+                    if let Some(bytes) = operands[n - 1].as_bytes() {
+                        state.show(bytes, out);
+                    }
+                }
+                b"TJ" => {
+                    if let Some(items) = operands.last().and_then(Obj::as_array) {
+                        for item in items {
+                            match item {
+                                Obj::Str(bytes) => state.show(bytes, out),
+                                other => {
+                                    let shift = -other.as_f64().unwrap_or(0.0) / 1000.0 * state.size * state.h_scale;
+                                    state.tm = multiply(&translate(shift, 0.0), &state.tm);
+                                }
+                            }
+                        }
+                    }
+                }
+                b"Do" => {
+                    let form = operands
+                        .last()
+                        .and_then(Obj::as_name)
+                        .and_then(|name| xobjects.and_then(|x| x.get(name)));
+                    // Forms are indirect objects; one already running would recurse into itself
+                    let running = |form: &Obj| matches!(form, Obj::Ref(num) if self.forms.contains(num));
+                    if let Some(form) = form.filter(|f| !running(f) && self.bytes_left > 0) {
+                        if let Obj::Stream(dict, _) = doc.resolve(form) {
+                            if doc.get(dict, b"Subtype").and_then(Obj::as_name) == Some(b"Form") {
+                                let matrix = doc
+                                    .get(dict, b"Matrix")
+                                    .and_then(Obj::as_array)
+                                    .filter(|m| m.len() == 6)
+                                    .map(|m| {
+                                        let v: Vec<f64> = m.iter().map(|x| doc.resolve(x).as_f64().unwrap_or(0.0)).collect();
+                                        [v[0], v[1], v[2], v[3], v[4], v[5]]
+                                    })
+                                    .unwrap_or(IDENTITY);
+                                let form_resources = doc.get(dict, b"Resources").and_then(Obj::as_dict).unwrap_or(resources);
+                                if let Some(data) = doc.stream_bytes(form) {
+                                    let num = match form {
+                                        Obj::Ref(num) => Some(*num),
+                                        _ => None,
+                                    };
+                                    self.forms.extend(num);
+                                    self.run(&data, form_resources, multiply(&matrix, &state.ctm), out, depth + 1);
+                                    if num.is_some() {
+                                        self.forms.pop();
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                b"ID" => lexer.skip_inline_image(),
+                _ => {}
+            }
+            operands.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// A PDF whose objects are numbered from 1 in order, with `trailer` as the
+    /// trailer dictionary's entries.
+    fn pdf(objects: &[Vec<u8>], trailer: &str) -> Vec<u8> {
+        let mut data = b"%PDF-1.7\n".to_vec();
+        for (i, body) in objects.iter().enumerate() {
+            data.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            data.extend_from_slice(body);
+            data.extend_from_slice(b"\nendobj\n");
+        }
+        data.extend_from_slice(format!("trailer\n<< {} >>\n%%EOF\n", trailer).as_bytes());
+        data
+    }
+
+    fn obj(body: &str) -> Vec<u8> {
+        body.as_bytes().to_vec()
+    }
+
+    fn stream(dict: &str, data: &[u8]) -> Vec<u8> {
+        let mut body = format!("<< {} /Length {} >>\nstream\n", dict, data.len()).into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\nendstream");
+        body
+    }
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// One page (object 3) showing `content` with font `font` as /F1 (object 5).
+    fn single_page(content: Vec<u8>, font: Vec<u8>, extra: Vec<Vec<u8>>) -> Vec<u8> {
+        let mut objects = vec![
+            obj("<< /Type /Catalog /Pages 2 0 R >>"),
+            obj("<< /Type /Pages /Kids [3 0 R] /Count 1 >>"),
+            obj("<< /Type /Page /Parent 2 0 R /Contents 4 0 R /Resources << /Font << /F1 5 0 R >> /XObject << /X1 6 0 R >> >> >>"),
+            content,
+            font,
+        ];
+        objects.extend(extra);
+        pdf(&objects, "/Root 1 0 R")
+    }
+
+    fn helvetica() -> Vec<u8> {
+        obj("<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>")
+    }
+
+    fn text_of(data: &[u8]) -> String {
+        let text = extract_text(data).unwrap().text;
+        text.strip_prefix("--- Page 1 ---\n\n").unwrap().to_string()
+    }
+
+    #[test]
+    fn extracts_pages_and_title() {
+        let page = |content: &str| obj(&format!("<< /Type /Page /Parent 2 0 R /Contents {} 0 R >>", content));
+        let data = pdf(
+            &[
+                obj("<< /Type /Catalog /Pages 2 0 R >>"),
+                obj("<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 2 /Resources << /Font << /F1 7 0 R >> >> >>"),
+                page("5"),
+                page("6"),
+                stream("", b"BT /F1 12 Tf 72 700 Td (Hello World) Tj 0 -14 Td (Second line) Tj ET"),
+                stream("", b"BT /F1 12 Tf 72 700 Td (Page two) Tj ET"),
+                helvetica(),
+                obj("<< /Title <FEFF004D00790020005400690074006C0065> >>"),
+            ],
+            "/Root 1 0 R /Info 8 0 R",
+        );
+        let result = extract_text(&data).unwrap();
+        assert_eq!(result.pages, 2);
+        assert_eq!(result.title.as_deref(), Some("My Title"));
+        assert_eq!(result.text, "--- Page 1 ---\n\nHello World\nSecond line\n\n--- Page 2 ---\n\nPage two");
+    }
+
+    #[test]
+    fn spaces_from_positioning() {
+        let content = stream("", b"BT /F1 12 Tf 72 700 Td [(Hello) -300 (World)] TJ [(Ke) -20 (rn)] TJ ET");
+        assert_eq!(text_of(&single_page(content, helvetica(), vec![])), "Hello WorldKern");
+    }
+
+    #[test]
+    fn decodes_flate_and_differences() {
+        let content = stream("/Filter /FlateDecode", &deflate(b"BT /F1 12 Tf 72 700 Td (A\x93x\x94) Tj ET"));
+        let font = obj(
+            "<< /Type /Font /Subtype /Type1 /Encoding << /BaseEncoding /WinAnsiEncoding /Differences [65 /eacute] >> >>",
+        );
+        assert_eq!(text_of(&single_page(content, font, vec![])), "\u{e9}\u{201c}x\u{201d}");
+    }
+
+    #[test]
+    fn maps_composite_fonts_through_to_unicode() {
+        let cmap = b"begincmap 1 begincodespacerange <0000> <FFFF> endcodespacerange \
+            2 beginbfchar <0001> <0048> <0002> <0069> endbfchar \
+            1 beginbfrange <0010> <0012> <0061> endbfrange endcmap";
+        let content = stream("", b"BT /F1 12 Tf 72 700 Td <00010002> Tj 0 -14 Td <001000110012> Tj ET");
+        let font = obj("<< /Type /Font /Subtype /Type0 /Encoding /Identity-H /ToUnicode 6 0 R >>");
+        assert_eq!(text_of(&single_page(content, font, vec![stream("", cmap)])), "Hi\nabc");
+    }
+
+    #[test]
+    fn reads_object_streams() {
+        // The font, object 6, is only defined inside the object stream
+        let data = pdf(
+            &[
+                obj("<< /Type /Catalog /Pages 2 0 R >>"),
+                obj("<< /Type /Pages /Kids [3 0 R] /Count 1 >>"),
+                obj("<< /Type /Page /Parent 2 0 R /Contents 4 0 R /Resources << /Font << /F1 6 0 R >> >> >>"),
+                stream("", b"BT /F1 12 Tf 72 700 Td (Packed) Tj ET"),
+                stream(
+                    "/Type /ObjStm /N 1 /First 4 /Filter /FlateDecode",
+                    &deflate(b"6 0 << /Type /Font /Subtype /Type1 >>"),
+                ),
+            ],
+            "/Root 1 0 R",
+        );
+        assert_eq!(text_of(&data), "Packed");
+    }
+
+    #[test]
+    fn runs_self_referencing_forms_once() {
+        let content = stream("", b"/X1 Do");
+        let form = stream(
+            "/Type /XObject /Subtype /Form /Resources << /Font << /F1 5 0 R >> /XObject << /X1 6 0 R >> >>",
+            b"BT /F1 12 Tf 72 700 Td (Form) Tj ET /X1 Do",
+        );
+        assert_eq!(text_of(&single_page(content, helvetica(), vec![form])), "Form");
+    }
+
+    #[test]
+    fn rejects_unreadable_files() {
+        assert!(extract_text(b"<html></html>").is_err());
+
+        let content = stream("", b"BT /F1 12 Tf (Secret) Tj ET");
+        let mut encrypted = single_page(content, helvetica(), vec![]);
+        encrypted.extend_from_slice(b"trailer\n<< /Encrypt << /Filter /Standard >> >>\n");
+        assert!(extract_text(&encrypted).err().unwrap().contains("encrypted"));
+
+        let scanned = single_page(stream("", b"q 100 0 0 100 0 0 cm /Im1 Do Q"), helvetica(), vec![]);
+        assert!(extract_text(&scanned).err().unwrap().contains("no extractable text"));
+    }
+}
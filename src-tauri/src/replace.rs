@@ -0,0 +1,274 @@
+use crate::fs_commands::{
+    content_hash, walk_search_files, write_temp_sibling, ContentMatcher, PathFilter, SearchOptions,
+};
+use crate::history::PendingSnapshot;
+use crate::ignore::IgnoreMatcher;
+use crate::sandbox::SandboxState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Clone)]
+pub struct LineChange {
+    pub line: usize,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct FileReplacePreview {
+    pub path: String,
+    pub name: String,
+    /// Hash of the content the preview was computed from; pass it back to apply
+    pub hash: String,
+    pub replacements: usize,
+    pub changes: Vec<LineChange>,
+}
+
+#[derive(Deserialize)]
+pub struct ReplaceTarget {
+    pub path: String,
+    pub hash: String,
+}
+
+#[derive(Serialize)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Default)]
+pub struct ReplaceResult {
+    /// Filled in preview mode
+    pub previews: Vec<FileReplacePreview>,
+    /// Filled in apply mode
+    pub applied: Vec<String>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+/// Replace matches line by line (the same unit search reports), keeping each
+/// line's original ending.
+fn replace_content(
+    matcher: &ContentMatcher,
+    content: &str,
+    replacement: &str,
+) -> (String, usize, Vec<LineChange>) {
+    let mut out = String::with_capacity(content.len());
+    let mut total = 0;
+    let mut changes = Vec::new();
+
+    for (i, segment) in content.split_inclusive('\n').enumerate() {
+        let body = segment.trim_end_matches('\n').trim_end_matches('\r');
+        let ending = &segment[body.len()..];
+
+        let (replaced, count) = matcher.replace_line(body, replacement);
+        if count > 0 {
+            total += count;
+            changes.push(LineChange {
+                line: i + 1,
+                before: body.to_string(),
+                after: replaced.clone(),
+            });
+        }
+        out.push_str(&replaced);
+        out.push_str(ending);
+    }
+
+    (out, total, changes)
+}
+
+fn preview_replacements(
+    dir: &Path,
+    matcher: &ContentMatcher,
+    filter: &PathFilter,
+    replacement: &str,
+) -> ReplaceResult {
+    let mut result = ReplaceResult::default();
+    let ignore = IgnoreMatcher::new(dir);
+
+    walk_search_files(dir, dir, filter, &ignore, &mut |path, name| {
+        let bytes = match fs::read(path) {
+            Ok(b) => b,
+            Err(_) => return true,
+        };
+        let content = match std::str::from_utf8(&bytes) {
+            Ok(c) => c,
+            Err(_) => return true,
+        };
+
+        let (_, replacements, changes) = replace_content(matcher, content, replacement);
+        if replacements > 0 {
+            result.previews.push(FileReplacePreview {
+                path: path.to_string_lossy().to_string(),
+                name: name.to_string(),
+                hash: content_hash(&bytes),
+                replacements,
+                changes,
+            });
+        }
+        true
+    });
+
+    result
+}
+
+/// Canonical paths of the files preview would consider: the same walk, with
+/// the same include/exclude globs and ignore rules.
+fn eligible_files(dir: &Path, filter: &PathFilter) -> HashSet<PathBuf> {
+    let mut files = HashSet::new();
+    let ignore = IgnoreMatcher::new(dir);
+    walk_search_files(dir, dir, filter, &ignore, &mut |path, _| {
+        if let Ok(canonical) = fs::canonicalize(path) {
+            files.insert(canonical);
+        }
+        true
+    });
+    files
+}
+
+/// Put `original` back atomically, the same way the new content went in.
+fn restore_original(path: &Path, original: &[u8]) -> std::io::Result<()> {
+    let tmp = write_temp_sibling(path, original)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+/// Write all accepted files or none: every new file is staged to a temp file
+/// first, then renamed over the original. A failed rename restores the files
+/// already replaced. Targets the preview's filters would not have walked are
+/// skipped. Like `write_file`, a successful apply records local history for
+/// every file it rewrote.
+fn apply_replacements(
+    sandbox: &SandboxState,
+    dir: &Path,
+    matcher: &ContentMatcher,
+    filter: &PathFilter,
+    replacement: &str,
+    targets: &[ReplaceTarget],
+) -> Result<ReplaceResult, String> {
+    let mut result = ReplaceResult::default();
+    // (canonical path written, path as the caller named it, original bytes, new content)
+    let mut staged: Vec<(PathBuf, String, Vec<u8>, String)> = Vec::new();
+    let dir = fs::canonicalize(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let eligible = eligible_files(&dir, filter);
+
+    for target in targets {
+        let skip = |reason: &str| SkippedFile {
+            path: target.path.clone(),
+            reason: reason.to_string(),
+        };
+
+        // Compare resolved paths so `..` segments and symlinks can't leave the folder
+        let path = match fs::canonicalize(&target.path) {
+            Ok(p) if p.starts_with(&dir) => p,
+            Ok(_) => {
+                result.skipped.push(skip("outside the search folder"));
+                continue;
+            }
+            Err(e) => {
+                result.skipped.push(skip(&format!("unreadable: {}", e)));
+                continue;
+            }
+        };
+        if !eligible.contains(&path) {
+            result.skipped.push(skip("excluded by the search filters"));
+            continue;
+        }
+        if let Err(e) = sandbox.check_write(&target.path) {
+            result.skipped.push(skip(&e));
+            continue;
+        }
+        let bytes = match fs::read(&path) {
+            Ok(b) => b,
+            Err(e) => {
+                result.skipped.push(skip(&format!("unreadable: {}", e)));
+                continue;
+            }
+        };
+        if content_hash(&bytes) != target.hash {
+            result.skipped.push(skip("changed on disk since preview"));
+            continue;
+        }
+        let content = match String::from_utf8(bytes.clone()) {
+            Ok(c) => c,
+            Err(_) => {
+                result.skipped.push(skip("not valid UTF-8"));
+                continue;
+            }
+        };
+
+        let (new_content, replacements, _) = replace_content(matcher, &content, replacement);
+        if replacements == 0 {
+            result.skipped.push(skip("no matches"));
+            continue;
+        }
+        staged.push((path, target.path.clone(), bytes, new_content));
+    }
+
+    // Read each original for history before anything is replaced
+    let pending: Vec<_> = staged
+        .iter()
+        .map(|(path, _, _, new_content)| PendingSnapshot::before_write(path, new_content.len()))
+        .collect();
+
+    // Stage every file before touching any original
+    let mut temps: Vec<PathBuf> = Vec::new();
+    for (path, _, _, new_content) in &staged {
+        match write_temp_sibling(path, new_content.as_bytes()) {
+            Ok(tmp) => temps.push(tmp),
+            Err(e) => {
+                for tmp in &temps {
+                    let _ = fs::remove_file(tmp);
+                }
+                return Err(format!("Failed to stage {}: {}", path.display(), e));
+            }
+        }
+    }
+
+    for (i, ((path, _, _, _), tmp)) in staged.iter().zip(&temps).enumerate() {
+        if let Err(e) = fs::rename(tmp, path) {
+            for (done_path, _, original, _) in &staged[..i] {
+                let _ = restore_original(done_path, original);
+            }
+            for tmp in &temps[i..] {
+                let _ = fs::remove_file(tmp);
+            }
+            return Err(format!("Failed to replace {}: {} (changes rolled back)", path.display(), e));
+        }
+    }
+
+    for (snapshot, (_, _, _, new_content)) in pending.into_iter().zip(&staged) {
+        if let Some(snapshot) = snapshot {
+            snapshot.after_write(new_content.as_bytes());
+        }
+    }
+    result.applied = staged.into_iter().map(|(_, named, _, _)| named).collect();
+    Ok(result)
+}
+
+/// Project-wide search and replace over the same files `search_file_contents` walks.
+/// Without `apply`, returns a per-file preview of the line changes. With `apply`,
+/// rewrites exactly those files, skipping any whose content no longer matches the
+/// preview hash.
+#[tauri::command]
+pub async fn replace_in_files(
+    sandbox: tauri::State<'_, SandboxState>,
+    dir: String,
+    query: String,
+    replacement: String,
+    options: Option<SearchOptions>,
+    apply: Option<Vec<ReplaceTarget>>,
+) -> Result<ReplaceResult, String> {
+    sandbox.check(&dir)?;
+    let options = options.unwrap_or_default();
+    let matcher = ContentMatcher::new(&query, &options)?;
+    let filter = PathFilter::new(&options.include, &options.exclude)?;
+    let root = Path::new(&dir);
+
+    match apply {
+        None => Ok(preview_replacements(root, &matcher, &filter, &replacement)),
+        Some(targets) => apply_replacements(&sandbox, root, &matcher, &filter, &replacement, &targets),
+    }
+}
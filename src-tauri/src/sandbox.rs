@@ -0,0 +1,153 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Registry of folders the filesystem commands may touch. Opt-in: while no
+/// root is registered every path is allowed, as before. Once the frontend
+/// registers a workspace, paths outside the registered roots, the explicitly
+/// granted paths and `~/.shoulders` are rejected.
+///
+/// All paths are compared after canonicalisation, so `..` segments and
+/// symlinks pointing out of a root don't escape it.
+#[derive(Default)]
+pub struct SandboxState {
+    roots: Mutex<Vec<PathBuf>>,
+    /// Individual files or folders the user picked outside the roots (imports)
+    grants: Mutex<Vec<PathBuf>>,
+}
+
+/// Canonical form of `path`, which need not exist yet: the nearest existing
+/// ancestor is canonicalised and the remaining plain components are appended.
+fn resolve(path: &Path) -> Result<PathBuf, String> {
+    if !path.is_absolute() {
+        return Err(format!("Path must be absolute: {}", path.display()));
+    }
+    if let Ok(canonical) = fs::canonicalize(path) {
+        return Ok(canonical);
+    }
+
+    let unresolvable = || format!("Cannot resolve path: {}", path.display());
+    let mut missing = Vec::new();
+    let mut existing = path;
+    loop {
+        // Present but not canonicalisable: a dangling symlink that writes would follow
+        if fs::symlink_metadata(existing).is_ok() {
+            return Err(unresolvable());
+        }
+        // file_name() is None for a trailing `..`, which can't be resolved by hand
+        missing.push(existing.file_name().ok_or_else(unresolvable)?);
+        existing = existing.parent().ok_or_else(unresolvable)?;
+        if let Ok(canonical) = fs::canonicalize(existing) {
+            return Ok(missing.iter().rev().fold(canonical, |acc, part| acc.join(part)));
+        }
+    }
+}
+
+fn global_config_dir() -> Option<PathBuf> {
+    let dir = dirs::home_dir()?.join(".shoulders");
+    fs::canonicalize(&dir).ok().or(Some(dir))
+}
+
+/// Entries of `~/.shoulders` that decide what the AI may do. Only their own
+/// commands write them; the filesystem commands refuse to, and the command
+/// policy denies shell commands that name them.
+pub(crate) const PROTECTED: &[&str] = &["command-policies", "allowed-hosts.json"];
+
+/// Whether a shell word may refer to a protected entry or to `.shoulders`
+/// itself (which a move or delete would take the entries along with).
+pub(crate) fn mentions_protected(word: &str) -> bool {
+    let trimmed = word.trim_end_matches('/');
+    PROTECTED.iter().any(|p| word.contains(p))
+        || trimmed.ends_with(".shoulders")
+        || (word.contains(".shoulders") && word.contains(['*', '?', '[', '{']))
+}
+
+fn is_protected(resolved: &Path) -> bool {
+    let Some(config) = global_config_dir() else { return false };
+    PROTECTED.iter().any(|p| {
+        let protected = config.join(p);
+        resolved.starts_with(&protected) || protected.starts_with(resolved)
+    })
+}
+
+impl SandboxState {
+    /// Reject `path` if the sandbox is active and it resolves outside every
+    /// allowed location.
+    pub(crate) fn check(&self, path: &str) -> Result<(), String> {
+        let roots = self.roots.lock().unwrap();
+        if roots.is_empty() {
+            return Ok(());
+        }
+
+        let resolved = resolve(Path::new(path))?;
+        let grants = self.grants.lock().unwrap();
+        let allowed = roots.iter().chain(grants.iter()).any(|r| resolved.starts_with(r))
+            || global_config_dir().is_some_and(|g| resolved.starts_with(g));
+
+        if allowed {
+            Ok(())
+        } else {
+            Err(format!("Access denied: {} is outside the workspace", path))
+        }
+    }
+
+    /// `check`, and also reject the protected entries of `~/.shoulders` and any
+    /// folder containing them. Applies even while the sandbox is off.
+    pub(crate) fn check_write(&self, path: &str) -> Result<(), String> {
+        self.check(path)?;
+        let resolved = resolve(Path::new(path)).unwrap_or_else(|_| PathBuf::from(path));
+        if is_protected(&resolved) {
+            return Err(format!("Access denied: {} is managed by the app", path));
+        }
+        Ok(())
+    }
+
+    /// The registered root `path` lies in, if any (the deepest, if roots nest).
+    pub(crate) fn root_for(&self, path: &str) -> Option<PathBuf> {
+        let resolved = resolve(Path::new(path)).ok()?;
+        let roots = self.roots.lock().unwrap();
+        roots
+            .iter()
+            .filter(|r| resolved.starts_with(r))
+            .max_by_key(|r| r.components().count())
+            .cloned()
+    }
+}
+
+/// Paths are resolved like `check` does, so a file that doesn't exist yet (a
+/// save-dialog target) can be granted.
+fn add_unique(list: &Mutex<Vec<PathBuf>>, path: &str) -> Result<(), String> {
+    let canonical = resolve(Path::new(path))?;
+    let mut list = list.lock().unwrap();
+    if !list.contains(&canonical) {
+        list.push(canonical);
+    }
+    Ok(())
+}
+
+/// Register a workspace (or an external folder such as a Zotero storage dir).
+/// The first registration turns the sandbox on.
+#[tauri::command]
+pub async fn sandbox_add_root(state: tauri::State<'_, SandboxState>, path: String) -> Result<(), String> {
+    add_unique(&state.roots, &path)
+}
+
+#[tauri::command]
+pub async fn sandbox_remove_root(state: tauri::State<'_, SandboxState>, path: String) -> Result<(), String> {
+    let canonical = resolve(Path::new(&path)).unwrap_or_else(|_| PathBuf::from(&path));
+    state.roots.lock().unwrap().retain(|r| *r != canonical);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn sandbox_roots(state: tauri::State<'_, SandboxState>) -> Result<Vec<String>, String> {
+    let roots = state.roots.lock().unwrap();
+    Ok(roots.iter().map(|r| r.to_string_lossy().to_string()).collect())
+}
+
+/// Allow one path outside the roots, for files the user picked in a dialog or
+/// dropped onto the window. Never call this with a path chosen by the AI.
+#[tauri::command]
+pub async fn sandbox_allow_path(state: tauri::State<'_, SandboxState>, path: String) -> Result<(), String> {
+    add_unique(&state.grants, &path)
+}
@@ -0,0 +1,459 @@
+use crate::fs_commands::{is_searchable_text, is_skipped_entry};
+use crate::ignore::{is_ignore_source, IgnoreMatcher};
+use crate::sandbox::SandboxState;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::Manager;
+
+/// Files larger than this are left out of the index (huge exported .bib/.csv dumps).
+const MAX_INDEXED_FILE_SIZE: u64 = 5 * 1024 * 1024;
+
+// Snippet markers handed to FTS5 — control characters never appear in indexed
+// text, so they can be stripped back out and turned into highlight ranges.
+const SNIPPET_START: char = '\u{2}';
+const SNIPPET_END: char = '\u{3}';
+
+/// One connection per workspace, keyed by workspace path. The ignore matcher
+/// opens a git repository, so it is built once per workspace and reused for
+/// watcher updates until an ignore file changes.
+pub struct SearchIndexState {
+    pub conns: Mutex<HashMap<String, Connection>>,
+    ignores: Mutex<HashMap<String, IgnoreMatcher>>,
+}
+
+impl Default for SearchIndexState {
+    fn default() -> Self {
+        Self {
+            conns: Mutex::new(HashMap::new()),
+            ignores: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+fn get_db_path(workspace: &str) -> Result<PathBuf, String> {
+    let shoulders_dir = Path::new(workspace).join(".shoulders");
+    if !shoulders_dir.exists() {
+        fs::create_dir_all(&shoulders_dir)
+            .map_err(|e| format!("Failed to create .shoulders: {}", e))?;
+    }
+    Ok(shoulders_dir.join("search-index.db"))
+}
+
+fn ensure_connection(state: &SearchIndexState, workspace: &str) -> Result<(), String> {
+    let mut guard = state.conns.lock().map_err(|e| e.to_string())?;
+    if guard.contains_key(workspace) {
+        return Ok(());
+    }
+
+    let path = get_db_path(workspace)?;
+    let conn = Connection::open(&path).map_err(|e| format!("Failed to open search index: {}", e))?;
+
+    // WAL mode + busy timeout
+    conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA busy_timeout=5000;")
+        .map_err(|e| format!("Failed to set pragmas: {}", e))?;
+
+    // indexed_files.id doubles as the FTS rowid so updates and deletes stay keyed
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS indexed_files (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            path TEXT NOT NULL UNIQUE,
+            modified INTEGER NOT NULL,
+            size INTEGER NOT NULL
+        );
+        CREATE VIRTUAL TABLE IF NOT EXISTS content_fts USING fts5(
+            body,
+            tokenize = 'unicode61 remove_diacritics 2'
+        );"
+    ).map_err(|e| format!("Failed to create schema: {}", e))?;
+
+    guard.insert(workspace.to_string(), conn);
+    Ok(())
+}
+
+fn collect_indexable_files(dir: &Path, ignore: &IgnoreMatcher, out: &mut Vec<PathBuf>) {
+    let read_dir = match fs::read_dir(dir) {
+        Ok(d) => d,
+        Err(_) => return,
+    };
+
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_dir = path.is_dir();
+        if is_skipped_entry(&name, is_dir) || ignore.is_ignored(&path) {
+            continue;
+        }
+
+        if is_dir {
+            collect_indexable_files(&path, ignore, out);
+        } else if is_searchable_text(&name) {
+            out.push(path);
+        }
+    }
+}
+
+/// Whether a path under `root` is one the walker would visit.
+fn is_indexable_location(root: &Path, path: &Path, ignore: &IgnoreMatcher) -> bool {
+    if ignore.is_ignored(path) {
+        return false;
+    }
+    let rel = match path.strip_prefix(root) {
+        Ok(r) => r,
+        Err(_) => return false,
+    };
+    let count = rel.components().count();
+    rel.components().enumerate().all(|(i, c)| {
+        let is_dir = i + 1 < count || path.is_dir();
+        !is_skipped_entry(&c.as_os_str().to_string_lossy(), is_dir)
+    })
+}
+
+/// Remove a path, and everything beneath it if it was a directory.
+fn remove_entries(conn: &Connection, path: &str) -> Result<usize, String> {
+    let prefix = format!("{}{}", path, std::path::MAIN_SEPARATOR);
+    conn.execute(
+        "DELETE FROM content_fts WHERE rowid IN (
+            SELECT id FROM indexed_files WHERE path = ?1 OR substr(path, 1, length(?2)) = ?2
+        )",
+        params![path, prefix],
+    ).map_err(|e| format!("Failed to remove index entries: {}", e))?;
+    conn.execute(
+        "DELETE FROM indexed_files WHERE path = ?1 OR substr(path, 1, length(?2)) = ?2",
+        params![path, prefix],
+    ).map_err(|e| format!("Failed to remove index entries: {}", e))
+}
+
+/// (Re)index a single file if its mtime or size changed. Returns true if it was written.
+fn index_file(conn: &Connection, path: &Path) -> Result<bool, String> {
+    let key = path.to_string_lossy().to_string();
+
+    let meta = match fs::metadata(path) {
+        Ok(m) if m.is_file() && m.len() <= MAX_INDEXED_FILE_SIZE => m,
+        _ => {
+            remove_entries(conn, &key)?;
+            return Ok(false);
+        }
+    };
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0);
+    let size = meta.len() as i64;
+
+    let existing: Option<(i64, i64, i64)> = conn
+        .query_row(
+            "SELECT id, modified, size FROM indexed_files WHERE path = ?1",
+            params![key],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Query failed: {}", e))?;
+
+    if let Some((_, m, s)) = existing {
+        if m == modified && s == size {
+            return Ok(false);
+        }
+    }
+
+    // Non-UTF-8 files are not searchable
+    let body = match fs::read_to_string(path) {
+        Ok(b) => b,
+        Err(_) => {
+            remove_entries(conn, &key)?;
+            return Ok(false);
+        }
+    };
+
+    let id = match existing {
+        Some((id, _, _)) => {
+            conn.execute(
+                "UPDATE indexed_files SET modified = ?1, size = ?2 WHERE id = ?3",
+                params![modified, size, id],
+            ).map_err(|e| format!("Failed to update index entry: {}", e))?;
+            conn.execute("DELETE FROM content_fts WHERE rowid = ?1", params![id])
+                .map_err(|e| format!("Failed to update index entry: {}", e))?;
+            id
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO indexed_files (path, modified, size) VALUES (?1, ?2, ?3)",
+                params![key, modified, size],
+            ).map_err(|e| format!("Failed to insert index entry: {}", e))?;
+            conn.last_insert_rowid()
+        }
+    };
+
+    conn.execute(
+        "INSERT INTO content_fts (rowid, body) VALUES (?1, ?2)",
+        params![id, body],
+    ).map_err(|e| format!("Failed to index file: {}", e))?;
+
+    Ok(true)
+}
+
+/// Apply watcher events to an open index. Indexes that haven't been opened this
+/// session are left alone — `search_index_build` reconciles them by mtime.
+pub fn update_paths(app: &tauri::AppHandle, workspace: &str, paths: &[PathBuf]) {
+    let state = app.state::<SearchIndexState>();
+    if !state.conns.lock().is_ok_and(|g| g.contains_key(workspace)) {
+        return;
+    }
+
+    let root = Path::new(workspace);
+    // Lock order: ignores, then conns
+    let mut ignores = match state.ignores.lock() {
+        Ok(g) => g,
+        Err(_) => return,
+    };
+    if paths.iter().any(|p| is_ignore_source(p)) {
+        ignores.remove(workspace);
+    }
+    let ignore = ignores
+        .entry(workspace.to_string())
+        .or_insert_with(|| IgnoreMatcher::new(root));
+
+    let guard = match state.conns.lock() {
+        Ok(g) => g,
+        Err(_) => return,
+    };
+    let conn = match guard.get(workspace) {
+        Some(c) => c,
+        None => return,
+    };
+
+    for path in paths {
+        if !is_indexable_location(root, path, ignore) {
+            continue;
+        }
+
+        let result = if path.is_dir() {
+            let mut files = Vec::new();
+            collect_indexable_files(path, ignore, &mut files);
+            files.iter().try_for_each(|f| index_file(conn, f).map(|_| ()))
+        } else if path.exists() {
+            let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            if is_searchable_text(&name) {
+                index_file(conn, path).map(|_| ())
+            } else {
+                Ok(())
+            }
+        } else {
+            remove_entries(conn, &path.to_string_lossy()).map(|_| ())
+        };
+
+        if let Err(e) = result {
+            eprintln!("[search-index] update failed for {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Turn user input into an FTS5 MATCH expression. Quoted segments become phrase
+/// queries, bare words are ANDed, and a trailing `*` keeps prefix matching.
+/// Everything is quoted so FTS5 operators in user text can't cause syntax errors.
+fn to_fts_query(query: &str) -> Option<String> {
+    let mut terms = Vec::new();
+    let mut chars = query.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let phrase: String = chars.by_ref().take_while(|&c| c != '"').collect();
+            if !phrase.trim().is_empty() {
+                terms.push(format!("\"{}\"", phrase.trim().replace('"', "\"\"")));
+            }
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            let (word, prefix) = match word.strip_suffix('*') {
+                Some(w) => (w.to_string(), true),
+                None => (word, false),
+            };
+            if !word.is_empty() {
+                terms.push(format!("\"{}\"{}", word.replace('"', "\"\""), if prefix { "*" } else { "" }));
+            }
+        }
+    }
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/// Strip the snippet markers and collapse whitespace, returning the clean text
+/// and the highlighted ranges as character offsets into it.
+fn extract_highlights(marked: &str) -> (String, Vec<crate::fs_commands::TextRange>) {
+    let mut text = String::with_capacity(marked.len());
+    let mut ranges = Vec::new();
+    let mut start = None;
+    let mut pos = 0;
+
+    for c in marked.chars() {
+        match c {
+            SNIPPET_START => start = Some(pos),
+            SNIPPET_END => {
+                if let Some(s) = start.take() {
+                    ranges.push(crate::fs_commands::TextRange { start: s, end: pos });
+                }
+            }
+            c if c.is_whitespace() => {
+                if !text.is_empty() && !text.ends_with(' ') {
+                    text.push(' ');
+                    pos += 1;
+                }
+            }
+            _ => {
+                text.push(c);
+                pos += 1;
+            }
+        }
+    }
+
+    (text, ranges)
+}
+
+#[derive(Serialize)]
+pub struct IndexStats {
+    pub indexed: usize,
+    pub removed: usize,
+    pub total: usize,
+}
+
+#[derive(Serialize, Clone)]
+pub struct IndexedSearchResult {
+    pub path: String,
+    pub name: String,
+    pub snippet: String,
+    pub highlights: Vec<crate::fs_commands::TextRange>,
+    pub score: f64,
+}
+
+/// Incrementally bring the workspace index up to date: new or changed files are
+/// (re)indexed, files that disappeared are dropped.
+/// The walk and file reads run on a blocking thread.
+#[tauri::command]
+pub async fn search_index_build(
+    app: tauri::AppHandle,
+    sandbox: tauri::State<'_, SandboxState>,
+    workspace: String,
+) -> Result<IndexStats, String> {
+    sandbox.check(&workspace)?;
+    tokio::task::spawn_blocking(move || build_index(&app.state::<SearchIndexState>(), &workspace))
+        .await
+        .map_err(|e| format!("Index build failed: {}", e))?
+}
+
+fn build_index(state: &SearchIndexState, workspace: &str) -> Result<IndexStats, String> {
+    ensure_connection(state, workspace)?;
+
+    let root = Path::new(workspace);
+    let ignore = IgnoreMatcher::new(root);
+    let mut files = Vec::new();
+    collect_indexable_files(root, &ignore, &mut files);
+    // A full build refreshes the matcher watcher updates use
+    state.ignores.lock().map_err(|e| e.to_string())?.insert(workspace.to_string(), ignore);
+
+    let mut guard = state.conns.lock().map_err(|e| e.to_string())?;
+    let conn = guard.get_mut(workspace).ok_or("Search index not initialized")?;
+    let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let mut indexed = 0;
+    for path in &files {
+        if index_file(&tx, path)? {
+            indexed += 1;
+        }
+    }
+
+    let current: HashSet<String> = files.iter().map(|p| p.to_string_lossy().to_string()).collect();
+    let stale: Vec<String> = {
+        let mut stmt = tx
+            .prepare("SELECT path FROM indexed_files")
+            .map_err(|e| format!("Prepare failed: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Query failed: {}", e))?;
+        rows.filter_map(Result::ok).filter(|p| !current.contains(p)).collect()
+    };
+    for path in &stale {
+        remove_entries(&tx, path)?;
+    }
+
+    tx.commit().map_err(|e| format!("Failed to commit index: {}", e))?;
+
+    Ok(IndexStats {
+        indexed,
+        removed: stale.len(),
+        total: files.len(),
+    })
+}
+
+/// Ranked full-text query. Supports `"exact phrases"` and `prefix*` terms.
+#[tauri::command]
+pub async fn search_index_query(
+    state: tauri::State<'_, SearchIndexState>,
+    sandbox: tauri::State<'_, SandboxState>,
+    workspace: String,
+    query: String,
+    max_results: usize,
+) -> Result<Vec<IndexedSearchResult>, String> {
+    sandbox.check(&workspace)?;
+    let fts_query = match to_fts_query(&query) {
+        Some(q) => q,
+        None => return Ok(Vec::new()),
+    };
+
+    ensure_connection(&state, &workspace)?;
+    let guard = state.conns.lock().map_err(|e| e.to_string())?;
+    let conn = guard.get(&workspace).ok_or("Search index not initialized")?;
+
+    let sql = format!(
+        "SELECT f.path, snippet(content_fts, 0, '{}', '{}', '…', 16), bm25(content_fts)
+         FROM content_fts JOIN indexed_files f ON f.id = content_fts.rowid
+         WHERE content_fts MATCH ?1
+         ORDER BY bm25(content_fts) LIMIT ?2",
+        SNIPPET_START, SNIPPET_END
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| format!("Prepare failed: {}", e))?;
+    let mut rows = stmt
+        .query(params![fts_query, max_results as i64])
+        .map_err(|e| format!("Query failed: {}", e))?;
+
+    let mut results = Vec::new();
+    while let Some(row) = rows.next().map_err(|e| format!("Row error: {}", e))? {
+        let path: String = row.get(0).map_err(|e| format!("Column error: {}", e))?;
+        let marked: String = row.get(1).map_err(|e| format!("Column error: {}", e))?;
+        let bm25: f64 = row.get(2).map_err(|e| format!("Column error: {}", e))?;
+
+        let (snippet, highlights) = extract_highlights(&marked);
+        let name = Path::new(&path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        results.push(IndexedSearchResult {
+            path,
+            name,
+            snippet,
+            highlights,
+            // bm25() is lower-is-better; flip it so higher scores rank first
+            score: -bm25,
+        });
+    }
+
+    Ok(results)
+}
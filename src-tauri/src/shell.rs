@@ -0,0 +1,301 @@
+use crate::command_policy::{self, PolicyDecision};
+use crate::sandbox::SandboxState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+
+pub struct ShellSession {
+    pub kill_tx: tokio::sync::watch::Sender<bool>,
+}
+
+pub struct ShellState {
+    pub sessions: Mutex<HashMap<String, ShellSession>>,
+}
+
+impl Default for ShellState {
+    fn default() -> Self {
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct ShellOptions {
+    /// Kill the command after this many seconds
+    pub timeout_secs: Option<u64>,
+    /// Whose command policy applies; defaults to the sandbox root containing `cwd`
+    pub workspace: Option<String>,
+    /// The user confirmed an `ask` decision
+    pub approved: bool,
+    /// Emit at most this many characters per stream (default
+    /// `DEFAULT_MAX_OUTPUT_CHARS`); the rest is read and dropped
+    pub max_output_chars: Option<usize>,
+}
+
+/// A command the policy did not let through.
+#[derive(Serialize)]
+pub struct PolicyBlock {
+    /// Always `"policy"`
+    pub kind: &'static str,
+    pub decision: PolicyDecision,
+}
+
+/// Spawn errors serialize as a plain string; policy blocks as a `PolicyBlock`.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum ShellError {
+    Io(String),
+    Policy(PolicyBlock),
+}
+
+#[derive(Serialize, Clone)]
+struct ShellOutput {
+    /// "stdout" or "stderr"
+    stream: &'static str,
+    data: String,
+}
+
+#[derive(Serialize, Clone)]
+struct ShellDone {
+    session_id: String,
+    /// `None` when the process was ended by a signal
+    exit_code: Option<i32>,
+    timed_out: bool,
+    killed: bool,
+    /// Some output was dropped at the `max_output_chars` cap
+    truncated: bool,
+}
+
+/// How long to keep reading output after the process exits. A backgrounded
+/// grandchild can hold the pipes open indefinitely.
+const DRAIN_GRACE: Duration = Duration::from_secs(2);
+/// Per-stream output cap when `max_output_chars` is not given, so a runaway
+/// command can't flood the IPC channel.
+const DEFAULT_MAX_OUTPUT_CHARS: usize = 10_000_000;
+
+fn shell_command(command: &str) -> Command {
+    #[cfg(unix)]
+    {
+        let mut cmd = Command::new("bash");
+        cmd.args(["-c", command]);
+        // Own process group, so kill takes down everything the command started
+        cmd.process_group(0);
+        cmd
+    }
+    #[cfg(windows)]
+    {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    }
+}
+
+fn kill_tree(pid: Option<u32>) {
+    let Some(pid) = pid else { return };
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(-(pid as i32), libc::SIGKILL);
+    }
+    #[cfg(windows)]
+    {
+        let _ = std::process::Command::new("taskkill")
+            .args(["/T", "/F", "/PID", &pid.to_string()])
+            .output();
+    }
+}
+
+/// Decode as much of `pending` as is valid UTF-8, keeping an incomplete
+/// trailing character for the next read.
+fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let valid = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => {
+            let text = String::from_utf8_lossy(pending).to_string();
+            pending.clear();
+            return text;
+        }
+    };
+    let rest = pending.split_off(valid);
+    let text = String::from_utf8_lossy(pending).to_string();
+    *pending = rest;
+    text
+}
+
+/// Emit `reader`'s output as `event` until `max_chars` characters have been
+/// sent. Past the cap the output is still read, so the process never blocks on
+/// a full pipe, but it is dropped and `truncated` is set.
+fn forward_output<R>(
+    app: tauri::AppHandle,
+    event: String,
+    stream: &'static str,
+    mut reader: R,
+    max_chars: usize,
+    truncated: Arc<AtomicBool>,
+) -> tokio::task::JoinHandle<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut buf = [0u8; 8192];
+        let mut pending = Vec::new();
+        let mut room = max_chars;
+        let mut emit = |data: String| {
+            let len = data.chars().count();
+            if len > room {
+                truncated.store(true, Ordering::Relaxed);
+            }
+            let data: String = if len > room { data.chars().take(room).collect() } else { data };
+            room -= len.min(room);
+            if !data.is_empty() {
+                let _ = app.emit(&event, ShellOutput { stream, data });
+            }
+        };
+        loop {
+            match reader.read(&mut buf).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    pending.extend_from_slice(&buf[..n]);
+                    emit(take_utf8(&mut pending));
+                }
+            }
+        }
+        if !pending.is_empty() {
+            emit(String::from_utf8_lossy(&pending).to_string());
+        }
+    })
+}
+
+/// Run `command` in a shell and stream its output. Emits, per session:
+///   shell-output-{session_id} → { stream: "stdout" | "stderr", data }
+///   shell-done-{session_id}   → { session_id, exit_code, timed_out, killed }
+/// Listeners must be registered before invoking. The session is dropped
+/// automatically once `shell-done` has been emitted; a `session_id` that is
+/// still running is refused.
+///
+/// The command is first checked against the command policy of `workspace`
+/// (or of the workspace containing `cwd`) and the decision is logged. `deny` is
+/// always refused; `ask` is refused unless `approved` says the user confirmed it.
+#[tauri::command]
+pub async fn shell_spawn(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, ShellState>,
+    sandbox: tauri::State<'_, SandboxState>,
+    session_id: String,
+    cwd: String,
+    command: String,
+    options: Option<ShellOptions>,
+) -> Result<(), ShellError> {
+    let ShellOptions { timeout_secs, workspace, approved, max_output_chars } = options.unwrap_or_default();
+    sandbox.check(&cwd).map_err(ShellError::Io)?;
+    command_policy::gate(&sandbox, workspace, &cwd, &command, approved)
+        .map_err(|decision| ShellError::Policy(PolicyBlock { kind: "policy", decision }))?;
+
+    // Claim the id before spawning, so a second spawn with the same id can't
+    // replace this session's kill switch
+    let (kill_tx, mut kill_rx) = tokio::sync::watch::channel(false);
+    {
+        let mut sessions = state.sessions.lock().unwrap();
+        if sessions.contains_key(&session_id) {
+            return Err(ShellError::Io(format!("Session {} is already running", session_id)));
+        }
+        sessions.insert(session_id.clone(), ShellSession { kill_tx });
+    }
+
+    let spawned = shell_command(&command)
+        .current_dir(&cwd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            state.sessions.lock().unwrap().remove(&session_id);
+            return Err(ShellError::Io(format!("Failed to start command: {}", e)));
+        }
+    };
+    let pid = child.id();
+
+    let output_event = format!("shell-output-{}", session_id);
+    let done_event = format!("shell-done-{}", session_id);
+
+    let max_chars = max_output_chars.unwrap_or(DEFAULT_MAX_OUTPUT_CHARS);
+    let truncated = Arc::new(AtomicBool::new(false));
+    let mut readers = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        readers.push(forward_output(app.clone(), output_event.clone(), "stdout", stdout, max_chars, truncated.clone()));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        readers.push(forward_output(app.clone(), output_event, "stderr", stderr, max_chars, truncated.clone()));
+    }
+
+    tokio::spawn(async move {
+        let timeout = async {
+            match timeout_secs {
+                Some(secs) => tokio::time::sleep(Duration::from_secs(secs)).await,
+                None => std::future::pending().await,
+            }
+        };
+        let kill_requested = async {
+            while kill_rx.changed().await.is_ok() {
+                if *kill_rx.borrow() {
+                    return;
+                }
+            }
+            std::future::pending::<()>().await
+        };
+
+        let (status, timed_out, killed) = tokio::select! {
+            status = child.wait() => (status.ok(), false, false),
+            _ = timeout => {
+                kill_tree(pid);
+                (child.wait().await.ok(), true, false)
+            }
+            _ = kill_requested => {
+                kill_tree(pid);
+                (child.wait().await.ok(), false, true)
+            }
+        };
+
+        for reader in readers {
+            let abort = reader.abort_handle();
+            if tokio::time::timeout(DRAIN_GRACE, reader).await.is_err() {
+                abort.abort();
+            }
+        }
+
+        let _ = app.emit(
+            &done_event,
+            ShellDone {
+                session_id: session_id.clone(),
+                exit_code: status.and_then(|s| s.code()),
+                timed_out,
+                killed,
+                truncated: truncated.load(Ordering::Relaxed),
+            },
+        );
+        app.state::<ShellState>().sessions.lock().unwrap().remove(&session_id);
+    });
+
+    Ok(())
+}
+
+/// Kill a running command and everything it started.
+#[tauri::command]
+pub async fn shell_kill(state: tauri::State<'_, ShellState>, session_id: String) -> Result<(), String> {
+    let sessions = state.sessions.lock().unwrap();
+    if let Some(session) = sessions.get(&session_id) {
+        let _ = session.kill_tx.send(true);
+    }
+    Ok(())
+}
@@ -0,0 +1,265 @@
+use serde::{Deserialize, Serialize};
+use crate::sandbox::SandboxState;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// One trashed item. On disk it lives at `.shoulders/trash/<id>/item/<name>`,
+/// with this record as `.shoulders/trash/<id>/meta.json`. The item gets its own
+/// folder so a trashed file called `meta.json` can't overwrite the record.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TrashEntry {
+    pub id: String,
+    pub name: String,
+    /// Original location, relative to the workspace root
+    pub original_path: String,
+    pub is_dir: bool,
+    /// Unix seconds
+    pub deleted_at: u64,
+    /// Bytes, summed over a directory's files
+    pub size: u64,
+}
+
+/// `TrashEntry` as returned to the frontend, with absolute paths resolved.
+#[derive(Serialize, Clone)]
+pub struct TrashItem {
+    #[serde(flatten)]
+    pub entry: TrashEntry,
+    pub path: String,
+}
+
+const META_FILE: &str = "meta.json";
+const ITEM_DIR: &str = "item";
+
+fn trash_dir(workspace: &Path) -> PathBuf {
+    workspace.join(".shoulders").join("trash")
+}
+
+/// Nearest ancestor of `path` that has a `.shoulders` folder.
+fn find_workspace(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .find(|a| a.join(".shoulders").is_dir())
+        .map(Path::to_path_buf)
+}
+
+/// Symlinks count as themselves and are never followed, so a link to a folder
+/// outside the workspace is neither measured nor deleted through.
+fn is_real_dir(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|m| m.is_dir())
+}
+
+fn total_size(path: &Path) -> u64 {
+    if is_real_dir(path) {
+        fs::read_dir(path)
+            .map(|rd| rd.flatten().map(|e| total_size(&e.path())).sum())
+            .unwrap_or(0)
+    } else {
+        fs::symlink_metadata(path).map(|m| m.len()).unwrap_or(0)
+    }
+}
+
+fn remove_any(path: &Path) -> std::io::Result<()> {
+    if is_real_dir(path) {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Rename, falling back to copy + delete when the source is on another device.
+fn move_path(src: &Path, dest: &Path) -> std::io::Result<()> {
+    match fs::rename(src, dest) {
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {}
+        result => return result,
+    }
+    if is_real_dir(src) {
+        crate::fs_commands::copy_dir_recursive(src, dest)?;
+    } else {
+        fs::copy(src, dest)?;
+    }
+    remove_any(src)
+}
+
+/// Ids are generated hex uuids; reject anything else so an id can't name a path.
+fn check_id(id: &str) -> Result<(), String> {
+    if !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        Ok(())
+    } else {
+        Err(format!("Invalid trash id: {}", id))
+    }
+}
+
+/// Whether `path` is non-empty and made only of plain names (no `..`, root or prefix).
+fn is_plain_relative(path: &str) -> bool {
+    let path = Path::new(path);
+    path.components().next().is_some() && path.components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// meta.json lives inside the workspace, where anything can edit it; only
+/// accept a plain relative `original_path` and a single-component `name`.
+fn check_entry(entry: &TrashEntry) -> Result<(), String> {
+    if !is_plain_relative(&entry.original_path) {
+        return Err(format!("Invalid original path in trash entry: {}", entry.original_path));
+    }
+    if !is_plain_relative(&entry.name) || Path::new(&entry.name).components().count() != 1 {
+        return Err(format!("Invalid name in trash entry: {}", entry.name));
+    }
+    Ok(())
+}
+
+fn to_item(workspace: &Path, entry: TrashEntry) -> TrashItem {
+    let path = workspace.join(&entry.original_path).to_string_lossy().to_string();
+    TrashItem { entry, path }
+}
+
+/// Move `path` into its workspace's trash. Items already in the trash, or
+/// outside any workspace, cannot be trashed.
+pub(crate) fn move_to_trash(path: &Path) -> Result<TrashItem, String> {
+    if !path.exists() {
+        return Err(format!("No such file or directory: {}", path.display()));
+    }
+    let workspace = find_workspace(path).ok_or("Not inside a workspace; use a hard delete instead")?;
+    let trash = trash_dir(&workspace);
+    if path.starts_with(&trash) {
+        return Err("Already in the trash".to_string());
+    }
+
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or("Cannot trash this path")?;
+    let original_path = path
+        .strip_prefix(&workspace)
+        .map_err(|e| e.to_string())?
+        .to_string_lossy()
+        .to_string();
+
+    let entry = TrashEntry {
+        id: uuid::Uuid::new_v4().simple().to_string(),
+        name: name.clone(),
+        original_path,
+        is_dir: is_real_dir(path),
+        deleted_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        size: total_size(path),
+    };
+
+    let slot = trash.join(&entry.id);
+    fs::create_dir_all(slot.join(ITEM_DIR)).map_err(|e| e.to_string())?;
+    let meta = serde_json::to_string_pretty(&entry).map_err(|e| e.to_string())?;
+    fs::write(slot.join(META_FILE), meta).map_err(|e| e.to_string())?;
+
+    if let Err(e) = move_path(path, &slot.join(ITEM_DIR).join(&name)) {
+        let _ = fs::remove_dir_all(&slot);
+        return Err(format!("Failed to move to trash: {}", e));
+    }
+
+    Ok(to_item(&workspace, entry))
+}
+
+fn read_entry(slot: &Path) -> Option<TrashEntry> {
+    let content = fs::read_to_string(slot.join(META_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// `path` if free, otherwise `name (restored)`, `name (restored 2)`, ...
+fn free_destination(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+    let parent = path.parent().unwrap_or_else(|| Path::new(""));
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+
+    (1..)
+        .map(|n| {
+            let suffix = if n == 1 { "restored".to_string() } else { format!("restored {}", n) };
+            parent.join(format!("{} ({}){}", stem, suffix, ext))
+        })
+        .find(|p| !p.exists())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Trashed items in the workspace, most recently deleted first.
+#[tauri::command]
+pub async fn trash_list(sandbox: tauri::State<'_, SandboxState>, workspace: String) -> Result<Vec<TrashItem>, String> {
+    sandbox.check(&workspace)?;
+    let root = Path::new(&workspace);
+    let read_dir = match fs::read_dir(trash_dir(root)) {
+        Ok(rd) => rd,
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    let mut items: Vec<TrashItem> = read_dir
+        .flatten()
+        .filter_map(|e| read_entry(&e.path()))
+        .map(|entry| to_item(root, entry))
+        .collect();
+    items.sort_by_key(|i| std::cmp::Reverse(i.entry.deleted_at));
+    Ok(items)
+}
+
+/// Move a trashed item back to where it was, recreating missing parent folders.
+/// If something now occupies that path, the item is restored next to it under a
+/// `(restored)` name. Returns the restored path.
+#[tauri::command]
+pub async fn trash_restore(
+    sandbox: tauri::State<'_, SandboxState>,
+    workspace: String,
+    id: String,
+) -> Result<String, String> {
+    sandbox.check(&workspace)?;
+    check_id(&id)?;
+    let root = Path::new(&workspace);
+    let slot = trash_dir(root).join(&id);
+    let entry = read_entry(&slot).ok_or_else(|| format!("Trash item not found: {}", id))?;
+    check_entry(&entry)?;
+
+    let dest = free_destination(&root.join(&entry.original_path));
+    sandbox.check_write(&dest.to_string_lossy())?;
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    move_path(&slot.join(ITEM_DIR).join(&entry.name), &dest).map_err(|e| format!("Failed to restore: {}", e))?;
+    let _ = fs::remove_dir_all(&slot);
+
+    Ok(dest.to_string_lossy().to_string())
+}
+
+/// Permanently delete the given trashed items, or the whole trash if `ids` is omitted.
+/// Returns the number of items removed.
+#[tauri::command]
+pub async fn trash_purge(
+    sandbox: tauri::State<'_, SandboxState>,
+    workspace: String,
+    ids: Option<Vec<String>>,
+) -> Result<usize, String> {
+    sandbox.check(&workspace)?;
+    let trash = trash_dir(Path::new(&workspace));
+    let slots: Vec<PathBuf> = match ids {
+        Some(ids) => {
+            ids.iter().try_for_each(|id| check_id(id))?;
+            ids.iter().map(|id| trash.join(id)).collect()
+        }
+        None => match fs::read_dir(&trash) {
+            Ok(rd) => rd.flatten().map(|e| e.path()).collect(),
+            Err(_) => return Ok(0),
+        },
+    };
+
+    let mut removed = 0;
+    for slot in slots {
+        // Only slot folders written by move_to_trash
+        if !slot.join(META_FILE).is_file() {
+            continue;
+        }
+        fs::remove_dir_all(&slot).map_err(|e| e.to_string())?;
+        removed += 1;
+    }
+    Ok(removed)
+}
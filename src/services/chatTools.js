@@ -1,4 +1,5 @@
 import { invoke } from '@tauri-apps/api/core'
+import { ask } from '@tauri-apps/plugin-dialog'
 import { tool } from 'ai'
 import { z } from 'zod'
 import { useReviewsStore } from '../stores/reviews'
@@ -8,6 +9,7 @@ import { nanoid } from '../stores/utils'
 import { extractDocumentText, extractBlockList } from './docxContext'
 import { SHOULDERS_SEARCH_URL } from './apiClient'
 import { isMultimodalImage, isPdf, getMimeType } from '../utils/fileTypes'
+import { runShellCommand } from './shellRunner'
 
 // External tools that transmit data to third-party services
 export const EXTERNAL_TOOLS = ['web_search', 'search_papers', 'fetch_url', 'add_reference']
@@ -267,8 +269,33 @@ async function _callOpenAlex(query, numResults, workspace) {
 }
 
 
+/**
+ * Format a fetch_url_content result ({ url, final_url, content_type, title, pages, content,
+ * citation }) for the model, in the same shape as the Exa results.
+ */
+function _formatFetchedPage(page) {
+  const parts = [`## ${page.title || 'Untitled'}`, `URL: ${page.final_url || page.url}`]
+  if (page.final_url && page.final_url !== page.url) parts.push(`Redirected from: ${page.url}`)
+  if (page.content_type !== 'text/html') {
+    parts.push(`Type: ${page.content_type}${page.pages ? ` (${page.pages} pages)` : ''}`)
+  }
+  const c = page.citation
+  if (c?.authors?.length) parts.push(`Authors: ${c.authors.join('; ')}`)
+  if (c?.journal) parts.push(`Journal: ${c.journal}`)
+  if (c?.date) parts.push(`Published: ${c.date}`)
+  if (c?.doi) parts.push(`DOI: ${c.doi}`)
+  if (c?.pdf_url) parts.push(`PDF: ${c.pdf_url}`)
+  parts.push('', page.content || 'No content available')
+  return parts.join('\n')
+}
+
 // ─── AI SDK Tools ────────────────────────────────────────────────────
 
+// run_command kills anything still running after this long
+const RUN_COMMAND_TIMEOUT_SECS = 600
+// and keeps at most this much of its output
+const RUN_COMMAND_MAX_OUTPUT = 100_000
+
 /**
  * Get all AI SDK tools for the workspace.
  * Returns an object of { toolName: tool({ ... }) } that AI SDK can consume directly.
@@ -288,8 +315,36 @@ export function getAiTools(workspace) {
       inputSchema: z.object({
         command: z.string().describe('The bash command to execute'),
       }),
-      execute: async ({ command }) => {
-        return await invoke('run_shell_command', { cwd: workspace.path, command })
+      execute: async ({ command }, { abortSignal } = {}) => {
+        const run = (approved) => runShellCommand(workspace.path, command, {
+          timeoutSecs: RUN_COMMAND_TIMEOUT_SECS,
+          workspace: workspace.path,
+          approved,
+          signal: abortSignal,
+          maxOutputChars: RUN_COMMAND_MAX_OUTPUT,
+        })
+
+        let res
+        try {
+          res = await run(false)
+        } catch (e) {
+          if (e?.kind !== 'policy') throw e
+          const { action, reason } = e.decision
+          if (action === 'deny') return `Blocked by command policy: ${reason}`
+          const yes = await ask(`The AI wants to run:\n\n${command}\n\n(${reason})`, { title: 'Allow Command?', kind: 'warning' })
+          if (!yes) return `Command rejected by user (${reason}).`
+          res = await run(true)
+        }
+
+        let result = res.stdout
+        if (res.stderr) result += '\n--- stderr ---\n' + res.stderr
+        if (res.truncated || result.length > RUN_COMMAND_MAX_OUTPUT) {
+          result = result.slice(0, RUN_COMMAND_MAX_OUTPUT) + '\n... [truncated at 100KB]'
+        }
+        if (res.timedOut) result += `\n[killed after ${RUN_COMMAND_TIMEOUT_SECS}s timeout]`
+        else if (res.killed) result += '\n[killed]'
+        else if (res.exitCode !== 0) result += `\n[exit code ${res.exitCode ?? 'unknown'}]`
+        return result
       },
     }),
 
@@ -867,18 +922,17 @@ export function getAiTools(workspace) {
         // Fallback: direct fetch with timeout
         const fetchWithTimeout = (url) => Promise.race([
           invoke('fetch_url_content', { url }),
-          new Promise((_, reject) => setTimeout(() => reject(new Error('Fetch timed out after 15s')), 15000)),
+          new Promise((_, reject) => setTimeout(() => reject(new Error('Fetch timed out after 30s')), 30000)),
         ])
 
         if (urls.length === 1) {
-          try { return await fetchWithTimeout(urls[0]) }
+          try { return _formatFetchedPage(await fetchWithTimeout(urls[0])) }
           catch (e) { return `Error fetching ${urls[0]}: ${e.message || e}` }
         }
         const results = []
         for (const url of urls) {
           try {
-            const text = await fetchWithTimeout(url)
-            results.push(`## ${url}\n${text}`)
+            results.push(_formatFetchedPage(await fetchWithTimeout(url)))
           } catch (e) {
             results.push(`## ${url}\nError: ${e}`)
           }
@@ -0,0 +1,61 @@
+/**
+ * Streaming file downloads via Rust's download_to_file / download_cancel (download.rs).
+ *
+ * Events per download:
+ *   download-progress-{downloadId} → { downloaded, total }  (total is null when unknown)
+ *
+ * The listener is registered BEFORE invoke('download_to_file') so small files
+ * can't finish before anyone is listening.
+ */
+
+import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
+
+let _counter = 0
+
+/**
+ * Download a URL to a file. The host must be allowlisted (Settings > Models >
+ * Advanced). An interrupted or cancelled download leaves `{path}.part`, which
+ * the next call for the same URL and path resumes.
+ *
+ * @param {string} url
+ * @param {string} path - Absolute target path; parent folders are created
+ * @param {object} [options]
+ * @param {string} [options.sha256] - Expected hex digest; a mismatch deletes the file and rejects
+ * @param {Record<string, string>} [options.headers] - Extra request headers
+ * @param {boolean} [options.overwrite] - Replace an existing file
+ * @param {boolean} [options.restart] - Discard a partial download instead of resuming it
+ * @param {(downloaded: number, total: number|null) => void} [options.onProgress]
+ * @param {AbortSignal} [options.signal] - Aborting cancels the download
+ * @returns {Promise<{ path: string, bytes: number, sha256: string, resumed_from: number, final_url: string, content_type: string|null }>}
+ */
+export async function downloadToFile(url, path, { sha256, headers, overwrite, restart, onProgress, signal } = {}) {
+  const downloadId = `dl-${++_counter}-${Date.now()}`
+
+  if (signal?.aborted) {
+    throw new DOMException('The operation was aborted.', 'AbortError')
+  }
+
+  const unlisten = await listen(`download-progress-${downloadId}`, (event) => {
+    onProgress?.(event.payload.downloaded, event.payload.total)
+  })
+  const cancel = () => invoke('download_cancel', { downloadId }).catch(() => {})
+  signal?.addEventListener('abort', cancel, { once: true })
+
+  try {
+    return await invoke('download_to_file', {
+      downloadId,
+      url,
+      path,
+      options: {
+        sha256: sha256 ?? null,
+        headers: headers ?? {},
+        overwrite: !!overwrite,
+        restart: !!restart,
+      },
+    })
+  } finally {
+    signal?.removeEventListener('abort', cancel)
+    try { unlisten() } catch {}
+  }
+}
@@ -8,12 +8,61 @@ export async function gitAdd(repoPath) {
   return invoke('git_add_all', { repoPath })
 }
 
-export async function gitCommit(repoPath, message) {
-  return invoke('git_commit', { repoPath, message })
+/**
+ * Stage or unstage whole files/folders (absolute or repo-relative paths).
+ */
+export async function gitStagePaths(repoPath, paths) {
+  return invoke('git_stage_paths', { repoPath, paths })
+}
+
+export async function gitUnstagePaths(repoPath, paths) {
+  return invoke('git_unstage_paths', { repoPath, paths })
 }
 
-export async function gitStatus(repoPath) {
-  return invoke('git_status', { repoPath })
+/**
+ * Hunks of a file's unstaged changes, or staged ones with staged = true.
+ * @returns {Promise<{path: string, binary: boolean, hunks: Array<{header: string, old_start: number, old_lines: number, new_start: number, new_lines: number, lines: Array<{origin: string, content: string, old_lineno: number|null, new_lineno: number|null}>}>}>}
+ */
+export async function gitFileHunks(repoPath, path, staged = false) {
+  return invoke('git_file_hunks', { repoPath, path, staged })
+}
+
+/**
+ * Stage parts of a file. selection: [{ hunk: index, lines?: [line indices] }]
+ * as returned by gitFileHunks(…, false); omit lines for the whole hunk.
+ */
+export async function gitStageHunks(repoPath, path, selection) {
+  return invoke('git_stage_hunks', { repoPath, path, selection })
+}
+
+/**
+ * Unstage parts of a file; indices come from gitFileHunks(…, true).
+ */
+export async function gitUnstageHunks(repoPath, path, selection) {
+  return invoke('git_unstage_hunks', { repoPath, path, selection })
+}
+
+/**
+ * Commit the index as the configured user.name/user.email.
+ * @param {object} [options]
+ * @param {string} [options.author_name] - Author override (committer stays the configured identity)
+ * @param {string} [options.author_email]
+ * @param {string[]} [options.co_authors] - 'Name <email>' entries, added as Co-authored-by trailers
+ * @param {boolean} [options.sign] - Sign with the configured gpg/ssh program; unset follows commit.gpgsign
+ * @returns {Promise<string>} commit hash
+ */
+export async function gitCommit(repoPath, message, options = {}) {
+  return invoke('git_commit', { repoPath, message, options })
+}
+
+/**
+ * Changed paths; an empty array means a clean tree. `index` and `worktree` are
+ * 'added' | 'modified' | 'deleted' | 'renamed' | 'typechange' | null, and
+ * worktree is 'untracked' for new files.
+ * @returns {Promise<Array<{path: string, orig_path: string|null, index: string|null, worktree: string|null, conflicted: boolean, ignored: boolean}>>}
+ */
+export async function gitStatus(repoPath, includeIgnored = false) {
+  return invoke('git_status', { repoPath, includeIgnored })
 }
 
 export async function gitBranch(repoPath) {
@@ -24,6 +73,47 @@ export async function gitBranch(repoPath) {
   }
 }
 
+// ── Branches ──
+
+/**
+ * List branches, local first.
+ * @returns {Promise<Array<{name: string, remote: string|null, is_head: boolean, upstream: string|null, hash: string, date: string, message: string}>>}
+ */
+export async function gitBranchList(repoPath, includeRemote = false) {
+  return invoke('git_branch_list', { repoPath, includeRemote })
+}
+
+/**
+ * Create a branch at startPoint (branch, `origin/branch` or commit; HEAD if null).
+ */
+export async function gitBranchCreate(repoPath, name, startPoint = null, checkout = false) {
+  return invoke('git_branch_create', { repoPath, name, startPoint, checkout })
+}
+
+/**
+ * Switch branches. Rejects with `{ kind: 'blocked', branch, files }` when
+ * uncommitted changes to `files` would be overwritten; other errors are strings.
+ */
+export async function gitCheckoutBranch(repoPath, name) {
+  return invoke('git_checkout_branch', { repoPath, name })
+}
+
+export async function gitBranchRename(repoPath, oldName, newName, force = false) {
+  return invoke('git_branch_rename', { repoPath, oldName, newName, force })
+}
+
+export async function gitBranchDelete(repoPath, name, force = false) {
+  return invoke('git_branch_delete', { repoPath, name, force })
+}
+
+export async function gitBranchSetUpstream(repoPath, name, upstream) {
+  return invoke('git_branch_set_upstream', { repoPath, name, upstream })
+}
+
+export async function gitBranchDeleteRemote(repoPath, remote, name, token) {
+  return invoke('git_branch_delete_remote', { repoPath, remote, name, token })
+}
+
 /**
  * Get commit log, optionally filtered by file.
  * @returns {Promise<Array<{hash: string, date: string, message: string}>>}
@@ -110,8 +200,37 @@ export async function gitPullFf(repoPath, remote, branch, token) {
   return invoke('git_pull_ff', { repoPath, remote, branch, token })
 }
 
-export async function gitMergeRemote(repoPath, remote, branch) {
-  return invoke('git_merge_remote', { repoPath, remote, branch })
+/**
+ * Merge remote/branch into HEAD. By default conflicts abort the merge and
+ * reject with "CONFLICT: …". With keepConflicts, resolves to
+ * { status: 'conflicts', conflicts: [{ path, base, ours, theirs, binary }] }
+ * and leaves the repo merging; otherwise { status: 'merged', commit }.
+ * `sign` signs the merge commit; unset follows commit.gpgsign.
+ */
+export async function gitMergeRemote(repoPath, remote, branch, keepConflicts = false, sign = null) {
+  return invoke('git_merge_remote', { repoPath, remote, branch, keepConflicts, sign })
+}
+
+/**
+ * Returns { merging: boolean, conflicts: [{ path, base, ours, theirs, binary }] }
+ */
+export async function gitMergeState(repoPath) {
+  return invoke('git_merge_state', { repoPath })
+}
+
+/**
+ * Resolve one conflicted file: resolution is 'ours', 'theirs' or 'custom' (with content).
+ */
+export async function gitMergeResolve(repoPath, path, resolution, content = null) {
+  return invoke('git_merge_resolve', { repoPath, path, resolution, content })
+}
+
+export async function gitMergeContinue(repoPath, message = null) {
+  return invoke('git_merge_continue', { repoPath, message })
+}
+
+export async function gitMergeAbort(repoPath) {
+  return invoke('git_merge_abort', { repoPath })
 }
 
 export async function gitSetUser(repoPath, name, email) {
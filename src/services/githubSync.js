@@ -80,7 +80,7 @@ async function githubApi(endpoint, { method = 'GET', body = null, token } = {})
   }
   if (body) headers['Content-Type'] = 'application/json'
 
-  const result = await invoke('proxy_api_call', {
+  const response = await invoke('proxy_api_request', {
     request: {
       url: `https://api.github.com${endpoint}`,
       method,
@@ -89,13 +89,14 @@ async function githubApi(endpoint, { method = 'GET', body = null, token } = {})
     },
   })
 
-  const parsed = JSON.parse(result)
-  if (parsed.message) {
-    throw new Error(parsed.message)
-  }
-  if (parsed.errors) {
+  // DELETE and some PUTs answer 204 No Content
+  const parsed = response.body ? JSON.parse(response.body) : null
+  if (parsed?.errors) {
     throw new Error(parsed.errors.map(e => e.message).join(', '))
   }
+  if (!response.ok || parsed?.message) {
+    throw new Error(parsed?.message || `GitHub API error ${response.status}`)
+  }
   return parsed
 }
 
@@ -196,7 +197,8 @@ export async function fetchAndPull(repoPath, token) {
 
     // Both ahead and behind — try auto-merge before escalating
     try {
-      await gitMergeRemote(repoPath, 'origin', branch)
+      // Background merge: never sign, so a pinentry prompt can't stall sync
+      await gitMergeRemote(repoPath, 'origin', branch, false, false)
       markSynced()
       return { pulled: true, ahead: status.ahead, behind: status.behind }
     } catch (mergeErr) {
@@ -313,7 +315,7 @@ export async function syncNow(repoPath, token) {
     } else {
       // Both ahead and behind — try auto-merge
       try {
-        await gitMergeRemote(repoPath, 'origin', branch)
+        await gitMergeRemote(repoPath, 'origin', branch, false, false)
         // Merge succeeded! Now push the merge commit
       } catch (e) {
         const msg = String(e)
@@ -154,6 +154,9 @@ export function openalexToCsl(work) {
   const abstract_ = work.abstract || reconstructAbstract(work.abstract_inverted_index)
   if (abstract_) csl.abstract = abstract_
 
+  // Direct link to the open-access PDF, used by Download PDF
+  if (work.best_oa_location?.pdf_url) csl._pdfUrl = work.best_oa_location.pdf_url
+
   // URL (prefer OA URL, fall back to DOI)
   if (work.open_access?.oa_url) {
     csl.URL = work.open_access.oa_url
@@ -0,0 +1,98 @@
+/**
+ * Streaming shell commands via Rust's shell_spawn / shell_kill (shell.rs).
+ *
+ * Events per session:
+ *   shell-output-{sessionId} → { stream: 'stdout' | 'stderr', data: string }
+ *   shell-done-{sessionId}   → { session_id, exit_code, timed_out, killed, truncated }
+ *
+ * As with tauriFetch, listeners are registered BEFORE invoke('shell_spawn')
+ * so fast commands can't finish before anyone is listening.
+ */
+
+import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
+
+let _counter = 0
+
+/**
+ * Run a command and resolve when it exits.
+ *
+ * @param {string} cwd
+ * @param {string} command
+ * @param {object} [options]
+ * @param {number} [options.timeoutSecs] - Kill the command after this many seconds
+ * @param {string} [options.workspace] - Check the command against this workspace's command policy
+ * @param {boolean} [options.approved] - The user confirmed a policy `ask` decision
+ * @param {(stream: string, data: string) => void} [options.onOutput] - Called for each output chunk
+ * @param {AbortSignal} [options.signal] - Aborting kills the command
+ * @param {number} [options.maxOutputChars] - Keep at most this many characters of each stream; the rest is dropped as it arrives.
+ *   Also sent to Rust, which stops emitting past the cap (it always caps at 10M characters)
+ * @returns {Promise<{ stdout: string, stderr: string, exitCode: number|null, timedOut: boolean, killed: boolean, truncated: boolean }>}
+ */
+export async function runShellCommand(cwd, command, { timeoutSecs, workspace, approved, onOutput, signal, maxOutputChars = Infinity } = {}) {
+  const sessionId = `sh-${++_counter}-${Date.now()}`
+  let stdout = ''
+  let stderr = ''
+  let truncated = false
+  const unlisteners = []
+
+  function _cleanup() {
+    for (const unlisten of unlisteners) {
+      try { unlisten() } catch {}
+    }
+    unlisteners.length = 0
+  }
+
+  let resolveDone
+  const done = new Promise((resolve) => { resolveDone = resolve })
+
+  unlisteners.push(await listen(`shell-output-${sessionId}`, (event) => {
+    const { stream, data } = event.payload
+    const current = stream === 'stderr' ? stderr : stdout
+    const room = maxOutputChars - current.length
+    if (data.length > room) truncated = true
+    const kept = room > 0 ? data.slice(0, room) : ''
+    if (stream === 'stderr') stderr += kept
+    else stdout += kept
+    onOutput?.(stream, data)
+  }))
+  unlisteners.push(await listen(`shell-done-${sessionId}`, (event) => {
+    resolveDone(event.payload)
+  }))
+
+  const kill = () => invoke('shell_kill', { sessionId }).catch(() => {})
+  if (signal) {
+    if (signal.aborted) {
+      _cleanup()
+      throw new DOMException('The operation was aborted.', 'AbortError')
+    }
+    signal.addEventListener('abort', kill, { once: true })
+  }
+
+  try {
+    // Rejects with { kind: 'policy', decision } when the command policy blocks it
+    await invoke('shell_spawn', {
+      sessionId,
+      cwd,
+      command,
+      options: {
+        timeout_secs: timeoutSecs ?? null,
+        workspace: workspace ?? null,
+        approved: !!approved,
+        max_output_chars: Number.isFinite(maxOutputChars) ? maxOutputChars : null,
+      },
+    })
+    const result = await done
+    return {
+      stdout,
+      stderr,
+      exitCode: result.exit_code,
+      timedOut: result.timed_out,
+      killed: result.killed,
+      truncated: truncated || !!result.truncated,
+    }
+  } finally {
+    signal?.removeEventListener('abort', kill)
+    _cleanup()
+  }
+}
@@ -312,7 +312,7 @@ export const useChatStore = defineStore('chat', () => {
     // Delete persisted file
     const workspace = useWorkspaceStore()
     if (workspace.shouldersDir) {
-      invoke('delete_path', { path: `${workspace.shouldersDir}/chats/${id}.json` }).catch(() => {})
+      invoke('delete_path', { path: `${workspace.shouldersDir}/chats/${id}.json`, hard: true }).catch(() => {})
     }
 
     if (activeSessionId.value === id) {
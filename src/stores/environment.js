@@ -1,5 +1,6 @@
 import { defineStore } from 'pinia'
 import { invoke } from '@tauri-apps/api/core'
+import { useWorkspaceStore } from './workspace'
 
 export const useEnvironmentStore = defineStore('environment', {
   state: () => ({
@@ -147,9 +148,11 @@ export const useEnvironmentStore = defineStore('environment', {
       }
     },
 
-    async _run(cmd) {
+    async _run(cmd, { approved = false } = {}) {
       try {
-        return await invoke('run_shell_command', { cwd: '.', command: cmd })
+        // The sandbox only accepts an absolute cwd once a workspace is open
+        const cwd = useWorkspaceStore().path || '.'
+        return await invoke('run_shell_command', { cwd, command: cmd, approved })
       } catch {
         return ''
       }
@@ -178,7 +181,8 @@ export const useEnvironmentStore = defineStore('environment', {
       }
 
       try {
-        const output = await this._run(cmd)
+        // The user clicked Install, which confirms the network access the command policy asks about
+        const output = await this._run(cmd, { approved: true })
         this.installOutput = output
 
         // Re-detect to update status
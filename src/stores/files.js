@@ -64,6 +64,8 @@ export const useFilesStore = defineStore('files', {
       let accumulatedPaths = new Set()
       const workspace = useWorkspaceStore()
       this.unlisten = await listen('fs-change', async (event) => {
+        // Other windows and external-folder watchers emit on the same event
+        if (event.payload?.watcher_id !== workspace.watcherId) return
         // Filter out internal directories that the app itself writes to —
         // .shoulders/ and .git/ changes never affect the file tree or open
         // editor tabs, so reacting to them is pure waste. Their own stores
@@ -79,7 +81,7 @@ export const useFilesStore = defineStore('files', {
         if (paths.length === 0) return
 
         if (import.meta.env.DEV) {
-          console.debug('[fs-watch]', event.payload?.kind, paths)
+          console.debug('[fs-watch]', event.payload?.changes)
         }
         // Accumulate paths across debounced events so none are lost
         for (const p of paths) accumulatedPaths.add(p)
@@ -423,7 +425,7 @@ export const useFilesStore = defineStore('files', {
     async deletePath(path) {
       try {
         this.deletingPaths.add(path)
-        await invoke('delete_path', { path })
+        const trashed = await invoke('delete_path', { path })
         await this.loadFileTree()
 
         // Close all tabs for the deleted file
@@ -443,6 +445,13 @@ export const useFilesStore = defineStore('files', {
         const { useReviewsStore } = await import('./reviews')
         useReviewsStore().discardAllForFile(path)
 
+        if (trashed) {
+          const { useToastStore } = await import('./toast')
+          useToastStore().show(`Moved '${trashed.name}' to trash`, {
+            action: { label: 'Undo', onClick: () => this.restoreFromTrash(trashed.id) },
+          })
+        }
+
         return true
       } catch (e) {
         console.error('Failed to delete:', e)
@@ -452,6 +461,22 @@ export const useFilesStore = defineStore('files', {
       }
     },
 
+    async restoreFromTrash(id) {
+      const workspace = useWorkspaceStore()
+      if (!workspace.path) return null
+      try {
+        const restoredPath = await invoke('trash_restore', { workspace: workspace.path, id })
+        await this.loadFileTree()
+        return restoredPath
+      } catch (e) {
+        console.error('Failed to restore from trash:', e)
+        const { useToastStore } = await import('./toast')
+        const { formatFileError } = await import('../utils/errorMessages')
+        useToastStore().show(formatFileError('restore', null, e), { type: 'error' })
+        return null
+      }
+    },
+
     cleanup() {
       if (this.unlisten) {
         this.unlisten()
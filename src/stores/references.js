@@ -224,6 +224,7 @@ export const useReferencesStore = defineStore('references', {
       const { listen } = await import('@tauri-apps/api/event')
 
       this._unlisten = await listen('fs-change', async (event) => {
+        if (event.payload?.watcher_id !== workspace.watcherId) return
         const paths = event.payload?.paths || []
         if (paths.some(p => p.includes('library.json'))) {
           if (this._selfWriteCount > 0) {
@@ -328,10 +329,10 @@ export const useReferencesStore = defineStore('references', {
       const workspace = useWorkspaceStore()
       if (workspace.projectDir) {
         if (ref._pdfFile) {
-          invoke('delete_path', { path: `${workspace.projectDir}/references/pdfs/${ref._pdfFile}` }).catch(() => {})
+          invoke('delete_path', { path: `${workspace.projectDir}/references/pdfs/${ref._pdfFile}`, hard: true }).catch(() => {})
         }
         if (ref._textFile) {
-          invoke('delete_path', { path: `${workspace.projectDir}/references/fulltext/${ref._textFile}` }).catch(() => {})
+          invoke('delete_path', { path: `${workspace.projectDir}/references/fulltext/${ref._textFile}`, hard: true }).catch(() => {})
         }
       }
 
@@ -442,7 +443,6 @@ export const useReferencesStore = defineStore('references', {
       if (!workspace.projectDir) return
 
       const pdfsDir = `${workspace.projectDir}/references/pdfs`
-      const textDir = `${workspace.projectDir}/references/fulltext`
       const destPdf = `${pdfsDir}/${key}.pdf`
 
       try {
@@ -452,7 +452,15 @@ export const useReferencesStore = defineStore('references', {
         console.warn('Failed to store PDF:', e)
       }
 
-      // Extract text for full-text search
+      await this.indexPdfText(key, destPdf)
+    },
+
+    // Extract a stored PDF's text into references/fulltext/ for full-text search
+    async indexPdfText(key, destPdf) {
+      const workspace = useWorkspaceStore()
+      if (!workspace.projectDir) return
+      const textDir = `${workspace.projectDir}/references/fulltext`
+
       try {
         const { extractTextFromPdf } = await import('../utils/pdfMetadata')
         const text = await extractTextFromPdf(destPdf)
@@ -75,6 +75,7 @@ export const useReviewsStore = defineStore('reviews', {
       // Watch for changes to pending-edits.json
       if (this.unlisten) this.unlisten()
       this.unlisten = await listen('fs-change', async (event) => {
+        if (event.payload?.watcher_id !== workspace.watcherId) return
         const paths = event.payload?.paths || []
         if (paths.some((p) => p.includes('pending-edits.json'))) {
           // Skip reload for self-triggered writes (accept/reject actions)
@@ -289,7 +290,7 @@ export const useReviewsStore = defineStore('reviews', {
         await invoke('write_file', { path: flagPath, content: '1' })
       } else {
         try {
-          await invoke('delete_path', { path: flagPath })
+          await invoke('delete_path', { path: flagPath, hard: true })
         } catch (e) {
           // File might not exist
         }
@@ -48,6 +48,8 @@ export const useWorkspaceStore = defineStore('workspace', {
     syncConflictBranch: null,
     lastSyncTime: null,
     remoteUrl: '',
+    watcherId: null,     // id from watch_directory; fs-change payloads carry it as watcher_id
+    searchIndexReady: false,  // search_index_build finished; content search can use the FTS index
     syncTimer: null,
     // Skills
     skillsManifest: null,  // Array<{ name, description, path }> | null
@@ -64,6 +66,9 @@ export const useWorkspaceStore = defineStore('workspace', {
     async openWorkspace(path) {
       this.path = path
 
+      // Confine filesystem commands to this workspace (plus ~/.shoulders)
+      await invoke('sandbox_add_root', { path })
+
       // Resolve global config directory (~/.shoulders/)
       try { this.globalConfigDir = await invoke('get_global_config_dir') }
       catch { this.globalConfigDir = '' }
@@ -74,6 +79,9 @@ export const useWorkspaceStore = defineStore('workspace', {
       // Initialize .shoulders directory (private AI state)
       await this.initShouldersDir()
 
+      // Thin out local file history (.shoulders/history) in the background
+      invoke('history_prune', { workspace: path }).catch(() => {})
+
       // Initialize .project directory (public project data)
       await this.initProjectDir()
 
@@ -84,10 +92,18 @@ export const useWorkspaceStore = defineStore('workspace', {
       await this.loadSettings()
 
       // Start file watching
-      await invoke('watch_directory', { path })
+      if (this.watcherId != null) await invoke('unwatch_directory', { id: this.watcherId })
+      this.watcherId = await invoke('watch_directory', { path })
+
+      // Bring the full-text index up to date in the background; the watcher keeps it current after that
+      this.searchIndexReady = false
+      invoke('search_index_build', { workspace: path })
+        .then(() => { if (this.path === path) this.searchIndexReady = true })
+        .catch(e => console.warn('[search-index] build failed:', e))
 
       // Hot-reload _instructions.md on change
       this._instructionsUnlisten = await listen('fs-change', (event) => {
+        if (event.payload?.watcher_id !== this.watcherId) return
         const paths = event.payload?.paths || []
         const instructionsPath = `${this.path}/_instructions.md`
         if (paths.some(p => p === instructionsPath)) {
@@ -247,7 +263,7 @@ When reviewing text:
         if (oldRefsExists) {
           try {
             await invoke('copy_dir', { src: oldRefsDir, dest: `${projectDir}/references` })
-            await invoke('delete_path', { path: oldRefsDir })
+            await invoke('delete_path', { path: oldRefsDir, hard: true })
           } catch (e) {
             console.warn('Failed to migrate references:', e)
           }
@@ -259,7 +275,7 @@ When reviewing text:
         if (oldStylesExists) {
           try {
             await invoke('copy_dir', { src: oldStylesDir, dest: `${projectDir}/styles` })
-            await invoke('delete_path', { path: oldStylesDir })
+            await invoke('delete_path', { path: oldStylesDir, hard: true })
           } catch (e) {
             console.warn('Failed to migrate styles:', e)
           }
@@ -270,7 +286,7 @@ When reviewing text:
         try {
           const pdfContent = await invoke('read_file', { path: oldPdfSettings })
           await invoke('write_file', { path: `${projectDir}/pdf-settings.json`, content: pdfContent })
-          await invoke('delete_path', { path: oldPdfSettings })
+          await invoke('delete_path', { path: oldPdfSettings, hard: true })
         } catch { /* no pdf-settings to migrate */ }
 
         // Migrate citation-style.json
@@ -278,7 +294,7 @@ When reviewing text:
         try {
           const styleContent = await invoke('read_file', { path: oldCitationStyle })
           await invoke('write_file', { path: `${projectDir}/citation-style.json`, content: styleContent })
-          await invoke('delete_path', { path: oldCitationStyle })
+          await invoke('delete_path', { path: oldCitationStyle, hard: true })
         } catch { /* no citation-style to migrate */ }
       }
 
@@ -850,10 +866,11 @@ exit 0
 
         // Check if there are changes to commit
         const status = await gitStatus(this.path)
-        if (status.trim()) {
+        if (status.length > 0) {
           const now = new Date()
           const timestamp = now.toISOString().replace('T', ' ').substring(0, 16)
-          await gitCommit(this.path, `Auto: ${timestamp}`)
+          // Never sign background commits: a pinentry prompt would stall auto-save
+          await gitCommit(this.path, `Auto: ${timestamp}`, { sign: false })
 
           // Auto-push if GitHub is connected
           await this.autoSync()
@@ -1151,7 +1168,12 @@ exit 0
       }
       if (this.path) {
         await this.autoCommit()
-        await invoke('unwatch_directory')
+        if (this.watcherId != null) {
+          await invoke('unwatch_directory', { id: this.watcherId })
+          this.watcherId = null
+          this.searchIndexReady = false
+        }
+        await invoke('sandbox_remove_root', { path: this.path })
       }
     },
   },
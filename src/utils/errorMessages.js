@@ -98,7 +98,9 @@ export function formatFileError(operation, filePath, error) {
   const str = typeof error === 'string' ? error : String(error || '')
 
   let reason = ''
-  if (/permission|denied|access/i.test(str)) {
+  if (error?.kind === 'conflict') {
+    reason = 'it changed on disk'
+  } else if (/permission|denied|access/i.test(str)) {
     reason = 'permission denied'
   } else if (/no such file|not found|does not exist/i.test(str)) {
     reason = 'file not found'